@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use rustpython_parser::ast;
+
+use crate::SqlFinder;
+use crate::finder_types::FinderType;
+use crate::visit::{Visit, walk_expr};
+
+// Constant propagation for plain names. Every module, class and function body gets its own
+// frame of the string literals assigned to names so far, `base + " WHERE active = 1"` then
+// extracts as the full statement. Only assignments at the top level of the body define a
+// constant, anything bound in a branch, loop or block, or bound to something that isn't a
+// literal, drops the name, it's a placeholder again from there on.
+//
+// With dataflow enabled, branches and loops bind constants as well. Whatever a block leaves
+// behind is kept when it only extended the earlier value, `if active: query += " WHERE ..."`
+// checks the query with the condition, and dropped when the ways through it disagree.
+#[derive(Default)]
+pub(crate) struct Frame {
+    values: HashMap<String, String>,
+    nesting: usize,
+}
+
+impl SqlFinder {
+    /// Runs `f` in a frame of its own, names of enclosing scopes aren't visible in it.
+    pub(crate) fn within_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.frames.borrow_mut().push(Frame::default());
+        let result = f();
+        self.frames.borrow_mut().pop();
+        result
+    }
+
+    /// Runs `f` for the nested bodies of a compound statement, which may not run at all.
+    pub(crate) fn within_block<T>(&self, f: impl FnOnce() -> T) -> T {
+        let before = self.values();
+        self.set_nesting(|n| n + 1);
+        let result = f();
+        self.set_nesting(|n| n.saturating_sub(1));
+        if self.config.dataflow() {
+            self.merge_values(&before);
+        }
+        result
+    }
+
+    /// Runs `body` and `orelse` of an `if`, only one of which runs.
+    pub(crate) fn within_branches<T>(
+        &self,
+        body: impl FnOnce() -> T,
+        orelse: impl FnOnce() -> T,
+    ) -> (T, T) {
+        if !self.config.dataflow() {
+            return (body(), orelse());
+        }
+        let before = self.values();
+        let body = body();
+        let after_body = self.values();
+        self.set_values(before);
+        let orelse = orelse();
+        self.merge_values(&after_body);
+        (body, orelse)
+    }
+
+    fn values(&self) -> HashMap<String, String> {
+        self.frames
+            .borrow()
+            .last()
+            .map(|frame| frame.values.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_values(&self, values: HashMap<String, String>) {
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.values = values;
+        }
+    }
+
+    /// Keeps the longer of two values where one extends the other, drops the rest.
+    fn merge_values(&self, other: &HashMap<String, String>) {
+        let mut frames = self.frames.borrow_mut();
+        let Some(frame) = frames.last_mut() else {
+            return;
+        };
+        let names: Vec<String> = frame.values.keys().chain(other.keys()).cloned().collect();
+        for name in names {
+            match (frame.values.get(&name), other.get(&name)) {
+                (Some(a), Some(b)) if a.starts_with(b.as_str()) => {}
+                (Some(a), Some(b)) if b.starts_with(a.as_str()) => {
+                    frame.values.insert(name, b.clone());
+                }
+                _ => {
+                    frame.values.remove(&name);
+                }
+            }
+        }
+    }
+
+    fn set_nesting(&self, f: impl Fn(usize) -> usize) {
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.nesting = f(frame.nesting);
+        }
+    }
+
+    pub(crate) fn constant(&self, name: &str) -> Option<String> {
+        self.frames.borrow().last()?.values.get(name).cloned()
+    }
+
+    /// Records the names `stmt` binds once it was analyzed.
+    pub(crate) fn bind_constants(&self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Assign(a) => {
+                for target in &a.targets {
+                    self.bind(target, Some(&a.value));
+                }
+            }
+            ast::Stmt::AnnAssign(a) => self.bind(&a.target, a.value.as_deref()),
+            ast::Stmt::AugAssign(a) => {
+                let extended = match &*a.target {
+                    ast::Expr::Name(name)
+                        if self.config.dataflow() && a.op == ast::Operator::Add =>
+                    {
+                        self.constant(&name.id)
+                            .zip(self.extract_literal(&a.value))
+                            .map(|(base, fragment)| base + &fragment)
+                    }
+                    _ => None,
+                };
+                self.bind_literal(&a.target, extended);
+            }
+            ast::Stmt::For(ast::StmtFor { target, .. })
+            | ast::Stmt::AsyncFor(ast::StmtAsyncFor { target, .. }) => self.bind(target, None),
+            ast::Stmt::With(ast::StmtWith { items, .. })
+            | ast::Stmt::AsyncWith(ast::StmtAsyncWith { items, .. }) => items
+                .iter()
+                .filter_map(|item| item.optional_vars.as_deref())
+                .for_each(|target| self.bind(target, None)),
+            ast::Stmt::Delete(d) => d.targets.iter().for_each(|t| self.bind(t, None)),
+            ast::Stmt::FunctionDef(ast::StmtFunctionDef { name, .. })
+            | ast::Stmt::AsyncFunctionDef(ast::StmtAsyncFunctionDef { name, .. })
+            | ast::Stmt::ClassDef(ast::StmtClassDef { name, .. }) => self.forget(name),
+            ast::Stmt::Import(ast::StmtImport { names, .. })
+            | ast::Stmt::ImportFrom(ast::StmtImportFrom { names, .. }) => {
+                for alias in names {
+                    self.forget(alias.asname.as_ref().unwrap_or(&alias.name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn bind(&self, target: &ast::Expr, value: Option<&ast::Expr>) {
+        match target {
+            ast::Expr::Name(_) => {
+                let literal = value
+                    .filter(|v| is_string_expr(v))
+                    .and_then(|v| self.extract_literal(v));
+                self.bind_literal(target, literal);
+            }
+            ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+            | ast::Expr::List(ast::ExprList { elts, .. }) => {
+                elts.iter().for_each(|elt| self.bind(elt, None));
+            }
+            ast::Expr::Starred(starred) => self.bind(&starred.value, None),
+            _ => {}
+        }
+    }
+
+    fn bind_literal(&self, target: &ast::Expr, literal: Option<String>) {
+        let ast::Expr::Name(name) = target else {
+            return self.bind(target, None);
+        };
+        let mut frames = self.frames.borrow_mut();
+        let Some(frame) = frames.last_mut() else {
+            return;
+        };
+        match literal {
+            Some(s) if frame.nesting == 0 || self.config.dataflow() => {
+                frame.values.insert(name.id.to_string(), s);
+            }
+            _ => {
+                frame.values.remove(name.id.as_str());
+            }
+        }
+    }
+
+    /// Names `stmt` assigns a value built on their current constant, `query = query + "..."`.
+    pub(crate) fn extended_names(&self, stmt: &ast::Stmt) -> Vec<String> {
+        let (targets, value): (Vec<&ast::Expr>, Option<&ast::Expr>) = match stmt {
+            ast::Stmt::Assign(a) => (a.targets.iter().collect(), Some(&a.value)),
+            ast::Stmt::AnnAssign(a) => (vec![&a.target], a.value.as_deref()),
+            _ => return vec![],
+        };
+        let Some(value) = value else {
+            return vec![];
+        };
+        let mut reads = Reads::default();
+        walk_expr(&mut reads, value);
+
+        targets
+            .into_iter()
+            .filter_map(|target| match target {
+                ast::Expr::Name(name)
+                    if reads.names.contains(name.id.as_str())
+                        && self.constant(&name.id).is_some() =>
+                {
+                    Some(name.id.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn forget(&self, name: &str) {
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.values.remove(name);
+        }
+    }
+
+    fn extract_literal(&self, value: &ast::Expr) -> Option<String> {
+        match self.extract_content(value)? {
+            FinderType::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Reads {
+    names: HashSet<String>,
+}
+
+impl Visit<'_> for Reads {
+    fn enter_expr(&mut self, expr: &ast::Expr) -> bool {
+        if let ast::Expr::Name(name) = expr {
+            self.names.insert(name.id.to_string());
+        }
+        true
+    }
+}
+
+/// Values that can extract to a string, skipping the rest keeps unrelated assignments quiet.
+fn is_string_expr(value: &ast::Expr) -> bool {
+    match value {
+        ast::Expr::Constant(c) => c.value.is_str(),
+        ast::Expr::Call(call) => matches!(&*call.func, ast::Expr::Attribute(_)),
+        ast::Expr::JoinedStr(_) | ast::Expr::BinOp(_) | ast::Expr::Name(_) => true,
+        _ => false,
+    }
+}
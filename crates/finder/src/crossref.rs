@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+
+use rustpython_parser::ast::{self, Ranged};
+
+use crate::visit::{Visit, walk_body};
+use crate::{SqlFinder, SqlString};
+
+// Cross reference pass for dead SQL. Only module level assignments are candidates, and a
+// variable counts as used as soon as its name is read anywhere in the module, be it as a plain
+// name or as an attribute. Anything reachable from outside the module can't be judged from here,
+// so names listed in `__all__` are never reported.
+impl SqlFinder {
+    pub(crate) fn mark_unreferenced(&self, suite: &ast::Suite, strings: &mut [SqlString]) {
+        let mut refs = References::default();
+        walk_body(&mut refs, suite);
+        let exported = exported_names(suite);
+
+        for stmt in suite {
+            let targets: Vec<&ast::Expr> = match stmt {
+                ast::Stmt::Assign(a) => a.targets.iter().collect(),
+                ast::Stmt::AnnAssign(a) => vec![&a.target],
+                _ => continue,
+            };
+            let stmt_range = stmt.range().start().to_usize()..stmt.range().end().to_usize();
+
+            for name in targets.into_iter().flat_map(target_names) {
+                if refs.names.contains(name)
+                    || exported.contains(name)
+                    || (self.config.dead_sql_private_only() && !name.starts_with('_'))
+                {
+                    continue;
+                }
+                strings
+                    .iter_mut()
+                    .filter(|s| {
+                        s.variable_name == name && stmt_range.contains(&s.range.start.byte_offset())
+                    })
+                    .for_each(|s| s.unreferenced = true);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct References {
+    names: HashSet<String>,
+}
+
+impl Visit<'_> for References {
+    fn enter_expr(&mut self, expr: &ast::Expr) -> bool {
+        match expr {
+            ast::Expr::Name(name) if name.ctx == ast::ExprContext::Load => {
+                self.names.insert(name.id.to_string());
+            }
+            ast::Expr::Attribute(attr) if attr.ctx == ast::ExprContext::Load => {
+                self.names.insert(attr.attr.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+fn target_names(target: &ast::Expr) -> Vec<&str> {
+    match target {
+        ast::Expr::Name(name) => vec![name.id.as_str()],
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+        | ast::Expr::List(ast::ExprList { elts, .. }) => {
+            elts.iter().flat_map(target_names).collect()
+        }
+        ast::Expr::Starred(starred) => target_names(&starred.value),
+        _ => vec![],
+    }
+}
+
+fn exported_names(suite: &ast::Suite) -> HashSet<&str> {
+    let is_all =
+        |target: &ast::Expr| matches!(target, ast::Expr::Name(n) if n.id.as_str() == "__all__");
+
+    suite
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ast::Stmt::Assign(a) if a.targets.iter().any(is_all) => Some(&*a.value),
+            ast::Stmt::AugAssign(a) if is_all(&a.target) => Some(&*a.value),
+            ast::Stmt::AnnAssign(a) if is_all(&a.target) => a.value.as_deref(),
+            _ => None,
+        })
+        .filter_map(|value| match value {
+            ast::Expr::List(ast::ExprList { elts, .. })
+            | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => Some(elts),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|elt| match elt {
+            ast::Expr::Constant(c) => c.value.as_str().map(String::as_str),
+            _ => None,
+        })
+        .collect()
+}
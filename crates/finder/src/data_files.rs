@@ -0,0 +1,167 @@
+use yaml_rust2::parser::{Event, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use crate::finder_types::ContextSet;
+
+// SQL kept in YAML and JSON files, like a `query: |` block per report. JSON is read as the YAML
+// it is a subset of. String values under a configured key are SQL, items of a list under it
+// too, and are named by their path in the document, `reports[0].query`.
+
+pub(crate) struct DataString {
+    pub(crate) name: String,
+    pub(crate) content: String,
+    /// Byte offset of the first character of the value
+    pub(crate) offset: usize,
+    /// Byte offset just past the last character of the value, before a closing quote
+    pub(crate) end: usize,
+}
+
+enum Node {
+    Mapping {
+        path: String,
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        key: Option<String>,
+        index: usize,
+    },
+}
+
+/// The SQL strings of a YAML or JSON document, or where and why it fails to parse.
+pub(crate) fn extract_data_strings(
+    src: &str,
+    keys: &ContextSet,
+) -> Result<Vec<DataString>, (usize, String)> {
+    let mut parser = Parser::new_from_str(src);
+    let mut stack: Vec<Node> = vec![];
+    let mut found = vec![];
+
+    loop {
+        let (event, marker) = parser
+            .next_token()
+            .map_err(|e| (char_offset(src, e.marker().index()), e.info().to_string()))?;
+        match event {
+            Event::StreamEnd => break,
+            Event::Scalar(value, style, ..) => {
+                if let Some(Node::Mapping {
+                    key: key @ None, ..
+                }) = stack.last_mut()
+                {
+                    *key = Some(value);
+                    continue;
+                }
+                let (path, key) = child(&mut stack);
+                if key.as_deref().is_some_and(|k| keys.is_match(k)) && !value.trim().is_empty() {
+                    let offset = value_start(src, &marker, style);
+                    found.push(DataString {
+                        name: path,
+                        end: value_end(src, offset, &value, style),
+                        content: value,
+                        offset,
+                    });
+                }
+            }
+            Event::MappingStart(..) => {
+                let (path, _) = child(&mut stack);
+                stack.push(Node::Mapping { path, key: None });
+            }
+            Event::SequenceStart(..) => {
+                let (path, key) = child(&mut stack);
+                stack.push(Node::Sequence {
+                    path,
+                    key,
+                    index: 0,
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                stack.pop();
+            }
+            Event::Alias(_) => {
+                child(&mut stack);
+            }
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Path and innermost key of the next value in the current node, moving past it.
+fn child(stack: &mut [Node]) -> (String, Option<String>) {
+    match stack.last_mut() {
+        Some(Node::Mapping { path, key }) => {
+            let key = key.take().unwrap_or_default();
+            let path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            (path, Some(key))
+        }
+        Some(Node::Sequence { path, key, index }) => {
+            *index += 1;
+            (format!("{path}[{}]", *index - 1), key.clone())
+        }
+        None => (String::new(), None),
+    }
+}
+
+/// Where the text of a scalar starts, past the quote or on the line after a block indicator.
+fn value_start(src: &str, marker: &Marker, style: TScalarStyle) -> usize {
+    let start = char_offset(src, marker.index());
+    match style {
+        TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => start + 1,
+        TScalarStyle::Literal | TScalarStyle::Folded => {
+            let skip_whitespace = |pos: usize| src.len() - src[pos..].trim_start().len();
+            let start = skip_whitespace(start);
+            if !src[start..].starts_with(['|', '>']) {
+                return start;
+            }
+            src[start..]
+                .find('\n')
+                .map_or(start, |line_end| skip_whitespace(start + line_end))
+        }
+        TScalarStyle::Plain => start,
+    }
+}
+
+/// Where the text of a scalar starting at `start` ends. Quoted ones end at their closing quote,
+/// the others are followed through the source word by word as folding and indentation make
+/// their text differ from the value.
+fn value_end(src: &str, start: usize, value: &str, style: TScalarStyle) -> usize {
+    match style {
+        TScalarStyle::DoubleQuoted | TScalarStyle::SingleQuoted => {
+            let quote = if style == TScalarStyle::DoubleQuoted {
+                '"'
+            } else {
+                '\''
+            };
+            let mut chars = src[start..].char_indices().peekable();
+            while let Some((i, ch)) = chars.next() {
+                match ch {
+                    '\\' if quote == '"' => {
+                        chars.next();
+                    }
+                    // `''` is a quote within a single quoted value
+                    '\'' if quote == '\''
+                        && chars.peek().is_some_and(|&(_, next)| next == '\'') =>
+                    {
+                        chars.next();
+                    }
+                    _ if ch == quote => return start + i,
+                    _ => {}
+                }
+            }
+            src.len()
+        }
+        _ => value.split_whitespace().fold(start, |end, word| {
+            src[end..].find(word).map_or(end, |i| end + i + word.len())
+        }),
+    }
+}
+
+pub(crate) fn char_offset(src: &str, index: usize) -> usize {
+    src.char_indices()
+        .nth(index)
+        .map_or(src.len(), |(offset, _)| offset)
+}
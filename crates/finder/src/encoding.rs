@@ -0,0 +1,62 @@
+use std::sync::LazyLock;
+
+use encoding_rs::{Encoding, UTF_8};
+use logging::{debug, warn};
+use regex::bytes::Regex;
+
+// PEP 263, a comment like `# -*- coding: latin-1 -*-` or `# vim: set fileencoding=cp1251 :`
+// on one of the first two lines declares the encoding of the file. SQL files do the same with
+// `-- encoding: latin-1`.
+static CODING_COOKIE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?-u)^[ \t\x0c]*(?:#|--).*?coding[:=][ \t]*([-\w.]+)")
+        .expect("cookie pattern is valid")
+});
+
+/// Decodes the bytes of a source file. A coding declaration is followed, anything else has to
+/// be UTF-8, invalid sequences are replaced then. Returns the encoding the text has to be
+/// encoded with again to get at offsets of the original file, `None` for UTF-8.
+pub(crate) fn decode_source(bytes: Vec<u8>, path: &str) -> (String, Option<&'static Encoding>) {
+    let declared = bytes
+        .split(|&b| b == b'\n')
+        .take(2)
+        .find_map(|line| CODING_COOKIE.captures(line))
+        .and_then(|caps| {
+            let label = String::from_utf8_lossy(&caps[1]).to_lowercase();
+            let encoding = encoding_for(&label);
+            if encoding.is_none() {
+                warn!("'{path}' declares the unknown encoding '{label}', reading it as UTF-8");
+            }
+            encoding
+        })
+        .filter(|&encoding| encoding != UTF_8);
+
+    if let Some(encoding) = declared {
+        debug!("Decoding '{path}' as {}", encoding.name());
+        let (text, _, malformed) = encoding.decode(&bytes);
+        if malformed {
+            warn!(
+                "'{path}' isn't valid {}, invalid sequences were replaced",
+                encoding.name()
+            );
+        }
+        return (text.into_owned(), Some(encoding));
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            warn!(
+                "'{path}' isn't valid UTF-8 and declares no encoding, invalid sequences were replaced"
+            );
+            (String::from_utf8_lossy(e.as_bytes()).into_owned(), None)
+        }
+    }
+}
+
+/// Encoding of a Python codec name. The WHATWG labels `encoding_rs` knows cover most of them,
+/// Python spells some with a separator the labels don't have, `latin-1` or `cp_1251`.
+fn encoding_for(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+        .or_else(|| Encoding::for_label(label.replace('_', "-").as_bytes()))
+        .or_else(|| Encoding::for_label(label.replace(['-', '_'], "").as_bytes()))
+}
@@ -0,0 +1,127 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::preanalysis::{ByteRange, PreanalyzedFile};
+use crate::{SqlFinder, SqlString};
+
+// `name = `, `obj.attr: str = ` at the start of a line, the literal itself is read by hand.
+static ASSIGNMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^[ \t]*([A-Za-z_][\w.]*)[ \t]*(?::[^=\n]*)?=[ \t]*").unwrap()
+});
+static CALL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([A-Za-z_][\w.]*)\([ \t]*").unwrap());
+
+// Degraded mode for files rustpython can't parse (newer syntax, stray errors). Only
+// assignments and calls with a plain string literal are recognized, everything found
+// here is flagged as approximate.
+impl SqlFinder {
+    pub(crate) fn analyze_approximate(&self, range_file: &PreanalyzedFile) -> Vec<SqlString> {
+        let src = range_file.src();
+
+        let assignments = ASSIGNMENT_RE.captures_iter(src).filter_map(|caps| {
+            let target = caps.get(1)?.as_str();
+            let name = target.rsplit('.').next()?;
+            if !self.config.is_sql_variable_name(name) {
+                return None;
+            }
+            let value_start = caps.get(0)?.end();
+            let (content, len) = read_plain_literal(&src[value_start..])?;
+            Some((name.to_string(), value_start, value_start + len, content))
+        });
+
+        let calls = CALL_RE.captures_iter(src).filter_map(|caps| {
+            let func = caps.get(1)?;
+            let line_start = src[..func.start()].rfind('\n').map_or(0, |i| i + 1);
+            if src[line_start..func.start()].contains('#')
+                || !self.config.is_sql_function_name(func.as_str())
+            {
+                return None;
+            }
+            let literal_start = caps.get(0)?.end();
+            let (content, len) = read_plain_literal(&src[literal_start..])?;
+            self.config.is_context_sql_str(&content).then(|| {
+                (
+                    func.as_str().to_string(),
+                    func.start(),
+                    literal_start + len,
+                    content,
+                )
+            })
+        });
+
+        let mut found: Vec<_> = assignments
+            .chain(calls)
+            .filter(|(_, start, _, _)| !range_file.is_disabled_at(*start))
+            .collect();
+        found.sort_by_key(|(_, start, _, _)| *start);
+
+        found
+            .into_iter()
+            .map(|(variable_name, start, end, sql_content)| SqlString {
+                variable_name,
+                sql_content,
+                range: range_file.byterange_to_range(ByteRange::new(start, end)),
+                approximate: true,
+                dynamic_spans: vec![],
+                unreferenced: false,
+                unextractable: false,
+                suppressed: None,
+                dialect: None,
+                content_start: None,
+                appended: false,
+                extends: false,
+                params: None,
+                interpolation: None,
+            })
+            .collect()
+    }
+}
+
+/// Reads a non-formatted string literal at the very start of `src` and returns its value with
+/// the length of the literal, prefix and quotes included.
+fn read_plain_literal(src: &str) -> Option<(String, usize)> {
+    let prefix_len = src.find(['"', '\''])?;
+    let prefix = &src[..prefix_len];
+    if prefix.len() > 2 || !prefix.chars().all(|c| matches!(c, 'r' | 'R' | 'u' | 'U')) {
+        return None;
+    }
+    let raw = prefix.contains(['r', 'R']);
+
+    let quoted = &src[prefix_len..];
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|q| quoted.starts_with(q))?;
+    let body = &quoted[quote.len()..];
+
+    let mut content = String::new();
+    let mut chars = body.char_indices();
+    while let Some((idx, ch)) = chars.next() {
+        if body[idx..].starts_with(quote) {
+            return Some((content, prefix_len + 2 * quote.len() + idx));
+        }
+        match ch {
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                match escaped {
+                    _ if raw => {
+                        content.push('\\');
+                        content.push(escaped);
+                    }
+                    'n' => content.push('\n'),
+                    't' => content.push('\t'),
+                    'r' => content.push('\r'),
+                    '\n' => {}
+                    '\\' | '\'' | '"' => content.push(escaped),
+                    _ => {
+                        content.push('\\');
+                        content.push(escaped);
+                    }
+                }
+            }
+            '\n' if quote.len() == 1 => return None,
+            _ => content.push(ch),
+        }
+    }
+    None
+}
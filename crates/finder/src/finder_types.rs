@@ -1,12 +1,13 @@
 use std::ops::{Add, Div, Mul, Sub};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::{env, fmt};
 
-use globset::{Glob, GlobSet, GlobSetBuilder};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
 use logging::{always_log, error};
-use regex::Regex;
+use regex::{Regex, RegexSet, RegexSetBuilder};
 
-use crate::preanalysis::ByteRange;
+use crate::preanalysis::{ByteRange, LineCol, PreanalyzedFile, Suppression};
 
 // Internal result type for processing
 #[derive(Debug, Clone)]
@@ -14,38 +15,143 @@ pub struct SqlResult {
     pub byte_range: ByteRange,
     pub variable_name: String,
     pub content: FinderType,
+    pub params: Option<CallParams>,
+    pub interpolation: Option<Interpolation>,
+}
+
+/// How the SQL passed to a function context was put together in Python.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// `f"... {value}"`
+    Fstring,
+    /// `"... {}".format(value)` and `Template(...).substitute(...)`
+    Format,
+    /// `"... %s" % value`
+    Percent,
+    /// `"... " + value` and `str.join`
+    Concat,
+}
+
+/// Literal parameters passed along with the SQL, `cursor.execute(sql, (a, b))`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CallParams {
+    /// A tuple or list with this many values
+    Positional(usize),
+    /// A dict with these keys
+    Named(Vec<String>),
+    /// The rows of `executemany`, each a set of parameters of its own
+    Rows(Vec<CallParams>),
+}
+
+/// A call the finder matched but left alone on purpose, reported by `--explain-extraction`.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub function_name: String,
+    pub reason: String,
+    pub range: crate::preanalysis::Range,
 }
 
 #[derive(Debug, Clone)]
 pub struct SqlExtract {
     pub file_path: String,
     pub strings: Vec<SqlString>,
+    /// Path relative to the project root (the working directory) with forward slashes.
     pub rel_path: String,
+    /// The file's source and line index, shared with everything that renders diagnostics.
+    pub source: Arc<PreanalyzedFile>,
+    /// Calls skipped on purpose, only recorded when extraction is explained.
+    pub explanations: Vec<Explanation>,
 }
 
 impl SqlExtract {
-    pub fn new(file_path: String, strings: Vec<SqlString>) -> Self {
-        let cwd = env::current_dir().expect("Can't get cwd, this is likely a permissions issue.");
-        let cwd_name = cwd
-            .file_name()
-            .expect("Current directory should have a name");
-
-        let relative_part = Path::new(&file_path)
-            .strip_prefix(&cwd)
-            .expect("Should always be able to strip prefix cwd from curr_file.");
-
-        let mut full_rel_path = PathBuf::new();
-        full_rel_path.push(cwd_name);
-        full_rel_path.push(relative_part);
-
-        let rel_path = full_rel_path.to_string_lossy().to_string();
+    pub fn new(file_path: String, strings: Vec<SqlString>, source: Arc<PreanalyzedFile>) -> Self {
+        let rel_path = project_path(&file_path);
 
         Self {
             file_path,
             strings,
             rel_path,
+            source,
+            explanations: vec![],
         }
     }
+
+    #[must_use]
+    pub fn with_explanations(mut self, explanations: Vec<Explanation>) -> Self {
+        self.explanations = explanations;
+        self
+    }
+
+    /// `rel_path` with the platform's separators, for human readable output.
+    #[must_use]
+    pub fn display_path(&self) -> String {
+        display_path(&self.rel_path)
+    }
+}
+
+/// The project root, the working directory the config is read from, canonicalized like the
+/// paths of the files found in it.
+#[must_use]
+pub fn project_root() -> Option<PathBuf> {
+    env::current_dir().and_then(|cwd| cwd.canonicalize()).ok()
+}
+
+/// `file_path` relative to the project root with forward slashes, the `rel_path` of its extract.
+#[must_use]
+pub fn project_path(file_path: &str) -> String {
+    relative_path(Path::new(file_path), &project_root().unwrap_or_default())
+}
+
+/// A path from `project_path` with the platform's separators, for human readable output. Files
+/// without an extract are shown the same way as the others.
+#[must_use]
+pub fn display_path(rel_path: &str) -> String {
+    if cfg!(windows) {
+        rel_path.replace('/', "\\")
+    } else {
+        rel_path.to_string()
+    }
+}
+
+/// Why a file produced no extract. A file without SQL is not an error, it yields an empty extract.
+#[derive(Debug, thiserror::Error)]
+pub enum AnalyzeError {
+    #[error("Failed to read file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to parse Python file '{path}' at {location} {message}")]
+    PythonParse {
+        path: String,
+        message: String,
+        location: LineCol,
+    },
+    #[error("Failed to parse data file '{path}' at {location} {message}")]
+    DataParse {
+        path: String,
+        message: String,
+        location: LineCol,
+    },
+    /// The JSON of a notebook, its code cells are parsed like any other Python
+    #[error("Failed to parse notebook '{path}' at {location} {message}")]
+    NotebookParse {
+        path: String,
+        message: String,
+        location: LineCol,
+    },
+}
+
+/// `file` relative to `root` with forward slashes. Files outside of `root` keep their full path.
+#[must_use]
+pub fn relative_path(file: &Path, root: &Path) -> String {
+    let path = file.strip_prefix(root).unwrap_or(file).to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    }
 }
 
 /// Represents a detected SQL variable
@@ -54,6 +160,44 @@ pub struct SqlString {
     pub variable_name: String,
     pub sql_content: String,
     pub range: crate::preanalysis::Range,
+    /// Found by the line based fallback instead of the AST, so the range is only a best guess
+    /// and rules relying on exact positions should skip it. Its content is never mapped back
+    /// to the source, findings within it have no position or fix of their own.
+    pub approximate: bool,
+    /// Parts of `sql_content` that stand in for values only known at runtime.
+    pub dynamic_spans: Vec<DynamicSpan>,
+    /// Assigned at module level but never read anywhere else in the module.
+    pub unreferenced: bool,
+    /// A matched variable or call whose value couldn't be extracted statically, it has no
+    /// content and only stands for a blind spot of the finder.
+    pub unextractable: bool,
+    /// Diagnostics the `# sqint: ignore` pragmas of its statement suppress. The string is still
+    /// checked, only matching findings are dropped when reporting.
+    pub suppressed: Option<Suppression>,
+    /// Dialect named by a `# sqint: dialect=...` pragma of its statement or file, in place of
+    /// the configured one. Unknown names are left for the analyzer to report.
+    pub dialect: Option<String>,
+    /// Source offset `sql_content` starts at, when it's the verbatim body of a literal in
+    /// `range`. Strings with escapes, placeholders or joined parts can't be mapped back.
+    pub content_start: Option<usize>,
+    /// Fragment of a `+=` that still has to be joined with the earlier value of its variable.
+    pub(crate) appended: bool,
+    /// Built on the earlier value of its variable, `query = query + "..."` or a joined `+=`.
+    pub(crate) extends: bool,
+    /// Parameters passed to the call the SQL was found in, when they're a literal.
+    pub params: Option<CallParams>,
+    /// How SQL passed straight to a function context was built, when interpolation detection
+    /// is enabled. Whether runtime values went into it is up to `dynamic_spans`.
+    pub interpolation: Option<Interpolation>,
+}
+
+/// Byte range of `sql_content` that was filled in with a placeholder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicSpan {
+    pub start: usize,
+    pub end: usize,
+    /// The runtime value was built with `str.join`, which usually means a list of values.
+    pub joined: bool,
 }
 
 impl SqlString {
@@ -66,6 +210,211 @@ impl SqlString {
             variable_name,
             sql_content,
             range,
+            approximate: false,
+            dynamic_spans: vec![],
+            unreferenced: false,
+            unextractable: false,
+            suppressed: None,
+            dialect: None,
+            content_start: None,
+            appended: false,
+            extends: false,
+            params: None,
+            interpolation: None,
+        }
+    }
+
+    pub fn is_suppressed(&self, code: &str) -> bool {
+        self.suppressed.as_ref().is_some_and(|s| s.covers(code))
+    }
+
+    pub(crate) fn suppress(&mut self, suppression: Option<&Suppression>) {
+        if let Some(suppression) = suppression {
+            self.suppressed = Some(match self.suppressed.take() {
+                Some(current) => current.merge(suppression),
+                None => suppression.clone(),
+            });
+        }
+    }
+
+    /// Byte range of the source `range` covers.
+    pub const fn byte_range(&self) -> ByteRange {
+        ByteRange::new(self.range.start.byte_offset(), self.range.end.byte_offset())
+    }
+
+    /// Source position of a 1 based line and column (in chars) of `sql_content`, like the
+    /// `Location` of a parser error. `None` when the content can't be mapped back or the
+    /// position lies outside of it.
+    pub fn source_position(
+        &self,
+        source: &PreanalyzedFile,
+        line: usize,
+        col: usize,
+    ) -> Option<LineCol> {
+        let start = self.content_start?;
+        let mut offset = 0;
+        for text in self.sql_content.split('\n').take(line.checked_sub(1)?) {
+            offset += text.len() + 1;
+        }
+        let text = self.sql_content.get(offset..)?.split('\n').next()?;
+        let in_line = text
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(text.len()))
+            .nth(col.checked_sub(1)?)?;
+        Some(source.offset_to_linecol(start + offset + in_line))
+    }
+
+    /// Source range from a byte offset into `sql_content` to the end of the string, for
+    /// findings about a part of it. `None` when the content can't be mapped back.
+    pub fn content_range(
+        &self,
+        source: &PreanalyzedFile,
+        offset: usize,
+    ) -> Option<crate::preanalysis::Range> {
+        let start = self.content_start? + offset.min(self.sql_content.len());
+        Some(source.byterange_to_range(ByteRange::new(
+            start,
+            self.range.end.byte_offset().max(start),
+        )))
+    }
+
+    /// Source byte range of `range` of `sql_content`, for rewriting that part of the literal
+    /// in place. `None` when the content can't be mapped back, the range touches a runtime
+    /// part, or the literal is a raw string whose text is best left as written.
+    pub fn rewritable_range(
+        &self,
+        source: &PreanalyzedFile,
+        range: std::ops::Range<usize>,
+    ) -> Option<ByteRange> {
+        let start = self.content_start?;
+        self.sql_content.get(range.clone())?;
+        if self
+            .dynamic_spans
+            .iter()
+            .any(|span| span.start < range.end && range.start < span.end)
+        {
+            return None;
+        }
+        let raw = source
+            .src()
+            .get(..start)?
+            .trim_end_matches(['"', '\''])
+            .chars()
+            .rev()
+            .take_while(char::is_ascii_alphabetic)
+            .any(|c| c.eq_ignore_ascii_case(&'r'));
+        if raw {
+            return None;
+        }
+        Some(ByteRange::new(start + range.start, start + range.end))
+    }
+
+    /// The statements of a string holding several, separated by semicolons outside of literals
+    /// and comments. Each starts where its statement does in the literal when the content can
+    /// be mapped back, and at the string otherwise. A single statement comes back as it is.
+    #[must_use]
+    pub fn split_statements(&self, source: &PreanalyzedFile) -> Vec<Self> {
+        let statements = crate::sql_script::split_statements(&self.sql_content);
+        if self.unextractable || statements.len() < 2 {
+            return vec![self.clone()];
+        }
+        statements
+            .into_iter()
+            .map(|statement| {
+                let (start, end) = (statement.range.start(), statement.range.end());
+                let mut piece = self.clone();
+                piece.sql_content = statement.content(&self.sql_content);
+                // The parameters are those of all statements together
+                piece.params = None;
+                piece.dynamic_spans = self
+                    .dynamic_spans
+                    .iter()
+                    .filter(|span| span.start >= start && span.end <= end)
+                    .map(|span| DynamicSpan {
+                        start: span.start - start,
+                        end: span.end - start,
+                        ..*span
+                    })
+                    .collect();
+                if let Some(content_start) = self.content_start {
+                    piece.content_start = Some(content_start + start);
+                    piece.range = source.byterange_to_range(ByteRange::new(
+                        content_start + start,
+                        content_start + end,
+                    ));
+                }
+                piece
+            })
+            .collect()
+    }
+
+    /// Finds `sql_content` as the body of a literal within `range` of `src`.
+    pub(crate) fn locate_content(&mut self, src: &str) {
+        if self.unextractable || self.sql_content.is_empty() {
+            return;
+        }
+        let start = self.range.start.byte_offset();
+        let Some(covered) = src.get(start..self.range.end.byte_offset()) else {
+            return;
+        };
+        self.content_start = covered
+            .match_indices(self.sql_content.as_str())
+            .find(|(idx, _)| {
+                let after = idx + self.sql_content.len();
+                covered[..*idx].ends_with(['"', '\'']) && covered[after..].starts_with(['"', '\''])
+            })
+            .map(|(idx, _)| start + idx);
+    }
+
+    /// Takes `dialect` unless a pragma closer to the string named one already.
+    pub(crate) fn override_dialect(&mut self, dialect: Option<&str>) {
+        if self.dialect.is_none() {
+            self.dialect = dialect.map(String::from);
+        }
+    }
+
+    /// Builds a `SqlString` from content rendered by `FinderType`, swapping the placeholder
+    /// markers for `PLACEHOLDER` and remembering where each of them ended up.
+    pub(crate) fn from_rendered(
+        variable_name: String,
+        rendered: &str,
+        range: crate::preanalysis::Range,
+    ) -> Self {
+        let mut sql_content = String::with_capacity(rendered.len());
+        let mut dynamic_spans = vec![];
+        for ch in rendered.chars() {
+            let joined = match ch {
+                PLACEHOLDER_MARKER => false,
+                JOINED_PLACEHOLDER_MARKER => true,
+                _ => {
+                    sql_content.push(ch);
+                    continue;
+                }
+            };
+            let start = sql_content.len();
+            sql_content.push_str(PLACEHOLDER);
+            dynamic_spans.push(DynamicSpan {
+                start,
+                end: sql_content.len(),
+                joined,
+            });
+        }
+        Self {
+            variable_name,
+            sql_content,
+            range,
+            approximate: false,
+            dynamic_spans,
+            unreferenced: false,
+            unextractable: false,
+            suppressed: None,
+            dialect: None,
+            content_start: None,
+            appended: false,
+            extends: false,
+            params: None,
+            interpolation: None,
         }
     }
     fn truncate_content(&self, len: usize) -> &str {
@@ -77,56 +426,608 @@ impl SqlString {
     }
 }
 
+/// Logging and print calls, excluded from extraction unless configured otherwise.
+pub const DEFAULT_EXCLUDED_FUNCTION_CONTEXTS: [&str; 5] =
+    ["print", "logging.*", "logger.*", "*.logger.*", "log.*"];
+
+/// Calls that only wrap their SQL argument, like SQLAlchemy's `text()`.
+pub const DEFAULT_PASSTHROUGH_WRAPPERS: [&str; 4] =
+    ["text", "sa.text", "sqlalchemy.text", "dedent"];
+
+/// Leading keywords of strings passed to function contexts that are taken for SQL.
+pub const DEFAULT_SQL_KEYWORDS: [&str; 12] = [
+    "select", "insert", "update", "delete", "create", "drop", "alter", "truncate", "with",
+    "explain", "show", "describe",
+];
+
+/// Comments naming the statement below them in raw SQL files, as written for aiosql and sqlc,
+/// `-- name: get_user_by_id :one`. The first capture group is the name.
+pub const DEFAULT_STATEMENT_NAME_PATTERN: &str = r"^--\s*name:\s*([A-Za-z_][\w.-]*)";
+
+/// Argument of a function context that holds the SQL, `execute:0` or `run_query:sql`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ArgSpec {
+    Position(usize),
+    Keyword(String),
+}
+
+impl ArgSpec {
+    fn parse(spec: &str) -> Option<Self> {
+        if let Ok(position) = spec.parse() {
+            return Some(Self::Position(position));
+        }
+        let is_identifier = spec
+            .chars()
+            .next()
+            .is_some_and(|c| c == '_' || c.is_alphabetic())
+            && spec.chars().all(|c| c == '_' || c.is_alphanumeric());
+        is_identifier.then(|| Self::Keyword(spec.to_string()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FinderConfig {
-    variable_ctx: GlobSet,
-    func_ctx: GlobSet,
+    variable_ctx: ContextSet,
+    func_ctx: ContextSet,
+    /// Function contexts without an argument spec, all of their arguments are extracted.
+    unrestricted_func_ctx: ContextSet,
+    func_args: Vec<(ContextSet, ArgSpec)>,
+    excluded_func_ctx: ContextSet,
+    return_ctx: ContextSet,
+    class_ctx: ContextSet,
+    keyword_ctx: ContextSet,
+    passthrough_wrappers: ContextSet,
+    templated_sql_files: ContextSet,
+    data_file_keys: Vec<(ContextSet, ContextSet)>,
     sql_regex: Regex,
+    statement_name_regex: Option<Regex>,
+    fallback_extraction: bool,
+    dead_sql_detection: bool,
+    dead_sql_private_only: bool,
+    interpolation_detection: bool,
+    assert_comparisons: bool,
+    module_literals: bool,
+    dataflow: bool,
+    suffix_matching: bool,
+    min_sql_length: usize,
+    case_sensitive: bool,
+    require_sql_keyword: bool,
+    report_unextractable: bool,
+    unnamed_literals: bool,
+    respect_noqa: bool,
+    explain_extraction: bool,
 }
 
 impl FinderConfig {
+    /// Function contexts may name the argument holding the SQL, `execute:0` extracts only the
+    /// first positional argument of `execute`, `run_query:sql` only its `sql` keyword. Regex
+    /// contexts can't, a `:` in them is part of the regex.
+    /// `case_sensitive` applies to the patterns of every context, `*sql*` matches
+    /// `SQL_STATEMENT` when it's false.
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
-    pub fn new(variable_ctx: &[String], func_ctx: &[String]) -> Self {
+    pub fn new(variable_ctx: &[String], func_ctx: &[String], case_sensitive: bool) -> Self {
+        let mut patterns = vec![];
+        let mut unrestricted = vec![];
+        let mut func_args = vec![];
+        for entry in func_ctx {
+            let is_regex = entry
+                .trim_start_matches(EXCLUSION_PREFIX)
+                .starts_with(REGEX_PREFIX);
+            match entry
+                .rsplit_once(':')
+                .filter(|_| !is_regex)
+                .and_then(|(pattern, spec)| Some((pattern, ArgSpec::parse(spec)?)))
+            {
+                Some((pattern, spec)) => {
+                    let pattern = pattern.to_string();
+                    func_args.push((
+                        slice_to_contexts(
+                            std::slice::from_ref(&pattern),
+                            "function_contexts",
+                            case_sensitive,
+                        ),
+                        spec,
+                    ));
+                    patterns.push(pattern);
+                }
+                None => {
+                    patterns.push(entry.clone());
+                    unrestricted.push(entry.clone());
+                }
+            }
+        }
+
         Self {
-            variable_ctx: slice_to_glob(variable_ctx, "variable_contexts"),
-            func_ctx: slice_to_glob(func_ctx, "function_contexts"),
-            sql_regex: Regex::new(r"(?i)^\s*(select|insert|update|delete|create|drop|alter|truncate|with|explain|show|describe)\b").unwrap(),
+            variable_ctx: slice_to_contexts(variable_ctx, "variable_contexts", case_sensitive),
+            func_ctx: slice_to_contexts(&patterns, "function_contexts", case_sensitive),
+            unrestricted_func_ctx: slice_to_contexts(
+                &unrestricted,
+                "function_contexts",
+                case_sensitive,
+            ),
+            func_args,
+            excluded_func_ctx: slice_to_contexts(
+                &DEFAULT_EXCLUDED_FUNCTION_CONTEXTS.map(String::from),
+                "excluded_function_contexts",
+                case_sensitive,
+            ),
+            return_ctx: slice_to_contexts(&[], "return_contexts", case_sensitive),
+            class_ctx: slice_to_contexts(&[], "class_contexts", case_sensitive),
+            keyword_ctx: slice_to_contexts(&[], "keyword_contexts", case_sensitive),
+            passthrough_wrappers: slice_to_contexts(
+                &DEFAULT_PASSTHROUGH_WRAPPERS.map(String::from),
+                "passthrough_wrappers",
+                case_sensitive,
+            ),
+            templated_sql_files: slice_to_contexts(&[], "templated_sql_file_patterns", true),
+            data_file_keys: vec![],
+            sql_regex: sql_keyword_regex(&DEFAULT_SQL_KEYWORDS.map(String::from)),
+            statement_name_regex: Some(Regex::new(DEFAULT_STATEMENT_NAME_PATTERN).unwrap()),
+            fallback_extraction: true,
+            dead_sql_detection: false,
+            dead_sql_private_only: false,
+            interpolation_detection: false,
+            assert_comparisons: false,
+            module_literals: false,
+            dataflow: false,
+            suffix_matching: true,
+            min_sql_length: 0,
+            case_sensitive,
+            require_sql_keyword: true,
+            report_unextractable: false,
+            unnamed_literals: false,
+            respect_noqa: false,
+            explain_extraction: false,
         }
     }
+
+    /// Function names never extracted from, even when they match a function context. Defaults
+    /// to logging and `print` calls, which mention SQL rather than run it.
+    #[must_use]
+    pub fn with_excluded_function_contexts(mut self, excluded: &[String]) -> Self {
+        self.excluded_func_ctx =
+            slice_to_contexts(excluded, "excluded_function_contexts", self.case_sensitive);
+        self
+    }
+
+    /// Functions whose returned strings are SQL, e.g. `get_*_query`. Empty by default.
+    #[must_use]
+    pub fn with_return_contexts(mut self, return_ctx: &[String]) -> Self {
+        self.return_ctx = slice_to_contexts(return_ctx, "return_contexts", self.case_sensitive);
+        self
+    }
+
+    /// Classes whose string members are SQL whatever they're named, e.g. `*Queries`. Empty by
+    /// default.
+    #[must_use]
+    pub fn with_class_contexts(mut self, class_ctx: &[String]) -> Self {
+        self.class_ctx = slice_to_contexts(class_ctx, "class_contexts", self.case_sensitive);
+        self
+    }
+
+    /// Keyword arguments that are SQL in any call, whatever the function is named, e.g.
+    /// `statement` for `run(statement="SELECT ...")`. Empty by default.
+    #[must_use]
+    pub fn with_keyword_contexts(mut self, keyword_ctx: &[String]) -> Self {
+        self.keyword_ctx = slice_to_contexts(keyword_ctx, "keyword_contexts", self.case_sensitive);
+        self
+    }
+
+    /// Calls whose first argument is extracted as if the call wasn't there, so
+    /// `execute(text("SELECT ..."))` is attributed to `execute`. Defaults to
+    /// `DEFAULT_PASSTHROUGH_WRAPPERS`.
+    #[must_use]
+    pub fn with_passthrough_wrappers(mut self, wrappers: &[String]) -> Self {
+        self.passthrough_wrappers =
+            slice_to_contexts(wrappers, "passthrough_wrappers", self.case_sensitive);
+        self
+    }
+
+    /// Paths of raw SQL files that are Jinja templates, like dbt models. Their `{{ ... }}`
+    /// expressions become placeholders and `{% ... %}` tags are dropped before splitting.
+    /// Empty by default.
+    #[must_use]
+    pub fn with_templated_sql_file_patterns(mut self, patterns: &[String]) -> Self {
+        self.templated_sql_files = slice_to_contexts(patterns, "templated_sql_file_patterns", true);
+        self
+    }
+
+    /// YAML and JSON files holding SQL, by path pattern with the keys whose string values are
+    /// SQL, `("reports/*.yaml", ["query"])`. Empty by default.
+    #[must_use]
+    pub fn with_data_file_keys(mut self, data_files: &[(String, Vec<String>)]) -> Self {
+        self.data_file_keys = data_files
+            .iter()
+            .map(|(pattern, keys)| {
+                (
+                    slice_to_contexts(std::slice::from_ref(pattern), "sql_in_data_files", true),
+                    slice_to_contexts(keys, "sql_in_data_files", true),
+                )
+            })
+            .collect();
+        self
+    }
+
+    /// Whether files that fail to parse are scanned line by line instead of being skipped.
+    #[must_use]
+    pub const fn with_fallback_extraction(mut self, enabled: bool) -> Self {
+        self.fallback_extraction = enabled;
+        self
+    }
+
+    /// Whether module level SQL variables that are never read get flagged as unreferenced.
+    #[must_use]
+    pub const fn with_dead_sql_detection(mut self, enabled: bool) -> Self {
+        self.dead_sql_detection = enabled;
+        self
+    }
+
+    /// Restricts dead SQL detection to underscore prefixed names.
+    #[must_use]
+    pub const fn with_dead_sql_private_only(mut self, enabled: bool) -> Self {
+        self.dead_sql_private_only = enabled;
+        self
+    }
+
+    /// Whether SQL passed to function contexts records how it was interpolated, an f-string,
+    /// `str.format()`, `%` formatting or concatenation.
+    #[must_use]
+    pub const fn with_interpolation_detection(mut self, enabled: bool) -> Self {
+        self.interpolation_detection = enabled;
+        self
+    }
+
+    /// Whether string literals compared against in `assert` statements are checked, regardless
+    /// of variable and function contexts.
+    #[must_use]
+    pub const fn with_assert_comparisons(mut self, enabled: bool) -> Self {
+        self.assert_comparisons = enabled;
+        self
+    }
+
+    /// Whether bare string statements at module level are checked when they look like SQL.
+    /// Docstrings never are.
+    #[must_use]
+    pub const fn with_module_literals(mut self, enabled: bool) -> Self {
+        self.module_literals = enabled;
+        self
+    }
+
+    /// Whether a query built up over several statements is reported once with its final value,
+    /// assignments in branches and loops included, instead of every intermediate string.
+    #[must_use]
+    pub const fn with_dataflow(mut self, enabled: bool) -> Self {
+        self.dataflow = enabled;
+        self
+    }
+
+    /// Whether function contexts also match the trailing parts of a dotted call, so `execute`
+    /// and `cursor.execute` match `self.db.cursor.execute`. On by default, off matches the full
+    /// path only.
+    #[must_use]
+    pub const fn with_suffix_matching(mut self, enabled: bool) -> Self {
+        self.suffix_matching = enabled;
+        self
+    }
+
+    /// Keywords a string passed to a function context has to start with to be taken for SQL,
+    /// after leading whitespace and comments. Replaces `DEFAULT_SQL_KEYWORDS`.
+    #[must_use]
+    pub fn with_sql_keywords(mut self, keywords: &[String]) -> Self {
+        self.sql_regex = sql_keyword_regex(keywords);
+        self
+    }
+
+    /// Pattern of the comments above a statement of a raw SQL file naming it, its first capture
+    /// group or the whole match is the name. Defaults to `DEFAULT_STATEMENT_NAME_PATTERN`,
+    /// `None` numbers every statement.
+    #[must_use]
+    pub fn with_statement_name_pattern(mut self, pattern: Option<Regex>) -> Self {
+        self.statement_name_regex = pattern;
+        self
+    }
+
+    /// Whether strings passed to function contexts need a leading SQL keyword. Off extracts
+    /// every string of a matched call, fragments like `"WHERE tenant_id = %s"` included.
+    #[must_use]
+    pub const fn with_require_sql_keyword(mut self, enabled: bool) -> Self {
+        self.require_sql_keyword = enabled;
+        self
+    }
+
+    /// Extracts every string constant that looks like SQL regardless of names, reported as
+    /// `<literal>`. Docstrings and arguments of excluded functions are skipped.
+    #[must_use]
+    pub const fn with_unnamed_literals(mut self, enabled: bool) -> Self {
+        self.unnamed_literals = enabled;
+        self
+    }
+
+    /// Also yields matched variables and calls whose value can't be extracted, like
+    /// `query = build_query(user_input)`, as `unextractable` strings without content.
+    #[must_use]
+    pub const fn with_unextractable_reporting(mut self, enabled: bool) -> Self {
+        self.report_unextractable = enabled;
+        self
+    }
+
+    /// Records why matched calls were skipped, like calls to `print` or logging excluded by
+    /// `excluded_function_contexts`, as the `explanations` of the extract.
+    #[must_use]
+    pub const fn with_explain_extraction(mut self, enabled: bool) -> Self {
+        self.explain_extraction = enabled;
+        self
+    }
+
+    /// Treats flake8 style `# noqa` comments like `# sqint: ignore`. Scoped ones only count
+    /// with sqint's own codes, `# noqa: E501` suppresses nothing.
+    #[must_use]
+    pub const fn with_respect_noqa(mut self, enabled: bool) -> Self {
+        self.respect_noqa = enabled;
+        self
+    }
+
+    /// Strings shorter than this after trimming aren't extracted, `""` or a lone `"SELECT"`
+    /// only make noise in the parser. 0 disables the limit.
+    #[must_use]
+    pub const fn with_min_sql_length(mut self, min_sql_length: usize) -> Self {
+        self.min_sql_length = min_sql_length;
+        self
+    }
+
+    pub(crate) const fn fallback_extraction(&self) -> bool {
+        self.fallback_extraction
+    }
+    pub(crate) const fn dead_sql_detection(&self) -> bool {
+        self.dead_sql_detection
+    }
+    pub(crate) const fn interpolation_detection(&self) -> bool {
+        self.interpolation_detection
+    }
+    pub(crate) const fn dead_sql_private_only(&self) -> bool {
+        self.dead_sql_private_only
+    }
+    pub(crate) const fn assert_comparisons(&self) -> bool {
+        self.assert_comparisons
+    }
+    pub(crate) const fn module_literals(&self) -> bool {
+        self.module_literals
+    }
+    pub(crate) const fn dataflow(&self) -> bool {
+        self.dataflow
+    }
+    pub(crate) const fn unnamed_literals(&self) -> bool {
+        self.unnamed_literals
+    }
+    pub(crate) const fn report_unextractable(&self) -> bool {
+        self.report_unextractable
+    }
+    pub(crate) const fn respect_noqa(&self) -> bool {
+        self.respect_noqa
+    }
+    pub(crate) const fn explain_extraction(&self) -> bool {
+        self.explain_extraction
+    }
     pub(crate) fn is_sql_variable_name(&self, name: &str) -> bool {
         self.variable_ctx.is_match(name)
     }
 
+    pub(crate) fn is_excluded_function_name(&self, name: &str) -> bool {
+        self.excluded_func_ctx.is_match(name)
+    }
+
+    /// An exclusion matching any layer of the name beats patterns matching the others.
     pub(crate) fn is_sql_function_name(&self, name: &str) -> bool {
-        self.func_ctx.is_match(name)
+        !self.is_excluded_function_name(name)
+            && self
+                .function_name_layers(name)
+                .any(|layer| self.func_ctx.is_match(layer))
+            && !self
+                .function_name_layers(name)
+                .any(|layer| self.func_ctx.is_excluded(layer))
+    }
+
+    /// `self.db.cursor.execute` is tried as a whole, then `db.cursor.execute`, `cursor.execute`
+    /// and `execute`. The longest one matching a function context wins.
+    fn function_name_layers<'a>(&self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        let suffixes = name
+            .match_indices('.')
+            .map(move |(i, _)| &name[i + 1..])
+            .filter(|_| self.suffix_matching);
+        std::iter::once(name).chain(suffixes)
+    }
+
+    /// The arguments of a matched function that hold SQL, `None` when all of them may.
+    pub(crate) fn sql_arguments(&self, name: &str) -> Option<Vec<&ArgSpec>> {
+        for layer in self.function_name_layers(name) {
+            if self.unrestricted_func_ctx.is_match(layer) {
+                return None;
+            }
+            let specs: Vec<&ArgSpec> = self
+                .func_args
+                .iter()
+                .filter(|(patterns, _)| patterns.is_match(layer))
+                .map(|(_, spec)| spec)
+                .collect();
+            if !specs.is_empty() {
+                return Some(specs);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn is_return_context(&self, name: &str) -> bool {
+        self.return_ctx.is_match(name)
+    }
+
+    pub(crate) fn is_class_context(&self, name: &str) -> bool {
+        self.class_ctx.is_match(name)
+    }
+
+    pub(crate) fn is_keyword_context(&self, name: &str) -> bool {
+        self.keyword_ctx.is_match(name)
+    }
+
+    pub(crate) fn is_passthrough_wrapper(&self, name: &str) -> bool {
+        self.passthrough_wrappers.is_match(name)
+    }
+
+    pub(crate) fn is_templated_sql(&self, path: &str) -> bool {
+        self.templated_sql_files.is_match(path)
+    }
+
+    /// Keys holding SQL in the data file at `path`, `None` when it isn't one.
+    pub(crate) fn data_file_keys(&self, path: &str) -> Option<&ContextSet> {
+        self.data_file_keys
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(path))
+            .map(|(_, keys)| keys)
+    }
+
+    /// Name given to a statement by one of the comments above it.
+    pub(crate) fn statement_name(&self, comments: &[&str]) -> Option<String> {
+        let regex = self.statement_name_regex.as_ref()?;
+        comments.iter().find_map(|comment| {
+            let captures = regex.captures(comment)?;
+            let name = captures.get(1).or_else(|| captures.get(0))?;
+            Some(name.as_str().to_string())
+        })
     }
 
     pub(crate) fn is_sql_str(&self, input: &str) -> bool {
         self.sql_regex.is_match(input)
     }
+
+    /// `is_sql_str` for strings of a matched context, which don't need a keyword at all when
+    /// `require_sql_keyword` is off.
+    pub(crate) fn is_context_sql_str(&self, input: &str) -> bool {
+        !self.require_sql_keyword || self.is_sql_str(input)
+    }
+
+    pub(crate) fn is_long_enough(&self, content: &FinderType) -> bool {
+        self.min_sql_length == 0
+            || content.to_string().trim().chars().count() >= self.min_sql_length
+    }
 }
 
-fn slice_to_glob(patterns: &[String], log_ctx: &str) -> GlobSet {
-    let valid_globs = patterns
+/// Matches strings starting with one of `keywords`, `-- ...` and `/* ... */` comments before
+/// it are skipped.
+fn sql_keyword_regex(keywords: &[String]) -> Regex {
+    let alternatives: Vec<String> = keywords
         .iter()
-        .filter_map(|pattern| match Glob::new(pattern) {
-            Ok(glob) => Some(glob),
-            Err(e) => {
-                always_log!("Failed to parse {log_ctx} glob pattern '{pattern}': {e}");
-                None
-            }
-        });
+        .map(|k| regex::escape(k.trim()))
+        .filter(|k| !k.is_empty())
+        .collect();
+    if alternatives.is_empty() {
+        // Nothing is SQL without keywords
+        return Regex::new(r"[^\s\S]").unwrap();
+    }
+    Regex::new(&format!(
+        r"(?is)^(?:\s+|--[^\n]*(?:\n|$)|/\*.*?\*/)*(?:{})\b",
+        alternatives.join("|")
+    ))
+    .unwrap()
+}
 
+fn build_glob(pattern: &str, case_sensitive: bool) -> Result<Glob, globset::Error> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+}
+
+/// Patterns of a context, globs and the `re:` prefixed regexes. A name matching either of them
+/// matches the context, unless it also matches one of the `!` prefixed exclusions.
+#[derive(Debug, Clone)]
+pub(crate) struct ContextSet {
+    globs: GlobSet,
+    regexes: RegexSet,
+    excluded: Option<Box<ContextSet>>,
+}
+
+impl ContextSet {
+    pub(crate) fn is_match(&self, name: &str) -> bool {
+        (self.globs.is_match(name) || self.regexes.is_match(name)) && !self.is_excluded(name)
+    }
+
+    pub(crate) fn is_excluded(&self, name: &str) -> bool {
+        self.excluded.as_ref().is_some_and(|e| e.is_match(name))
+    }
+}
+
+fn slice_to_contexts(patterns: &[String], log_ctx: &str, case_sensitive: bool) -> ContextSet {
+    let (excluded, patterns): (Vec<&String>, Vec<&String>) = patterns
+        .iter()
+        .partition(|p| p.starts_with(EXCLUSION_PREFIX));
+    let excluded: Vec<String> = excluded
+        .into_iter()
+        .map(|p| p[EXCLUSION_PREFIX.len()..].to_string())
+        .collect();
+    let (regexes, globs): (Vec<&String>, Vec<&String>) = patterns
+        .into_iter()
+        .partition(|p| p.starts_with(REGEX_PREFIX));
+
+    let valid_globs =
+        globs
+            .into_iter()
+            .filter_map(|pattern| match build_glob(pattern, case_sensitive) {
+                Ok(glob) => Some(glob),
+                Err(e) => {
+                    always_log!("Failed to parse {log_ctx} glob pattern '{pattern}': {e}");
+                    None
+                }
+            });
     let builder = valid_globs.fold(GlobSetBuilder::new(), |mut builder, glob| {
         builder.add(glob);
         builder
     });
-
-    builder.build().unwrap_or_else(|e| {
+    let globs = builder.build().unwrap_or_else(|e| {
         error!("Failed to build GlobSet for {log_ctx}: {e}");
         GlobSetBuilder::new().build().unwrap()
-    })
+    });
+
+    let valid_regexes = regexes.into_iter().filter_map(|pattern| {
+        let regex = &pattern[REGEX_PREFIX.len()..];
+        match Regex::new(regex) {
+            Ok(_) => Some(regex),
+            Err(e) => {
+                always_log!("Failed to parse {log_ctx} regex pattern '{regex}': {e}");
+                None
+            }
+        }
+    });
+    let regexes = RegexSetBuilder::new(valid_regexes)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .unwrap_or_else(|e| {
+            error!("Failed to build RegexSet for {log_ctx}: {e}");
+            RegexSet::empty()
+        });
+
+    ContextSet {
+        globs,
+        regexes,
+        excluded: (!excluded.is_empty())
+            .then(|| Box::new(slice_to_contexts(&excluded, log_ctx, case_sensitive))),
+    }
+}
+
+/// Context entries starting with this are regexes, `re:^(get|set)_.*_sql$`.
+const REGEX_PREFIX: &str = "re:";
+/// Context entries starting with this exclude names, `!*query_params*`. Exclusions beat any
+/// pattern of the same list.
+const EXCLUSION_PREFIX: &str = "!";
+
+const PLACEHOLDER: &str = "PLACEHOLDER";
+// Private use characters stand in for placeholders while strings are being put together, so
+// their positions survive formatting and concatenation and can be recovered at the end.
+const PLACEHOLDER_MARKER: char = '\u{E000}';
+const JOINED_PLACEHOLDER_MARKER: char = '\u{E001}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderKind {
+    Value,
+    Joined,
 }
 
 #[derive(Debug, Clone)]
@@ -136,7 +1037,7 @@ pub enum FinderType {
     Float(f64),
     Bool(bool),
     Tuple(Vec<FinderType>),
-    Placeholder,
+    Placeholder(PlaceholderKind),
 }
 
 impl FinderType {
@@ -147,7 +1048,7 @@ impl FinderType {
         }
     }
     pub fn is_placeholder(&self) -> bool {
-        matches!(self, Self::Placeholder)
+        matches!(self, Self::Placeholder(_))
     }
 }
 
@@ -169,7 +1070,10 @@ impl std::fmt::Display for FinderType {
                 }
                 write!(f, ")")
             }
-            Self::Placeholder => write!(f, "PLACEHOLDER"),
+            Self::Placeholder(PlaceholderKind::Value) => write!(f, "{PLACEHOLDER_MARKER}"),
+            Self::Placeholder(PlaceholderKind::Joined) => {
+                write!(f, "{JOINED_PLACEHOLDER_MARKER}")
+            }
         }
     }
 }
@@ -178,7 +1082,10 @@ impl Add for FinderType {
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Placeholder, _) | (_, Self::Placeholder) => Some(Self::Placeholder),
+            // Keep the literal parts around a dynamic segment, the marker records where it was
+            (Self::Str(s), p @ Self::Placeholder(_)) => Some(Self::Str(s + &p.to_string())),
+            (p @ Self::Placeholder(_), Self::Str(s)) => Some(Self::Str(p.to_string() + &s)),
+            (Self::Placeholder(k), _) | (_, Self::Placeholder(k)) => Some(Self::Placeholder(k)),
             (Self::Str(s1), Self::Str(s2)) => Some(Self::Str(s1 + &s2)),
             (Self::Int(s1), Self::Int(s2)) => Some(Self::Int(s1 + &s2)),
             (Self::Float(f1), Self::Float(f2)) => Some(Self::Float(f1 + f2)),
@@ -196,7 +1103,7 @@ impl Sub for FinderType {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Placeholder, _) | (_, Self::Placeholder) => Some(Self::Placeholder),
+            (Self::Placeholder(k), _) | (_, Self::Placeholder(k)) => Some(Self::Placeholder(k)),
             (Self::Float(f1), Self::Float(f2)) => Some(Self::Float(f1 - f2)),
             (Self::Int(s1), Self::Int(s2)) => {
                 if let (Ok(i1), Ok(i2)) = (s1.parse::<i64>(), s2.parse::<i64>()) {
@@ -215,7 +1122,7 @@ impl Mul for FinderType {
 
     fn mul(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Placeholder, _) | (_, Self::Placeholder) => Some(Self::Placeholder),
+            (Self::Placeholder(k), _) | (_, Self::Placeholder(k)) => Some(Self::Placeholder(k)),
             (Self::Float(f1), Self::Float(f2)) => Some(Self::Float(f1 * f2)),
             (Self::Int(s1), Self::Int(s2)) => {
                 if let (Ok(i1), Ok(i2)) = (s1.parse::<i64>(), s2.parse::<i64>()) {
@@ -238,7 +1145,7 @@ impl Div for FinderType {
 
     fn div(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Placeholder, _) | (_, Self::Placeholder) => Some(Self::Placeholder),
+            (Self::Placeholder(k), _) | (_, Self::Placeholder(k)) => Some(Self::Placeholder(k)),
             (Self::Float(f1), Self::Float(f2)) => {
                 if f2.is_normal() {
                     Some(Self::Float(f1 / f2))
@@ -247,16 +1154,33 @@ impl Div for FinderType {
                 }
             }
 
+            // True division like Python's `/`, `//` is `floor_div`
             (Self::Int(s1), Self::Int(s2)) => {
-                if let (Ok(i1), Ok(i2)) = (s1.parse::<i64>(), s2.parse::<i64>()) {
-                    if i2 == 0 {
-                        Some(Self::Int((i1 / i2).to_string()))
-                    } else {
-                        None
-                    }
+                let (i1, i2) = (s1.parse::<i64>().ok()?, s2.parse::<i64>().ok()?);
+                (i2 != 0).then(|| Self::Float(i1 as f64 / i2 as f64))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FinderType {
+    /// Python's `//`, rounding towards negative infinity.
+    pub fn floor_div(self, rhs: Self) -> Option<Self> {
+        match (self, rhs) {
+            (Self::Placeholder(k), _) | (_, Self::Placeholder(k)) => Some(Self::Placeholder(k)),
+            (Self::Float(f1), Self::Float(f2)) => {
+                f2.is_normal().then(|| Self::Float((f1 / f2).floor()))
+            }
+            (Self::Int(s1), Self::Int(s2)) => {
+                let (i1, i2) = (s1.parse::<i64>().ok()?, s2.parse::<i64>().ok()?);
+                let quotient = i1.checked_div(i2)?;
+                let floored = if i1 % i2 != 0 && (i1 < 0) != (i2 < 0) {
+                    quotient - 1
                 } else {
-                    None
-                }
+                    quotient
+                };
+                Some(Self::Int(floored.to_string()))
             }
             _ => None,
         }
@@ -7,68 +7,276 @@
 use logging::bail_with;
 use regex::Regex;
 
-use crate::finder_types::FinderType;
+use crate::finder_types::{FinderType, PlaceholderKind};
 
+/// `textwrap.dedent`: removes the leading spaces and tabs common to every line that isn't
+/// blank. Tabs and spaces don't match each other, and blank lines are emptied.
+pub fn dedent(text: &str) -> String {
+    let is_indent = |c: char| c == ' ' || c == '\t';
+    let is_blank = |line: &str| line.chars().all(is_indent);
+
+    let margin = text
+        .split('\n')
+        .filter(|line| !is_blank(line))
+        .map(|line| &line[..line.len() - line.trim_start_matches(is_indent).len()])
+        .reduce(|margin, indent| {
+            let common = margin
+                .bytes()
+                .zip(indent.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            &margin[..common]
+        })
+        .unwrap_or("");
+
+    text.split('\n')
+        .map(|line| {
+            if is_blank(line) {
+                ""
+            } else {
+                line.strip_prefix(margin).unwrap_or(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+enum FormatSegment {
+    Literal(String),
+    /// `{}`
+    Auto,
+    /// `{0}`
+    Index(usize),
+    /// `{name}`
+    Keyword(String),
+    /// Attribute or item access, a conversion or a format spec, `{0.name}` or `{limit:>10}`
+    Complex,
+}
+
+/// `str.format` replacement fields filled in from the literal arguments of the call. Fields
+/// without a value, out of range indices included, become placeholders. Mixing `{}` with `{0}`
+/// is a `ValueError` in Python, every field of such a string is a placeholder. `{{` and `}}`
+/// are literal braces.
+pub fn substitute_format_fields(
+    text: &str,
+    args: &[String],
+    kwargs: &[(String, String)],
+) -> String {
+    let segments = format_segments(text);
+    let auto = segments.iter().any(|s| matches!(s, FormatSegment::Auto));
+    let manual = segments
+        .iter()
+        .any(|s| matches!(s, FormatSegment::Index(_)));
+
+    let placeholder = FinderType::Placeholder(PlaceholderKind::Value).to_string();
+    let mut next_auto = 0;
+    let mut result = String::with_capacity(text.len());
+    for segment in &segments {
+        let value = match segment {
+            FormatSegment::Literal(s) => Some(s),
+            _ if auto && manual => None,
+            FormatSegment::Auto => {
+                next_auto += 1;
+                args.get(next_auto - 1)
+            }
+            FormatSegment::Index(i) => args.get(*i),
+            FormatSegment::Keyword(name) => kwargs.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+            FormatSegment::Complex => None,
+        };
+        result.push_str(value.unwrap_or(&placeholder));
+    }
+    result
+}
+
+fn format_segments(text: &str) -> Vec<FormatSegment> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                literal.push(c);
+            }
+            '{' => {
+                // Format specs can nest fields, `{value:{width}}`
+                let mut field = String::new();
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' if depth == 1 => break,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    field.push(c);
+                }
+                segments.push(FormatSegment::Literal(std::mem::take(&mut literal)));
+                segments.push(if field.is_empty() {
+                    FormatSegment::Auto
+                } else if let Ok(index) = field.parse() {
+                    FormatSegment::Index(index)
+                } else if field.chars().all(|c| c == '_' || c.is_alphanumeric()) {
+                    FormatSegment::Keyword(field)
+                } else {
+                    FormatSegment::Complex
+                });
+            }
+            _ => literal.push(c),
+        }
+    }
+    segments.push(FormatSegment::Literal(literal));
+    segments
+}
+
+/// `string.Template` substitution of `$name` and `${name}`, names without a value become
+/// placeholders. `$$` is a literal dollar sign.
+pub fn substitute_template(template: &str, values: &[(String, FinderType)]) -> Option<String> {
+    let re =
+        Regex::new(r"\$(?:(\$)|([_a-zA-Z][_a-zA-Z0-9]*)|\{([_a-zA-Z][_a-zA-Z0-9]*)\})").ok()?;
+    let placeholder = FinderType::Placeholder(PlaceholderKind::Value);
+
+    let result = re.replace_all(template, |caps: &regex::Captures| {
+        if caps.get(1).is_some() {
+            return "$".to_string();
+        }
+        let name = caps
+            .get(2)
+            .or_else(|| caps.get(3))
+            .map_or("", |m| m.as_str());
+        values
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map_or(&placeholder, |(_, value)| value)
+            .to_string()
+    });
+    Some(result.into_owned())
+}
+
+/// printf style `%` formatting. Specifiers follow Python's grammar, `%[(key)][flags][width]
+/// [.precision][length]type`, where a `*` width or precision takes the next positional argument.
+/// Values only known at runtime are rendered as placeholders, unpadded.
 pub fn format_python_string(
     format_str: &str,
     args: &[FinderType],
     kwargs: &[(String, FinderType)],
 ) -> Option<String> {
     let re = Regex::new(
-        r"%\(([^)]+)\)[-+0 #]*(?:\*|\d+)?(?:\.(?:\*|\d+))?[hlL]?[sdifgGeEoxXcubp%]|%[-+0 #]*(?:\*|\d+)?(?:\.(?:\*|\d+))?[hlL]?[sdifgGeEoxXcubp%]",
-    ).ok()?;
-    let mut result = format_str.to_string();
-    let mut value_index = 0;
-    let matches: Vec<_> = re.find_iter(format_str).collect();
+        r"%(?:\(([^)]*)\))?([-+0 #]*)(\*|\d+)?(?:\.(\*|\d*))?[hlL]?([diouxXeEfFgGcrsabp%])",
+    )
+    .ok()?;
+    let mut args = args.iter();
+    let mut result = String::with_capacity(format_str.len());
+    let mut last_end = 0;
 
-    for m in matches.iter().rev() {
-        let specifier = m.as_str();
+    for caps in re.captures_iter(format_str) {
+        let specifier = caps.get(0)?;
+        result.push_str(&format_str[last_end..specifier.start()]);
+        last_end = specifier.end();
 
-        if specifier == "%%" {
-            result.replace_range(m.range(), "%");
+        let conv = caps[5].chars().next()?;
+        if conv == '%' {
+            result.push('%');
             continue;
         }
+        let flags = caps.get(2).map_or("", |f| f.as_str());
+        let width = specifier_number(caps.get(3).map(|m| m.as_str()), &mut args)?;
+        let precision = specifier_number(caps.get(4).map(|m| m.as_str()), &mut args)?;
 
-        let (value, conv) = if specifier.starts_with("%(") {
-            // Named format specifier like %(name)s
-            let key_end = specifier.find(')')?;
-            let key = &specifier[2..key_end]; // Extract key between %( and )
-            let conv = specifier.chars().last()?;
+        let value = match caps.get(1) {
+            Some(key) => kwargs
+                .iter()
+                .find(|(k, _)| k == key.as_str())
+                .map(|(_, v)| v)?,
+            None => args.next()?,
+        };
+        if matches!(value, FinderType::Placeholder(_)) {
+            result.push_str(&value.to_string());
+            continue;
+        }
 
-            // Find the value in kwargs slice
-            let value = kwargs.iter().find(|(k, _)| k == key).map(|(_, v)| v)?;
-            (value, conv)
-        } else {
-            // Positional format specifier like %s, %d, etc.
-            if value_index >= args.len() {
-                return None;
+        let rendered = match conv {
+            's' | 'a' => {
+                let s = value.to_string();
+                match precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None => s,
+                }
             }
-            let value = &args[args.len() - 1 - value_index];
-            value_index += 1;
-            let conv = specifier.chars().last()?;
-            (value, conv)
+            'r' => match value {
+                FinderType::Str(s) => format!("'{s}'"),
+                other => other.to_string(),
+            },
+            'd' | 'i' => format_value_as_int(value)?,
+            'u' => format_value_as_unsigned(value)?,
+            'b' => format_value_as_binary(value)?,
+            'f' | 'F' => format_value_as_float(value, precision)?,
+            'g' | 'G' => format_value_as_general(value, precision, conv == 'G')?,
+            'e' | 'E' => format_value_as_scientific(value, precision, conv == 'E')?,
+            'o' => alternate_form(format_value_as_octal(value)?, "0o", flags),
+            'x' => alternate_form(format_value_as_hex(value, false)?, "0x", flags),
+            'X' => alternate_form(format_value_as_hex(value, true)?, "0X", flags),
+            'c' => format_value_as_char(value)?,
+            'p' => format_value_as_pointer(value)?,
+            _ => return bail_with!(None, "Unhandled format conversion specifier: {}", conv),
         };
+        let numeric = !matches!(conv, 's' | 'a' | 'r' | 'c');
+        result.push_str(&pad_formatted(rendered, width, flags, numeric));
+    }
+    result.push_str(&format_str[last_end..]);
 
-        let replacement = match conv {
-            's' => Some(value.to_string()),
-            'd' | 'i' => format_value_as_int(value),
-            'u' => format_value_as_unsigned(value),
-            'b' => format_value_as_binary(value),
-            'f' | 'F' => format_value_as_float(value, specifier),
-            'g' | 'G' => format_value_as_general(value, specifier),
-            'e' | 'E' => format_value_as_scientific(value, specifier),
-            'o' => format_value_as_octal(value),
-            'x' => format_value_as_hex(value, false),
-            'X' => format_value_as_hex(value, true),
-            'c' => format_value_as_char(value),
-            'p' => format_value_as_pointer(value),
-            _ => bail_with!(None, "Unhandled format conversion specifier: {}", conv),
-        };
+    Some(result)
+}
 
-        result.replace_range(m.range(), &replacement?);
+/// Width or precision of a specifier, `*` takes the next positional argument. `Some(None)` when
+/// there is none, or a `*` argument that isn't a literal number.
+fn specifier_number<'a>(
+    spec: Option<&str>,
+    args: &mut impl Iterator<Item = &'a FinderType>,
+) -> Option<Option<usize>> {
+    match spec {
+        None => Some(None),
+        Some("*") => Some(args.next()?.to_string().parse().ok()),
+        // `%.f` is precision 0
+        Some(n) => Some(Some(n.parse().unwrap_or(0))),
     }
+}
 
-    Some(result)
+fn alternate_form(digits: String, prefix: &str, flags: &str) -> String {
+    if !flags.contains('#') {
+        return digits;
+    }
+    match digits.strip_prefix('-') {
+        Some(abs) => format!("-{prefix}{abs}"),
+        None => format!("{prefix}{digits}"),
+    }
+}
+
+/// Applies sign flags and pads to `width`, zero padding goes between the sign and the digits.
+fn pad_formatted(mut rendered: String, width: Option<usize>, flags: &str, numeric: bool) -> String {
+    if numeric && !rendered.starts_with('-') {
+        if flags.contains('+') {
+            rendered.insert(0, '+');
+        } else if flags.contains(' ') {
+            rendered.insert(0, ' ');
+        }
+    }
+    let len = rendered.chars().count();
+    let Some(fill) = width.and_then(|w| w.checked_sub(len)).filter(|f| *f > 0) else {
+        return rendered;
+    };
+
+    if flags.contains('-') {
+        rendered + &" ".repeat(fill)
+    } else if flags.contains('0') && numeric {
+        let sign_len = usize::from(rendered.starts_with(['+', '-', ' ']));
+        rendered.insert_str(sign_len, &"0".repeat(fill));
+        rendered
+    } else {
+        " ".repeat(fill) + &rendered
+    }
 }
 
 fn format_value_as_unsigned(value: &FinderType) -> Option<String> {
@@ -90,9 +298,12 @@ fn format_value_as_binary(value: &FinderType) -> Option<String> {
     }
 }
 
-fn format_value_as_general(value: &FinderType, specifier: &str) -> Option<String> {
-    let precision = extract_precision(specifier).unwrap_or(6);
-    let uppercase = specifier.contains('G');
+fn format_value_as_general(
+    value: &FinderType,
+    precision: Option<usize>,
+    uppercase: bool,
+) -> Option<String> {
+    let precision = precision.unwrap_or(6).max(1);
 
     match value {
         FinderType::Float(f) => Some(format_general_float(*f, precision, uppercase)),
@@ -120,11 +331,14 @@ fn format_general_float(f: f64, precision: usize, uppercase: bool) -> String {
     };
 
     if exponent < -4 || exponent >= precision as i32 {
-        if uppercase {
-            format!("{:.prec$E}", f, prec = precision.saturating_sub(1))
+        let formatted = format!("{:.prec$e}", f, prec = precision.saturating_sub(1));
+        let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+        let mantissa = if mantissa.contains('.') {
+            mantissa.trim_end_matches('0').trim_end_matches('.')
         } else {
-            format!("{:.prec$e}", f, prec = precision.saturating_sub(1))
-        }
+            mantissa
+        };
+        python_exponent(&format!("{mantissa}e{exponent}"), uppercase)
     } else {
         let formatted = format!(
             "{:.prec$}",
@@ -145,8 +359,8 @@ fn format_general_float(f: f64, precision: usize, uppercase: bool) -> String {
     }
 }
 
-fn format_value_as_float(value: &FinderType, specifier: &str) -> Option<String> {
-    let precision = extract_precision(specifier).unwrap_or(6);
+fn format_value_as_float(value: &FinderType, precision: Option<usize>) -> Option<String> {
+    let precision = precision.unwrap_or(6);
     match value {
         FinderType::Float(f) => Some(format!("{f:.precision$}")),
         FinderType::Int(i) => i.parse::<f64>().ok().map(|f| format!("{f:.precision$}")),
@@ -168,14 +382,6 @@ fn format_value_as_pointer(value: &FinderType) -> Option<String> {
     }
 }
 
-fn extract_precision(specifier: &str) -> Option<usize> {
-    specifier.find('.').and_then(|dot_pos| {
-        let after_dot = &specifier[dot_pos + 1..];
-        after_dot
-            .find(|c: char| c.is_alphabetic())
-            .and_then(|end| after_dot[..end].parse().ok())
-    })
-}
 fn format_value_as_int(value: &FinderType) -> Option<String> {
     match value {
         FinderType::Int(i) => Some(i.clone()),
@@ -213,54 +419,41 @@ fn format_value_as_hex(value: &FinderType, uppercase: bool) -> Option<String> {
         _ => bail_with!(None, "Unhandled hex value formatting: {value}"),
     }
 }
-fn format_value_as_scientific(value: &FinderType, specifier: &str) -> Option<String> {
-    let precision = extract_precision(specifier).unwrap_or(6);
-    let uppercase = specifier.contains('E');
+fn format_value_as_scientific(
+    value: &FinderType,
+    precision: Option<usize>,
+    uppercase: bool,
+) -> Option<String> {
+    let precision = precision.unwrap_or(6);
+    let f = match value {
+        FinderType::Float(f) => *f,
+        FinderType::Int(i) => i.parse::<f64>().ok()?,
+        FinderType::Bool(b) => f64::from(u8::from(*b)),
+        FinderType::Str(s) => s.parse::<f64>().ok()?,
+        _ => bail_with!(None, "Unhandled scientific value formatting: {value}")?,
+    };
+    Some(python_exponent(&format!("{f:.precision$e}"), uppercase))
+}
 
-    match value {
-        FinderType::Float(f) => {
-            if uppercase {
-                Some(format!("{f:.precision$E}"))
-            } else {
-                Some(format!("{f:.precision$e}"))
-            }
-        }
-        FinderType::Int(i) => i.parse::<f64>().ok().map(|f| {
-            if uppercase {
-                format!("{f:.precision$E}")
-            } else {
-                format!("{f:.precision$e}")
-            }
-        }),
-        FinderType::Bool(b) => {
-            let val = if *b { 1.0 } else { 0.0 };
-            if uppercase {
-                Some(format!("{val:.precision$E}"))
-            } else {
-                Some(format!("{val:.precision$e}"))
-            }
-        }
-        FinderType::Str(s) => s.parse::<f64>().ok().map(|f| {
-            if uppercase {
-                format!("{f:.precision$E}")
-            } else {
-                format!("{f:.precision$e}")
-            }
-        }),
-        _ => bail_with!(None, "Unhandled scientific value formatting: {value}"),
-    }
+/// Rust renders `1.2e3`, Python `1.2e+03`.
+fn python_exponent(rendered: &str, uppercase: bool) -> String {
+    let Some((mantissa, exponent)) = rendered.split_once('e') else {
+        return rendered.to_string();
+    };
+    let (sign, digits) = exponent
+        .strip_prefix('-')
+        .map_or(("+", exponent), |digits| ("-", digits));
+    let e = if uppercase { 'E' } else { 'e' };
+    format!("{mantissa}{e}{sign}{digits:0>2}")
 }
 
 fn format_value_as_char(value: &FinderType) -> Option<String> {
     match value {
-        FinderType::Int(i) => {
-            if let Ok(code) = i.parse::<u32>() {
-                if let Some(ch) = char::from_u32(code) {
-                    return Some(ch.to_string());
-                }
-            }
-            None
-        }
+        FinderType::Int(i) => i
+            .parse::<u32>()
+            .ok()
+            .and_then(char::from_u32)
+            .map(|ch| ch.to_string()),
         FinderType::Str(s) => {
             if s.len() == 1 {
                 Some(s.clone())
@@ -1,189 +1,387 @@
+mod constants;
+mod crossref;
+mod data_files;
+mod encoding;
+mod fallback;
 mod finder_types;
 mod format;
+mod literals;
+mod notebook;
 pub mod preanalysis;
+mod sql_script;
+mod templating;
 mod tests;
 mod traversal;
-pub use crate::finder_types::{FinderConfig, SqlExtract, SqlString};
-use logging::{bail_with, error};
+mod visit;
+pub use crate::finder_types::{
+    AnalyzeError, CallParams, DEFAULT_EXCLUDED_FUNCTION_CONTEXTS, DEFAULT_PASSTHROUGH_WRAPPERS,
+    DEFAULT_SQL_KEYWORDS, DEFAULT_STATEMENT_NAME_PATTERN, DynamicSpan, Explanation, FinderConfig,
+    Interpolation, SqlExtract, SqlString, display_path, project_path, project_root, relative_path,
+};
+use encoding_rs::Encoding;
+use logging::{bail_with, debug, info};
 use rustpython_parser::{
     Parse,
     ast::{self, Ranged},
 };
-use std::{fs, sync::Arc};
+use std::{cell::RefCell, fs, sync::Arc};
 
 pub struct SqlFinder {
     config: Arc<FinderConfig>,
+    frames: RefCell<Vec<constants::Frame>>,
+    unextractable: RefCell<Vec<(String, preanalysis::ByteRange)>>,
+    explanations: RefCell<Vec<(String, String, preanalysis::ByteRange)>>,
 }
 
 impl SqlFinder {
     #[must_use]
     pub const fn new(config: Arc<FinderConfig>) -> Self {
-        Self { config }
+        Self {
+            config,
+            frames: RefCell::new(Vec::new()),
+            unextractable: RefCell::new(Vec::new()),
+            explanations: RefCell::new(Vec::new()),
+        }
     }
 
-    #[must_use]
-    pub fn analyze_file(&mut self, file_path: &str, is_raw_sql: bool) -> Option<SqlExtract> {
-        if is_raw_sql {
+    /// Extracts the SQL of a file, an extract without strings means the file has no SQL.
+    pub fn analyze_file(
+        &mut self,
+        file_path: &str,
+        is_raw_sql: bool,
+    ) -> Result<SqlExtract, AnalyzeError> {
+        if is_raw_sql && let Some(keys) = self.config.data_file_keys(file_path) {
+            Self::analyze_data_file(file_path, keys)
+        } else if is_raw_sql {
             self.analyze_sql_file(file_path)
         } else {
             self.analyze_python_file(file_path)
         }
     }
 
-    fn analyze_python_file(&mut self, file_path: &str) -> Option<SqlExtract> {
-        let source_code = fs::read_to_string(file_path)
-            .inspect_err(|e| error!("Failed to read file '{file_path}': {e}"))
-            .ok()?;
+    fn analyze_python_file(&mut self, file_path: &str) -> Result<SqlExtract, AnalyzeError> {
+        let (source_code, encoding) = read_source(file_path)?;
+        let source_code = if file_path.ends_with(notebook::NOTEBOOK_EXTENSION) {
+            notebook::code_cells(&source_code).map_err(|(offset, message)| {
+                AnalyzeError::NotebookParse {
+                    path: file_path.to_string(),
+                    message,
+                    location: preanalysis::PreanalyzedFile::from_src(source_code.as_str())
+                        .offset_to_linecol(offset),
+                }
+            })?
+        } else {
+            source_code
+        };
+        let range_file = Arc::new(
+            preanalysis::PreanalyzedFile::from_src(source_code)
+                .with_noqa(self.config.respect_noqa())
+                .with_encoding(encoding),
+        );
 
-        let parsed = ast::Suite::parse(&source_code, file_path)
-            .inspect_err(|e| {
-                error!("Failed to parse Python file '{file_path}': {e}");
-            })
-            .ok()?;
+        let strings = self.analyze_preanalyzed(&range_file, file_path)?;
+        let explanations = self.take_explanations(&range_file);
 
-        let range_file = preanalysis::PreanalyzedFile::from_src(&source_code);
-        let strings = self.analyze_stmts(&parsed, &range_file);
+        Ok(SqlExtract::new(file_path.to_string(), strings, range_file)
+            .with_explanations(explanations))
+    }
 
-        Some(SqlExtract::new(file_path.to_string(), strings))
+    /// Same as `analyze_file` for Python, but for source that is already in memory.
+    pub fn analyze_python_src(
+        &self,
+        source_code: &str,
+        file_path: &str,
+    ) -> Result<Vec<SqlString>, AnalyzeError> {
+        self.analyze_preanalyzed(
+            &preanalysis::PreanalyzedFile::from_src(source_code)
+                .with_noqa(self.config.respect_noqa()),
+            file_path,
+        )
     }
-    fn analyze_sql_file(&mut self, file_path: &str) -> Option<SqlExtract> {
-        let source_code = fs::read_to_string(file_path)
-            .inspect_err(|e| error!("Failed to read file '{file_path}': {e}"))
-            .ok()?;
-        let mut strings = Vec::new();
-        let mut current_pos = 0;
-        let mut line_num = 1;
-        let mut col_num = 1;
-
-        for (index, sql_segment) in source_code.split(';').enumerate() {
-            let cleaned = Self::trim_sql_comments(sql_segment);
-
-            if !cleaned.is_empty() {
-                let start_line = line_num;
-                let start_col = col_num;
-                let range = crate::preanalysis::Range {
-                    start: crate::preanalysis::LineCol::new(start_line, start_col, 0),
-                };
-                strings.push(SqlString::new(
-                    format!("sql_statement_{}", index + 1),
-                    cleaned,
-                    range,
-                ));
-            }
 
-            // Update position for next segment
-            for ch in sql_segment.chars() {
-                if ch == '\n' {
-                    line_num += 1;
-                    col_num = 1;
-                } else {
-                    col_num += 1;
+    fn analyze_preanalyzed(
+        &self,
+        range_file: &preanalysis::PreanalyzedFile,
+        file_path: &str,
+    ) -> Result<Vec<SqlString>, AnalyzeError> {
+        if range_file.should_ignore_file() {
+            debug!("Skipping '{file_path}', it has a `# sqint: ignore-file` pragma");
+            return Ok(vec![]);
+        }
+        let strings = match ast::Suite::parse(range_file.src(), file_path) {
+            Ok(parsed) => {
+                let mut strings = self.analyze_scope(&parsed, range_file, None);
+                if self.config.module_literals() {
+                    strings.extend(self.analyze_module_literals(&parsed, range_file));
                 }
+                if self.config.unnamed_literals() {
+                    let unnamed = self.analyze_unnamed_literals(&parsed, range_file, &strings);
+                    strings.extend(unnamed);
+                }
+                if self.config.dead_sql_detection() {
+                    self.mark_unreferenced(&parsed, &mut strings);
+                }
+                strings.extend(self.take_unextractable(range_file));
+                strings
             }
-
-            // Account for the semicolon delimiter (except for the last segment)
-            if current_pos + sql_segment.len() < source_code.len() {
-                col_num += 1;
+            Err(e) if self.config.fallback_extraction() => {
+                info!(
+                    "Failed to parse Python file '{file_path}': {e}. Falling back to approximate line based extraction."
+                );
+                self.analyze_approximate(range_file)
+            }
+            Err(e) => {
+                return Err(AnalyzeError::PythonParse {
+                    path: file_path.to_string(),
+                    message: e.error.to_string(),
+                    location: range_file.offset_to_linecol(e.offset.to_usize()),
+                });
             }
-            current_pos += sql_segment.len() + 1;
+        };
+        let mut strings = apply_line_pragmas(strings, range_file);
+        // Positions within fallback strings would only be guesses, they're reported at the string
+        for s in strings.iter_mut().filter(|s| !s.approximate) {
+            s.locate_content(range_file.src());
         }
+        Ok(strings)
+    }
+    fn analyze_data_file(
+        file_path: &str,
+        keys: &finder_types::ContextSet,
+    ) -> Result<SqlExtract, AnalyzeError> {
+        let (source_code, encoding) = read_source(file_path)?;
+        let range_file =
+            Arc::new(preanalysis::PreanalyzedFile::from_src(source_code).with_encoding(encoding));
 
-        Some(SqlExtract::new(file_path.to_string(), strings))
+        let strings = data_files::extract_data_strings(range_file.src(), keys)
+            .map_err(|(offset, message)| AnalyzeError::DataParse {
+                path: file_path.to_string(),
+                message,
+                location: range_file.offset_to_linecol(offset),
+            })?
+            .into_iter()
+            .map(|found| {
+                SqlString::new(
+                    found.name,
+                    found.content,
+                    range_file
+                        .byterange_to_range(preanalysis::ByteRange::new(found.offset, found.end)),
+                )
+            })
+            .collect();
+        Ok(SqlExtract::new(file_path.to_string(), strings, range_file))
     }
 
-    fn trim_sql_comments(sql: &str) -> String {
-        let lines: Vec<&str> = sql.lines().collect();
-        let mut start_idx = 0;
-        let mut end_idx = lines.len();
-
-        // Find first non-comment, non-empty line
-        for (i, line) in lines.iter().enumerate() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() && !trimmed.starts_with("--") && !trimmed.starts_with("#") {
-                start_idx = i;
-                break;
-            }
-        }
+    fn analyze_sql_file(&self, file_path: &str) -> Result<SqlExtract, AnalyzeError> {
+        let (source_code, encoding) = read_source(file_path)?;
+        Ok(self.analyze_preanalyzed_sql(
+            preanalysis::PreanalyzedFile::from_src(source_code).with_encoding(encoding),
+            file_path,
+        ))
+    }
 
-        // Find last non-comment, non-empty line
-        for (i, line) in lines.iter().enumerate().rev() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() && !trimmed.starts_with("--") && !trimmed.starts_with("#") {
-                end_idx = i + 1;
-                break;
-            }
-        }
+    /// Same as `analyze_file` for raw SQL, but for source that is already in memory, like
+    /// stdin. `file_path` only names the extract and is matched against the file patterns.
+    #[must_use]
+    pub fn analyze_sql_src(&self, source_code: &str, file_path: &str) -> SqlExtract {
+        self.analyze_preanalyzed_sql(
+            preanalysis::PreanalyzedFile::from_src(source_code),
+            file_path,
+        )
+    }
 
-        if start_idx >= end_idx {
-            return String::new();
-        }
+    fn analyze_preanalyzed_sql(
+        &self,
+        range_file: preanalysis::PreanalyzedFile,
+        file_path: &str,
+    ) -> SqlExtract {
+        let range_file = Arc::new(range_file);
+        let script = if self.config.is_templated_sql(file_path) {
+            debug!("Stripping template syntax of {file_path}");
+            templating::strip_templates(range_file.src())
+        } else {
+            range_file.src().to_string()
+        };
 
-        lines[start_idx..end_idx].join("\n").trim().to_string()
+        let strings = sql_script::split_statements(&script)
+            .into_iter()
+            .enumerate()
+            .map(|(index, statement)| {
+                let comments: Vec<&str> = statement
+                    .comments
+                    .iter()
+                    .map(|comment| &range_file.src()[comment.start()..comment.end()])
+                    .collect();
+                let content = statement.content(&script);
+                let name = self
+                    .config
+                    .statement_name(&comments)
+                    .or_else(|| sql_script::summary(&content))
+                    .unwrap_or_else(|| format!("sql_statement_{}", index + 1));
+                SqlString::new(
+                    name,
+                    content,
+                    range_file.byterange_to_range(statement.range),
+                )
+            })
+            .collect();
+        SqlExtract::new(file_path.to_string(), strings, range_file)
     }
 
     #[allow(clippy::too_many_lines)]
+    /// `function` is the name of the innermost enclosing function, if any.
     pub(crate) fn analyze_stmts(
         &self,
         suite: &ast::Suite,
         rf: &preanalysis::PreanalyzedFile,
+        function: Option<&str>,
     ) -> Vec<SqlString> {
         let mut results = Vec::new();
         for stmt in suite {
             let start_offset = stmt.range().start().to_usize();
-            let end_offset = stmt.range().end().to_usize();
-            if rf.should_ignore_stmt_at(start_offset) || rf.should_ignore_stmt_at(end_offset) {
+            if rf.is_disabled_at(start_offset) {
                 continue;
             }
+            // Pragmas on any line of a statement cover everything found in it, for statements
+            // with a body only those on the header
+            let suppression = rf.suppression_in_byte_range(start_offset, header_end(stmt));
+            let dialect = rf.dialect_in_byte_range(start_offset, header_end(stmt));
+            let first = results.len();
 
-            let stmt_results = match stmt {
+            results.extend(self.analyze_named_exprs(stmt, rf));
+            results.extend(self.analyze_nested_scopes(stmt, rf));
+            let analyze = || match stmt {
                 ast::Stmt::Assign(a) => self.analyze_assignment(a, rf),
                 ast::Stmt::AnnAssign(a) => self.analyze_annotated_assignment(a, rf),
+                ast::Stmt::AugAssign(a) => self.analyze_aug_assignment(a, rf),
 
                 ast::Stmt::For(ast::StmtFor { body, orelse, .. })
                 | ast::Stmt::AsyncFor(ast::StmtAsyncFor { body, orelse, .. })
-                | ast::Stmt::While(ast::StmtWhile { body, orelse, .. })
-                | ast::Stmt::If(ast::StmtIf { body, orelse, .. }) => {
-                    self.analyze_body_and_orelse(body, orelse, rf)
+                | ast::Stmt::While(ast::StmtWhile { body, orelse, .. }) => {
+                    self.analyze_body_and_orelse(body, orelse, rf, function)
+                }
+                ast::Stmt::If(ast::StmtIf { body, orelse, .. }) => {
+                    let (body, orelse) = self.within_branches(
+                        || self.analyze_stmts(body, rf, function),
+                        || self.analyze_stmts(orelse, rf, function),
+                    );
+                    body.into_iter().chain(orelse).collect()
                 }
 
-                ast::Stmt::FunctionDef(ast::StmtFunctionDef { body, .. })
-                | ast::Stmt::AsyncFunctionDef(ast::StmtAsyncFunctionDef { body, .. })
-                | ast::Stmt::ClassDef(ast::StmtClassDef { body, .. })
-                | ast::Stmt::With(ast::StmtWith { body, .. })
-                | ast::Stmt::AsyncWith(ast::StmtAsyncWith { body, .. }) => {
-                    self.analyze_stmts(body, rf)
+                ast::Stmt::FunctionDef(ast::StmtFunctionDef {
+                    name, args, body, ..
+                })
+                | ast::Stmt::AsyncFunctionDef(ast::StmtAsyncFunctionDef {
+                    name, args, body, ..
+                }) => {
+                    let mut results = self.analyze_arg_defaults(args, rf);
+                    results.extend(self.analyze_scope(body, rf, Some(name.as_str())));
+                    results
+                }
+                ast::Stmt::ClassDef(c) => self.analyze_class(c, rf),
+                ast::Stmt::With(ast::StmtWith { items, body, .. })
+                | ast::Stmt::AsyncWith(ast::StmtAsyncWith { items, body, .. }) => {
+                    let mut results = self.analyze_with_items(items, rf);
+                    results.extend(self.analyze_stmts(body, rf, function));
+                    results
                 }
 
                 ast::Stmt::Try(t) => {
-                    self.analyze_try(&t.body, &t.orelse, &t.finalbody, &t.handlers, rf)
+                    self.analyze_try(&t.body, &t.orelse, &t.finalbody, &t.handlers, rf, function)
                 }
                 ast::Stmt::TryStar(t) => {
-                    self.analyze_try(&t.body, &t.orelse, &t.finalbody, &t.handlers, rf)
+                    self.analyze_try(&t.body, &t.orelse, &t.finalbody, &t.handlers, rf, function)
+                }
+                ast::Stmt::Match(m) => {
+                    let mut results = self.analyze_match_exprs(m, rf);
+                    results.extend(
+                        m.cases
+                            .iter()
+                            .flat_map(|c| self.analyze_stmts(&c.body, rf, function)),
+                    );
+                    results
                 }
-                ast::Stmt::Match(f) => f
-                    .cases
-                    .iter()
-                    .flat_map(|c| self.analyze_stmts(&c.body, rf))
-                    .collect(),
 
-                ast::Stmt::Expr(e) => self.analyze_stmt_expr(e, rf),
-                ast::Stmt::Return(_)
-                | ast::Stmt::Import(_)
+                ast::Stmt::Expr(e) => {
+                    let mut results = self.analyze_stmt_expr(e, rf);
+                    // Generators hand out their SQL like other functions return it
+                    if let ast::Expr::Yield(ast::ExprYield {
+                        value: Some(value), ..
+                    }) = &*e.value
+                        && let Some(name) = self.returning_function(function)
+                    {
+                        results.extend(self.analyze_returned(value, name, rf));
+                    }
+                    results
+                }
+                ast::Stmt::Assert(a) => {
+                    let mut results = self.analyze_assert_calls(a, rf);
+                    if self.config.assert_comparisons() {
+                        results.extend(self.analyze_assert(a, rf));
+                    }
+                    results
+                }
+                ast::Stmt::Return(ast::StmtReturn {
+                    value: Some(value), ..
+                }) => self
+                    .returning_function(function)
+                    .map_or_else(Vec::new, |name| self.analyze_returned(value, name, rf)),
+                ast::Stmt::Return(_) => vec![],
+                ast::Stmt::Raise(r) => self.analyze_raise(r, rf),
+                ast::Stmt::Import(_)
                 | ast::Stmt::ImportFrom(_)
                 | ast::Stmt::Continue(_)
-                | ast::Stmt::Assert(_)
-                | ast::Stmt::Delete(_)
-                | ast::Stmt::Raise(_) => {
+                | ast::Stmt::Delete(_) => {
                     vec![]
                 }
                 _ => {
                     bail_with!(vec![], "Unimplemented stmt: {:?}", stmt)
                 }
             };
+            let mut stmt_results = if is_compound(stmt) {
+                self.within_block(analyze)
+            } else {
+                analyze()
+            };
+            if self.config.dataflow() {
+                let extended = self.extended_names(stmt);
+                stmt_results
+                    .iter_mut()
+                    .filter(|s| extended.contains(&s.variable_name))
+                    .for_each(|s| s.extends = true);
+            }
 
             results.extend(stmt_results);
+            for s in &mut results[first..] {
+                s.suppress(suppression.as_ref());
+                s.override_dialect(dialect);
+            }
+            self.bind_constants(stmt);
         }
 
+        traversal::resolve_appends(&mut results);
+        results
+    }
+
+    /// The enclosing function, if its returned values are SQL.
+    fn returning_function<'a>(&self, function: Option<&'a str>) -> Option<&'a str> {
+        function.filter(|name| self.config.is_return_context(name))
+    }
+
+    /// Analyzes the body of a module, function or class. Fragments appended to a variable that
+    /// wasn't assigned SQL in the same scope can't be checked and are dropped. With dataflow,
+    /// strings a later one was built on are dropped as well.
+    pub(crate) fn analyze_scope(
+        &self,
+        suite: &ast::Suite,
+        rf: &preanalysis::PreanalyzedFile,
+        function: Option<&str>,
+    ) -> Vec<SqlString> {
+        let mut results = self.within_scope(|| self.analyze_stmts(suite, rf, function));
+        results.retain(|s| !s.appended);
+        if self.config.dataflow() {
+            traversal::drop_superseded(&mut results);
+        }
         results
     }
 
@@ -192,9 +390,10 @@ impl SqlFinder {
         body: &Vec<ast::Stmt>,
         orelse: &Vec<ast::Stmt>,
         range_file: &preanalysis::PreanalyzedFile,
+        function: Option<&str>,
     ) -> Vec<SqlString> {
-        let body_results = self.analyze_stmts(body, range_file);
-        let orelse_results = self.analyze_stmts(orelse, range_file);
+        let body_results = self.analyze_stmts(body, range_file, function);
+        let orelse_results = self.analyze_stmts(orelse, range_file, function);
         body_results.into_iter().chain(orelse_results).collect()
     }
 
@@ -205,20 +404,23 @@ impl SqlFinder {
         finalbody: &Vec<ast::Stmt>,
         handlers: &[ast::ExceptHandler],
         range_file: &preanalysis::PreanalyzedFile,
+        function: Option<&str>,
     ) -> Vec<SqlString> {
-        let body_results = self.analyze_stmts(body, range_file);
+        let body_results = self.analyze_stmts(body, range_file, function);
 
         let handler_results: Vec<SqlString> = handlers
             .iter()
             .filter_map(|h| {
-                h.as_except_handler()
-                    .map_or_else(|| None, |eh| Some(self.analyze_stmts(&eh.body, range_file)))
+                h.as_except_handler().map_or_else(
+                    || None,
+                    |eh| Some(self.analyze_stmts(&eh.body, range_file, function)),
+                )
             })
             .flatten()
             .collect();
 
-        let orelse_results = self.analyze_stmts(orelse, range_file);
-        let finally_results = self.analyze_stmts(finalbody, range_file);
+        let orelse_results = self.analyze_stmts(orelse, range_file, function);
+        let finally_results = self.analyze_stmts(finalbody, range_file, function);
 
         body_results
             .into_iter()
@@ -228,3 +430,67 @@ impl SqlFinder {
             .collect()
     }
 }
+
+/// Statements with nested bodies that run conditionally, repeatedly or not at all.
+const fn is_compound(stmt: &ast::Stmt) -> bool {
+    matches!(
+        stmt,
+        ast::Stmt::For(_)
+            | ast::Stmt::AsyncFor(_)
+            | ast::Stmt::While(_)
+            | ast::Stmt::If(_)
+            | ast::Stmt::With(_)
+            | ast::Stmt::AsyncWith(_)
+            | ast::Stmt::Try(_)
+            | ast::Stmt::TryStar(_)
+            | ast::Stmt::Match(_)
+    )
+}
+
+fn read_source(file_path: &str) -> Result<(String, Option<&'static Encoding>), AnalyzeError> {
+    fs::read(file_path)
+        .map(|bytes| encoding::decode_source(bytes, file_path))
+        .map_err(|source| AnalyzeError::Io {
+            path: file_path.to_string(),
+            source,
+        })
+}
+
+/// End of the part of `stmt` before its body, the whole statement for simple ones.
+fn header_end(stmt: &ast::Stmt) -> usize {
+    let body = match stmt {
+        ast::Stmt::FunctionDef(ast::StmtFunctionDef { body, .. })
+        | ast::Stmt::AsyncFunctionDef(ast::StmtAsyncFunctionDef { body, .. })
+        | ast::Stmt::ClassDef(ast::StmtClassDef { body, .. })
+        | ast::Stmt::For(ast::StmtFor { body, .. })
+        | ast::Stmt::AsyncFor(ast::StmtAsyncFor { body, .. })
+        | ast::Stmt::While(ast::StmtWhile { body, .. })
+        | ast::Stmt::If(ast::StmtIf { body, .. })
+        | ast::Stmt::With(ast::StmtWith { body, .. })
+        | ast::Stmt::AsyncWith(ast::StmtAsyncWith { body, .. })
+        | ast::Stmt::Try(ast::StmtTry { body, .. })
+        | ast::Stmt::TryStar(ast::StmtTryStar { body, .. }) => body.first(),
+        ast::Stmt::Match(m) => return m.subject.range().end().to_usize(),
+        _ => None,
+    };
+    body.map_or(stmt.range().end(), |first| first.range().start())
+        .to_usize()
+}
+
+/// Pragmas on the lines of a string itself, for strings found outside of a statement's own
+/// analysis like module literals or the line based fallback. Strings without a dialect pragma
+/// of their own take the file's.
+fn apply_line_pragmas(
+    mut strings: Vec<SqlString>,
+    rf: &preanalysis::PreanalyzedFile,
+) -> Vec<SqlString> {
+    for s in &mut strings {
+        let suppression =
+            rf.suppression_in_byte_range(s.range.start.byte_offset(), s.range.end.byte_offset());
+        s.suppress(suppression.as_ref());
+        let dialect =
+            rf.dialect_in_byte_range(s.range.start.byte_offset(), s.range.end.byte_offset());
+        s.override_dialect(dialect.or_else(|| rf.file_dialect()));
+    }
+    strings
+}
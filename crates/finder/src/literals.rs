@@ -0,0 +1,99 @@
+use rustpython_parser::{ast, text_size::TextRange};
+
+use crate::preanalysis::PreanalyzedFile;
+use crate::visit::{Visit, walk_body, walk_exprs};
+use crate::{SqlFinder, SqlString};
+
+// Heuristic pass for SQL that no context names, like literals handed through `*args`. Every
+// string constant that looks like SQL is extracted, wherever it is. Docstrings, arguments of
+// excluded calls such as logging and the parts of strings built with `+`, `%` or f-strings are
+// skipped, and so are constants an earlier pass already extracted.
+impl SqlFinder {
+    pub(crate) fn analyze_unnamed_literals(
+        &self,
+        suite: &ast::Suite,
+        range_file: &PreanalyzedFile,
+        found: &[SqlString],
+    ) -> Vec<SqlString> {
+        let mut literals = Literals {
+            finder: self,
+            found: vec![],
+        };
+        walk_body(&mut literals, without_docstring(suite));
+
+        // Calls are found with the range of the whole call, the literal lies within it
+        let is_found = |offset: usize| {
+            found.iter().any(|s| {
+                s.range.start.byte_offset() <= offset && offset < s.range.end.byte_offset()
+            })
+        };
+        literals
+            .found
+            .into_iter()
+            .filter(|(range, s)| {
+                let start = range.start().to_usize();
+                s.trim().len() >= MIN_UNNAMED_LITERAL_LEN
+                    && self.config.is_sql_str(s)
+                    && !is_found(start)
+                    && !range_file.is_disabled_at(start)
+            })
+            .map(|(range, s)| {
+                SqlString::new(
+                    UNNAMED_LITERAL_NAME.to_string(),
+                    s,
+                    range_file.byterange_to_range(range.into()),
+                )
+            })
+            .collect()
+    }
+}
+
+const UNNAMED_LITERAL_NAME: &str = "<literal>";
+/// Same bar as for bare module literals, shorter strings are rarely SQL.
+const MIN_UNNAMED_LITERAL_LEN: usize = 10;
+
+struct Literals<'a> {
+    finder: &'a SqlFinder,
+    found: Vec<(TextRange, String)>,
+}
+
+impl<'a> Visit<'a> for Literals<'_> {
+    fn enter_stmt(&mut self, stmt: &'a ast::Stmt) -> bool {
+        let (decorators, body) = match stmt {
+            ast::Stmt::FunctionDef(f) => (&f.decorator_list, &f.body),
+            ast::Stmt::AsyncFunctionDef(f) => (&f.decorator_list, &f.body),
+            ast::Stmt::ClassDef(c) => (&c.decorator_list, &c.body),
+            _ => return true,
+        };
+        walk_exprs(self, decorators);
+        walk_body(self, without_docstring(body));
+        false
+    }
+
+    fn enter_expr(&mut self, expr: &'a ast::Expr) -> bool {
+        match expr {
+            ast::Expr::Constant(c) => {
+                if let ast::Constant::Str(s) = &c.value {
+                    self.found.push((c.range, s.clone()));
+                }
+                false
+            }
+            // Fragments of a string put together at runtime aren't SQL on their own
+            ast::Expr::JoinedStr(_) | ast::Expr::BinOp(_) => false,
+            ast::Expr::Call(call) => {
+                let name = SqlFinder::extract_function_name(&call.func);
+                !self.finder.config.is_excluded_function_name(&name)
+            }
+            _ => true,
+        }
+    }
+}
+
+fn without_docstring(body: &[ast::Stmt]) -> &[ast::Stmt] {
+    match body.first() {
+        Some(ast::Stmt::Expr(ast::StmtExpr { value, .. })) if matches!(&**value, ast::Expr::Constant(c) if c.value.is_str()) => {
+            &body[1..]
+        }
+        _ => body,
+    }
+}
@@ -0,0 +1,141 @@
+use yaml_rust2::parser::{Event, Parser};
+
+use crate::data_files::char_offset;
+
+// Jupyter notebooks are JSON, read as the YAML it is a subset of like data files. Their code
+// cells are put together into Python source with every line on the line of the notebook its
+// JSON string is on, so findings point into the notebook. Columns are counted within the code.
+// IPython magics and shell escapes aren't Python and are left out, as are `%%` cell magics.
+
+/// Extension of notebook files.
+pub(crate) const NOTEBOOK_EXTENSION: &str = ".ipynb";
+
+struct Frame {
+    /// Key of the node in the mapping holding it, `None` for items of sequences
+    key: Option<String>,
+    is_mapping: bool,
+    /// Key read within a mapping, waiting for its value
+    pending_key: Option<String>,
+}
+
+#[derive(Default)]
+struct Cell {
+    is_code: bool,
+    /// Parts of the source with the line of the notebook each one starts on
+    source: Vec<(String, usize)>,
+}
+
+/// Python source of the code cells of a notebook, or where and why it fails to parse.
+pub(crate) fn code_cells(src: &str) -> Result<String, (usize, String)> {
+    let mut parser = Parser::new_from_str(src);
+    let mut stack: Vec<Frame> = vec![];
+    let mut cell = Cell::default();
+    let mut code = String::new();
+    let mut line = 1;
+
+    loop {
+        let (event, marker) = parser
+            .next_token()
+            .map_err(|e| (char_offset(src, e.marker().index()), e.info().to_string()))?;
+        match event {
+            Event::StreamEnd => break,
+            Event::Scalar(value, ..) => {
+                if let Some(
+                    frame @ Frame {
+                        is_mapping: true,
+                        pending_key: None,
+                        ..
+                    },
+                ) = stack.last_mut()
+                {
+                    frame.pending_key = Some(value);
+                    continue;
+                }
+                let key = value_key(&mut stack);
+                if in_cell(&stack) {
+                    match key.as_deref() {
+                        Some("cell_type") => cell.is_code = value == "code",
+                        Some("source") => cell.source.push((value, marker.line())),
+                        _ => {}
+                    }
+                } else if stack.len() == 4
+                    && in_cell(&stack[..3])
+                    && stack[3].key.as_deref() == Some("source")
+                {
+                    cell.source.push((value, marker.line()));
+                }
+            }
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                let key = value_key(&mut stack);
+                stack.push(Frame {
+                    key,
+                    is_mapping: matches!(event, Event::MappingStart(..)),
+                    pending_key: None,
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                if in_cell(&stack) {
+                    let cell = std::mem::take(&mut cell);
+                    if cell.is_code {
+                        line = push_cell(&mut code, line, &cell.source);
+                    }
+                }
+                stack.pop();
+            }
+            Event::Alias(_) => {
+                value_key(&mut stack);
+            }
+            _ => {}
+        }
+    }
+    Ok(code)
+}
+
+/// Key of the next value in the current node, moving past it.
+fn value_key(stack: &mut [Frame]) -> Option<String> {
+    stack.last_mut().and_then(|frame| frame.pending_key.take())
+}
+
+/// Whether `stack` ends in a cell, a mapping in the `cells` list of the notebook.
+fn in_cell(stack: &[Frame]) -> bool {
+    matches!(
+        stack,
+        [_, cells, cell] if cells.key.as_deref() == Some("cells") && !cells.is_mapping && cell.is_mapping
+    )
+}
+
+/// Appends the lines of a code cell to `code`, `line` is the line of it the next text goes on.
+/// Returns that line after the cell.
+fn push_cell(code: &mut String, mut line: usize, source: &[(String, usize)]) -> usize {
+    if source
+        .first()
+        .is_some_and(|(part, _)| part.trim_start().starts_with("%%"))
+    {
+        return line;
+    }
+
+    // Parts usually are one line each, a source given as a single string has all of them
+    let mut lines: Vec<(String, usize)> = vec![];
+    for (part, at) in source {
+        for text in part.split_inclusive('\n') {
+            match lines.last_mut() {
+                Some((last, _)) if !last.ends_with('\n') => last.push_str(text),
+                _ => lines.push((text.to_string(), *at)),
+            }
+        }
+    }
+
+    for (text, at) in lines {
+        while line < at {
+            code.push('\n');
+            line += 1;
+        }
+        let text = text.trim_end_matches(['\n', '\r']);
+        if !text.trim_start().starts_with(['%', '!']) {
+            code.push_str(text);
+        }
+        code.push('\n');
+        line += 1;
+    }
+    line
+}
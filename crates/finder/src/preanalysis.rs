@@ -1,8 +1,14 @@
 use std::fmt::Display;
+use std::sync::LazyLock;
 
-use rangemap::RangeMap;
+use encoding_rs::Encoding;
+use logging::{debug, warn};
+use rangemap::{RangeMap, RangeSet};
+use regex::Regex;
+use rustpython_parser::lexer::lex;
 use rustpython_parser::text_size::TextRange;
-use std::collections::HashSet;
+use rustpython_parser::{Mode, Tok};
+use std::collections::{BTreeSet, HashMap};
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct LineCol {
@@ -19,6 +25,18 @@ impl LineCol {
             byte_offset,
         }
     }
+
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    pub const fn col(&self) -> usize {
+        self.col
+    }
+
+    pub const fn byte_offset(&self) -> usize {
+        self.byte_offset
+    }
 }
 
 impl Display for LineCol {
@@ -27,128 +45,567 @@ impl Display for LineCol {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Range {
     pub start: LineCol,
+    pub end: LineCol,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct ByteRange {
     start: usize,
+    end: usize,
+}
+
+impl ByteRange {
+    pub(crate) const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    pub const fn end(&self) -> usize {
+        self.end
+    }
 }
 
 impl From<TextRange> for ByteRange {
     fn from(value: TextRange) -> Self {
         Self {
             start: value.start().to_usize(),
+            end: value.end().to_usize(),
+        }
+    }
+}
+
+/// Unit columns are counted in. `LineCol` counts characters, which is what terminals show,
+/// LSP clients count UTF-16 code units and some editors bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnEncoding {
+    #[default]
+    Chars,
+    /// Characters with tabs advancing to the next multiple of the tab width, the columns
+    /// editors show
+    Display(usize),
+    Bytes,
+    Utf16,
+}
+
+/// Columns `text` takes up at the start of a line with tabs expanded to `tab_width`.
+pub fn display_width(text: &str, tab_width: usize) -> usize {
+    text.chars()
+        .filter(|&ch| ch != '\r')
+        .fold(0, |col, ch| match ch {
+            '\t' if tab_width > 1 => col + tab_width - col % tab_width,
+            _ => col + 1,
+        })
+}
+
+/// Diagnostics a `# sqint: ignore` pragma suppresses, `ignore[SQ100, SQ302]` only lists some.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Suppression {
+    All,
+    Codes(BTreeSet<String>),
+}
+
+impl Suppression {
+    /// Parses what follows `sqint: ignore`, anything but a bracketed list means all codes.
+    fn parse(rest: &str) -> Self {
+        rest.trim_start()
+            .strip_prefix('[')
+            .and_then(|list| list.split_once(']'))
+            .map_or(Self::All, |(codes, _)| {
+                Self::Codes(
+                    codes
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|code| !code.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            })
+    }
+
+    pub fn covers(&self, code: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Codes(codes) => codes.contains(code),
+        }
+    }
+
+    #[must_use]
+    pub fn merge(self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Codes(mut codes), Self::Codes(more)) => {
+                codes.extend(more.iter().cloned());
+                Self::Codes(codes)
+            }
+            _ => Self::All,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pragma {
+    /// `# sqint: ignore`, the line it's on
+    Ignore(Suppression),
+    /// `# sqint: disable`, every line up to the next `enable`
+    Disable,
+    /// `# sqint: enable`
+    Enable,
+    /// `# sqint: ignore-file`, within the first lines or as the first comment
+    IgnoreFile,
+    /// `# sqint: dialect=sqlite`, for the whole file on a line of its own where `ignore-file`
+    /// would count, otherwise for the statement it trails
+    Dialect(String),
+}
+
+/// `# noqa` or `# noqa: E501,SQ100`, anywhere in a comment like flake8 reads it.
+static NOQA: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)#\s*noqa(?::\s?(?P<codes>[A-Z][0-9A-Z]*(?:[,\s]+[A-Z][0-9A-Z]*)*))?")
+        .expect("noqa pattern is valid")
+});
+
+/// Prefix of sqint's own rule codes, scoped `noqa` comments ignore every other code.
+const NOQA_CODE_PREFIX: &str = "SQ";
+
+/// Byte order mark editors on Windows like to start UTF-8 files with.
+const BOM: char = '\u{feff}';
+
+/// Lines an `ignore-file` pragma may appear in, after shebangs, encodings or license headers.
+const FILE_PRAGMA_LINES: usize = 10;
+
 #[derive(Debug, Clone)]
 struct PragmaMap {
-    // Maps filename to a set of lines to ignore from the analysis
-    ignores: HashSet<usize>,
+    // Lines with an ignore pragma of their own
+    ignores: HashMap<usize, Suppression>,
+    // Lines with a `# noqa` comment, only honored when enabled
+    noqa: HashMap<usize, Suppression>,
+    // Lines between disable and enable directives
+    disabled: RangeSet<usize>,
+    // Line of the disable directive that wasn't closed yet
+    open: Option<usize>,
+    // Lines with a dialect pragma trailing a statement
+    dialects: HashMap<usize, String>,
+    file_dialect: Option<String>,
+    // Every dialect pragma with the range of its comment, to report unknown names
+    dialect_pragmas: Vec<(ByteRange, String)>,
+    ignore_file: bool,
+    seen_comment: bool,
 }
 
 impl PragmaMap {
     fn new() -> Self {
         Self {
-            ignores: HashSet::new(),
+            ignores: HashMap::new(),
+            noqa: HashMap::new(),
+            disabled: RangeSet::new(),
+            open: None,
+            dialects: HashMap::new(),
+            file_dialect: None,
+            dialect_pragmas: vec![],
+            ignore_file: false,
+            seen_comment: false,
         }
     }
 
-    fn add_ignore(&mut self, line: usize) {
-        self.ignores.insert(line);
+    /// Records the pragma of a comment at `offset` on `line`, if it is one. `own_line` tells
+    /// whether nothing but the comment is on that line.
+    fn scan(&mut self, line: usize, offset: usize, comment: &str, own_line: bool) {
+        match PreanalyzedFile::line_pragma(comment) {
+            Some(Pragma::Dialect(name)) => {
+                let range = ByteRange::new(offset, offset + comment.len());
+                self.dialect_pragmas.push((range, name.clone()));
+                if own_line && self.is_file_scope(line) {
+                    self.file_dialect = Some(name);
+                } else {
+                    self.dialects.insert(line, name);
+                }
+            }
+            Some(pragma) => self.add(line, pragma),
+            None => {}
+        }
+        if let Some(suppression) = noqa_suppression(comment) {
+            self.noqa.insert(line, suppression);
+        }
+        self.seen_comment = true;
     }
 
-    pub fn should_ignore_line(&self, line: usize) -> bool {
-        self.ignores.contains(&line)
+    fn add(&mut self, line: usize, pragma: Pragma) {
+        match pragma {
+            Pragma::Ignore(suppression) => {
+                self.ignores.insert(line, suppression);
+            }
+            // Directives don't nest, repeating the current state changes nothing
+            Pragma::Disable => {
+                self.open.get_or_insert(line);
+            }
+            Pragma::Enable => {
+                if let Some(start) = self.open.take() {
+                    self.disabled.insert(start..line + 1);
+                }
+            }
+            Pragma::IgnoreFile => {
+                self.ignore_file |= self.is_file_scope(line);
+            }
+            Pragma::Dialect(name) => {
+                self.dialects.insert(line, name);
+            }
+        }
+    }
+
+    /// Whether a pragma on `line` may apply to the whole file.
+    const fn is_file_scope(&self, line: usize) -> bool {
+        line <= FILE_PRAGMA_LINES || !self.seen_comment
+    }
+
+    /// Closes a disable without enable at `last_line`, the end of the file.
+    fn finish(&mut self, last_line: usize) {
+        if let Some(start) = self.open.take() {
+            warn!(
+                "`# sqint: disable` on line {start} is never enabled again, suppressing up to the end of the file"
+            );
+            self.disabled.insert(start..last_line + 1);
+        }
+    }
+
+    fn is_disabled(&self, line: usize) -> bool {
+        self.disabled.contains(&line)
+    }
+
+    fn suppression(&self, line: usize, respect_noqa: bool) -> Option<&Suppression> {
+        self.ignores
+            .get(&line)
+            .or_else(|| self.noqa.get(&line).filter(|_| respect_noqa))
+    }
+}
+
+/// What a `# noqa` comment suppresses, `None` when it's scoped to other tools' codes only.
+fn noqa_suppression(comment: &str) -> Option<Suppression> {
+    let caps = NOQA.captures(comment)?;
+    let Some(codes) = caps.name("codes") else {
+        return Some(Suppression::All);
+    };
+    let codes: BTreeSet<String> = codes
+        .as_str()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|code| code.to_uppercase().starts_with(NOQA_CODE_PREFIX))
+        .map(str::to_uppercase)
+        .collect();
+    (!codes.is_empty()).then_some(Suppression::Codes(codes))
+}
+
+/// Comments of `src` with their offset. Taken from the lexer so a `#` inside a string never
+/// counts. Where the source stops lexing, the rest of each line after its first `#` is taken.
+fn comments(src: &str) -> Vec<(usize, &str)> {
+    let mut comments = vec![];
+    for token in lex(src, Mode::Module) {
+        match token {
+            Ok((Tok::Comment(_), range)) => {
+                comments.push((range.start().to_usize(), &src[range]));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let error = e.location.to_usize();
+                let line_start = src[..error].rfind(['\n', '\r']).map_or(0, |i| i + 1);
+                let mut offset = line_start;
+                for line in src[line_start..].split_inclusive(['\n', '\r']) {
+                    if let Some(pos) = line.find('#')
+                        && offset + pos >= error
+                    {
+                        comments.push((offset + pos, line[pos..].trim_end()));
+                    }
+                    offset += line.len();
+                }
+                break;
+            }
+        }
     }
+    comments
+}
+
+#[cfg(test)]
+thread_local! {
+    pub static CONSTRUCTIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
 }
 
+/// Source of a file together with its line index and pragmas. Built once per file and shared
+/// through `SqlExtract`, so later stages never have to read or scan the file again.
 #[derive(Debug, Clone)]
-pub struct PreanalyzedFile<'a> {
+pub struct PreanalyzedFile {
     // Maps a byte range to the line number.
     map: RangeMap<usize, usize>,
-    src: &'a str,
+    // Byte offset of the start of each line, in order
+    line_starts: Vec<usize>,
+    src: String,
     pragmas: PragmaMap,
+    respect_noqa: bool,
+    // What the file was decoded from, when it wasn't UTF-8
+    encoding: Option<&'static Encoding>,
+    // Bytes of the BOM stripped from the start, byte columns of the first line count them
+    bom_len: usize,
 }
 
-impl<'a> PreanalyzedFile<'a> {
-    pub fn should_ignore_stmt_at(&self, offset: usize) -> bool {
-        let line = self
-            .map
-            .get(&offset)
-            .expect("Shouldn't ever exceed indexed lines");
-        self.pragmas.should_ignore_line(*line)
+impl PreanalyzedFile {
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Text of a 1 based line, without the line break.
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.src.len());
+        Some(self.src[start..end].trim_end_matches(['\r', '\n']))
+    }
+
+    /// Whether the file has a `# sqint: ignore-file` pragma and shouldn't be analyzed at all.
+    pub const fn should_ignore_file(&self) -> bool {
+        self.pragmas.ignore_file
+    }
+
+    /// Whether `offset` lies between `# sqint: disable` and `# sqint: enable`. Only counts
+    /// where a statement starts, a function ending in a disabled block keeps its earlier body.
+    pub fn is_disabled_at(&self, offset: usize) -> bool {
+        self.line_at(offset)
+            .is_some_and(|line| self.pragmas.is_disabled(line))
+    }
+
+    /// The `# sqint: ignore` pragma on the line of `offset`, if any.
+    pub fn suppression_at(&self, offset: usize) -> Option<&Suppression> {
+        self.line_at(offset)
+            .and_then(|line| self.pragmas.suppression(line, self.respect_noqa))
+    }
+
+    /// Every pragma on the lines from `start` to `end`, merged.
+    pub fn suppression_in_byte_range(&self, start: usize, end: usize) -> Option<Suppression> {
+        self.lines_between(start, end)
+            .filter_map(|line| self.pragmas.suppression(line, self.respect_noqa))
+            .fold(None, |merged, s| {
+                Some(merged.map_or_else(|| s.clone(), |m: Suppression| m.merge(s)))
+            })
+    }
+
+    /// Dialect of a `# sqint: dialect=...` pragma on the lines from `start` to `end`, the first
+    /// one wins. The file's own dialect pragma isn't included.
+    pub fn dialect_in_byte_range(&self, start: usize, end: usize) -> Option<&str> {
+        self.lines_between(start, end)
+            .find_map(|line| self.pragmas.dialects.get(&line))
+            .map(String::as_str)
+    }
+
+    /// Dialect of a `# sqint: dialect=...` pragma at the top of the file.
+    pub fn file_dialect(&self) -> Option<&str> {
+        self.pragmas.file_dialect.as_deref()
+    }
+
+    /// Every dialect pragma with the range of its comment, ordered by position. Names aren't
+    /// validated, the finder doesn't know the supported dialects.
+    pub fn dialect_pragmas(&self) -> Vec<(Range, &str)> {
+        self.pragmas
+            .dialect_pragmas
+            .iter()
+            .map(|(range, name)| (self.byterange_to_range(*range), name.as_str()))
+            .collect()
+    }
+
+    /// Codes listed in `# sqint: ignore[...]` pragmas, with their line, ordered by line.
+    pub fn suppressed_codes(&self) -> Vec<(usize, &str)> {
+        let mut codes: Vec<(usize, &str)> = self
+            .pragmas
+            .ignores
+            .iter()
+            .filter_map(|(line, suppression)| match suppression {
+                Suppression::Codes(codes) => Some(codes.iter().map(|c| (*line, c.as_str()))),
+                Suppression::All => None,
+            })
+            .flatten()
+            .collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// Line of `offset`, `None` past the end of the source where no pragma can apply.
+    fn line_at(&self, offset: usize) -> Option<usize> {
+        let line = self.map.get(&offset).copied();
+        if line.is_none() {
+            debug!(
+                "Offset {offset} lies past the end of the source ({} bytes)",
+                self.src.len()
+            );
+        }
+        line
+    }
+
+    /// Lines from the one of `start` to the one of `end`, up to the last line when `end` lies
+    /// past the end of the source.
+    fn lines_between(&self, start: usize, end: usize) -> impl Iterator<Item = usize> {
+        let last = self.map.last_range_value().map_or(0, |(_, line)| *line);
+        self.line_at(start)
+            .into_iter()
+            .flat_map(move |first| first..=self.line_at(end).unwrap_or(last))
     }
-    pub fn from_src(src: &'a str) -> Self {
+
+    pub fn from_src(src: impl Into<String>) -> Self {
+        #[cfg(test)]
+        CONSTRUCTIONS.with(|c| c.set(c.get() + 1));
+
+        let mut src = src.into();
+        // Offsets and columns are counted from after a BOM, it isn't Python source. Byte columns
+        // still count it, they are positions in the file.
+        let bom_len = if src.starts_with(BOM) {
+            src.drain(..BOM.len_utf8());
+            BOM.len_utf8()
+        } else {
+            0
+        };
         let mut range_map = RangeMap::new();
         let mut pragmas = PragmaMap::new();
         let mut line = 1;
         let mut last_line_start = 0;
+        let mut line_starts = vec![0];
 
-        // Process line by line to properly handle pragmas
-        for (offset, ch) in src.char_indices() {
-            if ch == '\n' {
-                // Check current line for pragma
-                let line_text = &src[last_line_start..offset];
-                if Self::line_has_pragma(line_text) {
-                    pragmas.add_ignore(line);
-                }
-
+        // `\r\n`, `\n` and a lone `\r` all end a line, as they do for Python
+        let bytes = src.as_bytes();
+        for (offset, &byte) in bytes.iter().enumerate() {
+            if byte == b'\n' || (byte == b'\r' && bytes.get(offset + 1) != Some(&b'\n')) {
                 range_map.insert(last_line_start..(offset + 1), line);
                 line += 1;
                 last_line_start = offset + 1;
+                line_starts.push(last_line_start);
             }
         }
+        range_map.insert(last_line_start..src.len() + 1, line);
 
-        // Handle last line if no trailing newline
-        if last_line_start < src.len() {
-            let line_text = &src[last_line_start..];
-            if Self::line_has_pragma(line_text) {
-                pragmas.add_ignore(line);
-            }
+        for (offset, comment) in comments(&src) {
+            let (line_range, line) = range_map
+                .get_key_value(&offset)
+                .expect("Shouldn't ever exceed indexed lines");
+            let own_line = src[line_range.start..offset].trim().is_empty();
+            pragmas.scan(*line, offset, comment, own_line);
         }
-
-        range_map.insert(last_line_start..src.len() + 1, line);
+        pragmas.finish(line);
 
         Self {
             map: range_map,
+            line_starts,
             src,
             pragmas,
+            respect_noqa: false,
+            encoding: None,
+            bom_len,
         }
     }
 
-    fn line_has_pragma(line: &str) -> bool {
-        // Look for comment and check if it contains sqint: ignore
-        if let Some(comment_pos) = line.find('#') {
-            let comment = &line[comment_pos + 1..].trim();
-            return comment.starts_with("sqint: ignore")
-                || comment.starts_with("sqint:ignore")
-                || comment.contains("sqint: ignore");
+    /// Also honors `# noqa` comments, a `# sqint: ignore` on the same line takes precedence.
+    #[must_use]
+    pub const fn with_noqa(mut self, enabled: bool) -> Self {
+        self.respect_noqa = enabled;
+        self
+    }
+
+    /// Remembers the encoding the source was decoded from, byte columns are counted in it.
+    #[must_use]
+    pub const fn with_encoding(mut self, encoding: Option<&'static Encoding>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    fn encoded_len(&self, text: &str) -> usize {
+        self.encoding
+            .map_or(text.len(), |encoding| encoding.encode(text).0.len())
+    }
+
+    fn line_pragma(comment: &str) -> Option<Pragma> {
+        let comment = comment.strip_prefix('#')?.trim();
+        match comment.strip_prefix("sqint:").map(str::trim) {
+            Some("disable") => return Some(Pragma::Disable),
+            Some("enable") => return Some(Pragma::Enable),
+            Some("ignore-file") => return Some(Pragma::IgnoreFile),
+            Some(rest) if rest.starts_with("dialect") => {
+                let name = rest["dialect".len()..].trim_start().strip_prefix('=')?;
+                let name = name.split_whitespace().next().unwrap_or_default();
+                return Some(Pragma::Dialect(name.to_string()));
+            }
+            _ => {}
         }
-        false
+        comment
+            .split_once("sqint: ignore")
+            .or_else(|| comment.split_once("sqint:ignore"))
+            // `ignored` or `ignore-next-line` aren't the pragma
+            .filter(|(_, rest)| {
+                rest.is_empty() || rest.starts_with(|ch: char| ch.is_whitespace() || ch == '[')
+            })
+            .map(|(_, rest)| Pragma::Ignore(Suppression::parse(rest)))
     }
 
     pub fn byterange_to_range(&self, byte_range: ByteRange) -> Range {
-        let (start_line_byte_range, start_line_number) = self
+        Range {
+            start: self.offset_to_linecol(byte_range.start),
+            end: self.offset_to_linecol(byte_range.end),
+        }
+    }
+
+    /// `range` with its columns counted in `encoding` instead of characters.
+    pub fn encode_range(&self, range: &Range, encoding: ColumnEncoding) -> Range {
+        Range {
+            start: self.encode_linecol(&range.start, encoding),
+            end: self.encode_linecol(&range.end, encoding),
+        }
+    }
+
+    fn encode_linecol(&self, pos: &LineCol, encoding: ColumnEncoding) -> LineCol {
+        LineCol {
+            col: self.column(pos, encoding),
+            ..pos.clone()
+        }
+    }
+
+    /// 1 based column of `pos` counted in `encoding`, whatever unit its own column is in.
+    pub fn column(&self, pos: &LineCol, encoding: ColumnEncoding) -> usize {
+        let Some((line_range, _)) = self.map.get_key_value(&pos.byte_offset) else {
+            return pos.col;
+        };
+        let Some(before) = self.src.get(line_range.start..pos.byte_offset) else {
+            return pos.col;
+        };
+        let col = match encoding {
+            ColumnEncoding::Chars => before.chars().filter(|&ch| ch != '\r').count(),
+            ColumnEncoding::Display(tab_width) => display_width(before, tab_width),
+            ColumnEncoding::Bytes if line_range.start == 0 => {
+                self.bom_len + self.encoded_len(before)
+            }
+            ColumnEncoding::Bytes => self.encoded_len(before),
+            ColumnEncoding::Utf16 => before.chars().map(char::len_utf16).sum(),
+        };
+        col + 1
+    }
+
+    /// Position of `offset`, which is clamped to the end of the source. An offset inside of a
+    /// multi-byte character gets the column of that character.
+    pub(crate) fn offset_to_linecol(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let (line_byte_range, line_number) = self
             .map
-            .get_key_value(&byte_range.start)
-            .expect("We analyze files right before calculating linecols.");
+            .get_key_value(&offset)
+            .expect("Every offset up to the end of the source is indexed");
 
-        let start_col = self.src[start_line_byte_range.start..byte_range.start]
-            .chars()
+        let col = self.src[line_byte_range.start..]
+            .char_indices()
+            .take_while(|(idx, _)| line_byte_range.start + idx < offset)
+            .filter(|&(_, ch)| ch != '\r')
             .count()
             + 1;
 
-        let start = LineCol {
-            col: start_col,
-            line: *start_line_number,
-            byte_offset: byte_range.start,
-        };
-
-        Range { start }
+        LineCol {
+            col,
+            line: *line_number,
+            byte_offset: offset,
+        }
     }
 }
@@ -0,0 +1,310 @@
+use logging::debug;
+
+use crate::preanalysis::ByteRange;
+
+// Splitting of raw SQL files into statements. A semicolon only ends a statement outside of
+// string literals, quoted identifiers, dollar quoted function bodies and comments. Comments
+// and whitespace around a statement aren't part of it, comments inside of it are kept for the
+// parser. psql meta-commands such as `\c mydb` or `\i other.sql` take up the rest of their
+// line and are skipped, within a statement they're blanked out so positions stay the same.
+// Line comments between two statements are kept with the second, they may name it. Unnamed
+// statements get a summary of their verb and object as the name.
+
+pub(crate) struct Statement {
+    pub(crate) range: ByteRange,
+    /// `--` comments since the previous statement, without their line breaks
+    pub(crate) comments: Vec<ByteRange>,
+    meta_commands: Vec<ByteRange>,
+}
+
+impl Statement {
+    /// Text of the statement with its meta-commands replaced by spaces.
+    pub(crate) fn content(&self, src: &str) -> String {
+        let mut content = String::with_capacity(self.range.end() - self.range.start());
+        let mut pos = self.range.start();
+        for command in &self.meta_commands {
+            content.push_str(&src[pos..command.start()]);
+            content.extend(std::iter::repeat_n(' ', command.end() - command.start()));
+            pos = command.end();
+        }
+        content.push_str(&src[pos..self.range.end()]);
+        content
+    }
+}
+
+/// The statements in `src`, without the terminating semicolon.
+pub(crate) fn split_statements(src: &str) -> Vec<Statement> {
+    let bytes = src.as_bytes();
+    let mut statements = vec![];
+    // Start and end of the statement read so far, `None` while only comments were seen
+    let mut current: Option<(usize, usize)> = None;
+    let mut comments = vec![];
+    let mut meta_commands = vec![];
+    let mut finish = |start: usize,
+                      end: usize,
+                      comments: &mut Vec<ByteRange>,
+                      meta_commands: &mut Vec<ByteRange>| {
+        let meta_commands = std::mem::take(meta_commands)
+            .into_iter()
+            .filter(|command: &ByteRange| command.end() <= end)
+            .collect();
+        statements.push(Statement {
+            range: ByteRange::new(start, end),
+            comments: std::mem::take(comments),
+            meta_commands,
+        });
+    };
+    let mut line_start = true;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let byte = bytes[pos];
+        let next = bytes.get(pos + 1).copied();
+        let end = match byte {
+            b'\n' => {
+                line_start = true;
+                pos += 1;
+                continue;
+            }
+            _ if byte.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b';' => {
+                if let Some((start, end)) = current.take() {
+                    finish(start, end, &mut comments, &mut meta_commands);
+                }
+                line_start = false;
+                pos += 1;
+                continue;
+            }
+            b'-' if next == Some(b'-') => {
+                let end = line_end(bytes, pos);
+                if current.is_none() {
+                    comments.push(ByteRange::new(pos, end));
+                }
+                pos = end;
+                continue;
+            }
+            // MySQL comments, only at the start of a line where they can't be an operator
+            b'#' if line_start => {
+                pos = line_end(bytes, pos);
+                continue;
+            }
+            b'\\' if line_start => {
+                let end = line_end(bytes, pos);
+                debug!(
+                    "Skipping psql meta-command `{}`",
+                    String::from_utf8_lossy(&bytes[pos..end]).trim_end()
+                );
+                if current.is_some() {
+                    meta_commands.push(ByteRange::new(pos, end));
+                }
+                pos = end;
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                pos = find(bytes, pos + 2, b"*/").map_or(bytes.len(), |end| end + 2);
+                line_start = false;
+                continue;
+            }
+            b'\'' | b'"' | b'`' => quoted_end(bytes, pos, byte),
+            b'$' => dollar_quoted_end(bytes, pos).unwrap_or(pos + 1),
+            _ => pos + 1,
+        };
+        let start = current.map_or(pos, |(start, _)| start);
+        current = Some((start, end));
+        line_start = false;
+        pos = end;
+    }
+    if let Some((start, end)) = current {
+        finish(start, end, &mut comments, &mut meta_commands);
+    }
+    statements
+}
+
+/// Longest summary, longer ones are cut
+const MAX_SUMMARY_CHARS: usize = 60;
+/// Tokens looked at for the object of a statement
+const SUMMARY_TOKENS: usize = 64;
+/// Words between the verb of a DML statement and its table, `INSERT OR IGNORE INTO users`
+const DML_MODIFIERS: &[&str] = &["INTO", "FROM", "OR", "REPLACE", "IGNORE", "ONLY"];
+/// Words between the verb of a DDL statement and its object that aren't part of the summary
+const DDL_MODIFIERS: &[&str] = &[
+    "OR",
+    "REPLACE",
+    "IF",
+    "NOT",
+    "EXISTS",
+    "TEMP",
+    "TEMPORARY",
+    "CONCURRENTLY",
+];
+/// Kinds of objects a DDL statement creates, changes or drops
+const OBJECT_KINDS: &[&str] = &[
+    "TABLE",
+    "INDEX",
+    "UNIQUE",
+    "VIEW",
+    "MATERIALIZED",
+    "SEQUENCE",
+    "SCHEMA",
+    "FUNCTION",
+    "PROCEDURE",
+    "TRIGGER",
+    "TYPE",
+    "EXTENSION",
+    "DATABASE",
+];
+
+/// Short name of a statement from its leading verb and the object it works on, like
+/// `UPDATE users` or `CREATE INDEX idx_users_email`. `None` for statements it can't make out.
+pub(crate) fn summary(content: &str) -> Option<String> {
+    let tokens = summary_tokens(content);
+    let verb = tokens.first()?.to_ascii_uppercase();
+    let rest = &tokens[1..];
+    let is =
+        |token: &str, keywords: &[&str]| keywords.iter().any(|k| token.eq_ignore_ascii_case(k));
+
+    let (kinds, object): (Vec<String>, Option<&str>) = match verb.as_str() {
+        "SELECT" => {
+            let mut depth = 0_usize;
+            let from = rest.iter().position(|&token| {
+                match token {
+                    "(" => depth += 1,
+                    ")" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                depth == 0 && is(token, &["FROM"])
+            });
+            (vec![], from.and_then(|from| rest.get(from + 1)).copied())
+        }
+        "INSERT" | "REPLACE" | "UPDATE" | "DELETE" | "MERGE" => (
+            vec![],
+            rest.iter()
+                .copied()
+                .find(|&token| !is(token, DML_MODIFIERS)),
+        ),
+        "CREATE" | "DROP" | "ALTER" | "TRUNCATE" => {
+            let mut kinds = vec![];
+            let mut object = None;
+            for &token in rest {
+                if is(token, OBJECT_KINDS) {
+                    kinds.push(token.to_ascii_uppercase());
+                } else if !is(token, DDL_MODIFIERS) {
+                    object = Some(token).filter(|token| !is(token, &["ON"]));
+                    break;
+                }
+            }
+            (kinds, object)
+        }
+        _ => return None,
+    };
+    // Template placeholders and punctuation don't name anything
+    let object = object.filter(|object| {
+        object
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || "\"`[".contains(c))
+            && !object.chars().all(|c| c == '_')
+    });
+
+    let summary = std::iter::once(verb)
+        .chain(kinds)
+        .chain(object.map(str::to_string))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(summary.chars().take(MAX_SUMMARY_CHARS).collect())
+}
+
+/// The first tokens of `content`, dotted and quoted names as one token and any other
+/// character that isn't whitespace on its own. Comments and literals are skipped.
+fn summary_tokens(content: &str) -> Vec<&str> {
+    let bytes = content.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || !b.is_ascii();
+    let mut tokens = vec![];
+    let mut pos = 0;
+
+    while pos < bytes.len() && tokens.len() < SUMMARY_TOKENS {
+        let byte = bytes[pos];
+        let next = bytes.get(pos + 1).copied();
+        match byte {
+            _ if byte.is_ascii_whitespace() => pos += 1,
+            b'-' if next == Some(b'-') => pos = line_end(bytes, pos),
+            b'/' if next == Some(b'*') => {
+                pos = find(bytes, pos + 2, b"*/").map_or(bytes.len(), |end| end + 2);
+            }
+            b'\'' => pos = quoted_end(bytes, pos, byte),
+            _ if is_word(byte) || matches!(byte, b'"' | b'`' | b'[') => {
+                let start = pos;
+                loop {
+                    pos = match bytes.get(pos) {
+                        Some(&quote @ (b'"' | b'`')) => quoted_end(bytes, pos, quote),
+                        Some(b'[') => find(bytes, pos, b"]").map_or(bytes.len(), |end| end + 1),
+                        Some(&b) if is_word(b) => pos + 1,
+                        _ => break,
+                    };
+                    if bytes.get(pos) == Some(&b'.') {
+                        pos += 1;
+                    }
+                }
+                tokens.push(&content[start..pos]);
+            }
+            _ => {
+                let len = content[pos..].chars().next().map_or(1, char::len_utf8);
+                tokens.push(&content[pos..pos + len]);
+                pos += len;
+            }
+        }
+    }
+    tokens
+}
+
+/// End of a literal or identifier opened by `quote` at `start`, doubled quotes escape it.
+/// An unterminated one runs to the end of the file.
+fn quoted_end(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut pos = start + 1;
+    while pos < bytes.len() {
+        if bytes[pos] == quote {
+            if bytes.get(pos + 1) == Some(&quote) {
+                pos += 2;
+                continue;
+            }
+            return pos + 1;
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+/// End of a Postgres dollar quoted string at `start`, `$$ ... $$` or `$body$ ... $body$`, as
+/// function bodies are written. Positional parameters like `$1` and identifiers containing a
+/// `$` don't open one.
+fn dollar_quoted_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+    if start > 0 && (is_ident(bytes[start - 1]) || bytes[start - 1] == b'$') {
+        return None;
+    }
+    let tag_len = bytes[start + 1..].iter().position(|&b| !is_ident(b))?;
+    let tag_end = start + 1 + tag_len;
+    if bytes.get(tag_end) != Some(&b'$') || bytes.get(start + 1).is_some_and(u8::is_ascii_digit) {
+        return None;
+    }
+    let delimiter = &bytes[start..=tag_end];
+    Some(find(bytes, tag_end + 1, delimiter).map_or(bytes.len(), |end| end + delimiter.len()))
+}
+
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |pos| start + pos)
+}
+
+fn find(bytes: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(start..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| start + pos)
+}
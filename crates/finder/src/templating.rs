@@ -0,0 +1,59 @@
+// Pre-pass for Jinja templated SQL files like dbt models. `{{ ... }}` expressions become a
+// placeholder identifier, `{% ... %}` tags and `{# ... #}` comments are blanked out. Every
+// replacement keeps the byte length and the line breaks of what it replaces, positions in the
+// result are those of the template.
+//
+// An expression where a statement starts, `{{ config(materialized='table') }}` on top of a
+// model, can't be a value and is blanked out as well.
+
+const EXPRESSION: (&str, &str) = ("{{", "}}");
+const TAG: (&str, &str) = ("{%", "%}");
+const COMMENT: (&str, &str) = ("{#", "#}");
+
+/// `src` with its template syntax replaced.
+pub(crate) fn strip_templates(src: &str) -> String {
+    let mut stripped = String::with_capacity(src.len());
+    let mut pos = 0;
+    // Whether nothing but whitespace was written since the start or the last semicolon
+    let mut statement_start = true;
+
+    while let Some((offset, (open, close))) = next_delimiter(&src[pos..]) {
+        let text = &src[pos..pos + offset];
+        stripped.push_str(text);
+        if let Some(last) = text.trim_end().chars().last() {
+            statement_start = last == ';';
+        }
+
+        let start = pos + offset;
+        let end = src[start + open.len()..]
+            .find(close)
+            .map_or(src.len(), |end| start + open.len() + end + close.len());
+        let placeholder = open == EXPRESSION.0 && !statement_start;
+        blank(&mut stripped, &src[start..end], placeholder);
+        statement_start &= !placeholder;
+        pos = end;
+    }
+    stripped.push_str(&src[pos..]);
+    stripped
+}
+
+fn next_delimiter(text: &str) -> Option<(usize, (&'static str, &'static str))> {
+    [EXPRESSION, TAG, COMMENT]
+        .into_iter()
+        .filter_map(|delimiters| Some((text.find(delimiters.0)?, delimiters)))
+        .min_by_key(|(offset, _)| *offset)
+}
+
+/// Replaces `template` by spaces, or by underscores up to its first line break for a
+/// placeholder. Line breaks are kept.
+fn blank(stripped: &mut String, template: &str, placeholder: bool) {
+    let mut fill = if placeholder { '_' } else { ' ' };
+    for c in template.chars() {
+        if c == '\n' || c == '\r' {
+            stripped.push(c);
+            fill = ' ';
+        } else {
+            stripped.extend(std::iter::repeat_n(fill, c.len_utf8()));
+        }
+    }
+}
@@ -3,7 +3,7 @@
 mod tests {
 
     use crate::*;
-    use preanalysis::PreanalyzedFile;
+    use preanalysis::{PreanalyzedFile, Suppression};
     use rustpython_parser::{
         Parse,
         ast::{self},
@@ -15,6 +15,7 @@ mod tests {
             "sql".to_string(),
             "also_query".to_string(),
             "queries".to_string(),
+            "*_sql".to_string(),
         ];
         let func_ctx = vec![
             "execute".to_string(),
@@ -25,15 +26,29 @@ mod tests {
             "also_query_fun".to_string(),
             "outer_func".to_string(),
         ];
-        let class_ctx = ["Ok".to_string()];
-        SqlFinder::new(FinderConfig::new(&variable_ctx, &func_ctx).into())
+        SqlFinder::new(FinderConfig::new(&variable_ctx, &func_ctx, true).into())
+    }
+
+    /// SQL strings found in `code` by a finder with `config`.
+    fn harness_analyze(config: FinderConfig, code: &str) -> Vec<SqlString> {
+        SqlFinder::new(config.into())
+            .analyze_python_src(code, "test.py")
+            .unwrap()
+    }
+
+    /// Names and SQL of the strings found in `code` by a finder with `config`.
+    fn harness_find_with(config: FinderConfig, code: &str) -> Vec<(String, String)> {
+        harness_analyze(config, code)
+            .into_iter()
+            .map(|s| (s.variable_name, s.sql_content))
+            .collect()
     }
 
     fn harness_find(code: &str, expected: Vec<(&str, &str)>, name: &str) {
         let range_file = PreanalyzedFile::from_src(code);
         let parsed = ast::Suite::parse(code, "test.py").expect("Failed to parse");
         let finder = harness_create_test_finder();
-        let contexts = finder.analyze_stmts(&parsed, &range_file);
+        let contexts = finder.analyze_scope(&parsed, &range_file, None);
 
         println!("Parsed contexts: {contexts:?}");
         println!("Expected contexts: {expected:?}");
@@ -209,7 +224,10 @@ mod tests {
     fn subscript_assignment() {
         harness_find(
             r#"queries["query"] = "SELECT * FROM users WHERE username = ? OR email = ?""#,
-            vec![], // Subscripts not currently handled
+            vec![(
+                r#"queries["query"]"#,
+                "SELECT * FROM users WHERE username = ? OR email = ?",
+            )],
             "subscript assignment",
         );
     }
@@ -523,7 +541,7 @@ sql = "DELETE FROM temp_data"
 table = "users"
 query = f"select * from {table}"
             "#,
-            vec![("query", "select * from PLACEHOLDER")],
+            vec![("query", "select * from users")],
             "f-string simple variable substitution",
         );
     }
@@ -536,10 +554,7 @@ table = "users"
 status = "active"
 query = f"select * from {table} where status = '{status}'"
             "#,
-            vec![(
-                "query",
-                "select * from PLACEHOLDER where status = 'PLACEHOLDER'",
-            )],
+            vec![("query", "select * from users where status = 'active'")],
             "f-string multiple variables substitution",
         );
     }
@@ -552,10 +567,7 @@ table = "products"
 min_price = 100
 query = f"select * from {table} where price > {min_price}"
             "#,
-            vec![(
-                "query",
-                "select * from PLACEHOLDER where price > PLACEHOLDER",
-            )],
+            vec![("query", "select * from products where price > PLACEHOLDER")],
             "f-string with number substitution",
         );
     }
@@ -613,6 +625,23 @@ query = "select * from {0} where id = {1}".format("users", 123)
             vec![("query", "select * from users where id = 123")],
             "format method numbered substitution",
         );
+        harness_find(
+            r#"
+query = "select {1}, {0} from {0} where {0}.id = {2}".format("users", "name")
+            "#,
+            vec![(
+                "query",
+                "select name, users from users where users.id = PLACEHOLDER",
+            )],
+            "repeated and out of range indices",
+        );
+        harness_find(
+            r#"
+query = "select * from {} where id = {0}".format("users", 123)
+            "#,
+            vec![("query", "select * from PLACEHOLDER where id = PLACEHOLDER")],
+            "mixed automatic and manual numbering",
+        );
     }
 
     #[test]
@@ -632,7 +661,7 @@ query = f"""
             "#,
             vec![(
                 "query",
-                "\n    select \n        id,\n        name,\n        email\n    from PLACEHOLDER\n    where status = 'PLACEHOLDER'\n",
+                "\n    select \n        id,\n        name,\n        email\n    from users\n    where status = 'active'\n",
             )],
             "multiline f-string substitution",
         );
@@ -661,7 +690,6 @@ query = f"select * from {'table.' + 's'} where id > 5"
             "f-string with expression evaluation",
         );
     }
-    #[ignore]
     #[test]
     fn format_with_dictionary_unpacking() {
         harness_find(
@@ -677,6 +705,20 @@ query = "select * from {table} where status = '{status}' limit {limit}".format(*
         );
     }
 
+    #[test]
+    fn format_with_kwargs_and_dictionary_unpacking() {
+        harness_find(
+            r#"
+query = "select * from {table} where status = '{status}' limit {}".format(10, table="orders", **rest)
+            "#,
+            vec![(
+                "query",
+                "select * from orders where status = 'PLACEHOLDER' limit 10",
+            )],
+            "explicit kwargs are filled in, the rest are placeholders",
+        );
+    }
+
     #[test]
     fn percent_with_mixed_types() {
         harness_find(
@@ -698,7 +740,7 @@ query = "select * from %s where price > %.2f and quantity = %d" % ("products", 9
 table_name = "UsErS"
 query = f"select * from {table_name.lower()}"
             "#,
-            vec![("query", "select * from PLACEHOLDER")],
+            vec![("query", "select * from users")],
             "f-string with method call substitution",
         );
     }
@@ -738,7 +780,7 @@ prefix = "temp_"
 table = "users"
 query = "select * from {}".format(prefix + table)
             "#,
-            vec![("query", "select * from PLACEHOLDER")],
+            vec![("query", "select * from temp_users")],
             "format with string concatenation substitution",
         );
     }
@@ -840,7 +882,7 @@ query = "select * from users limit {}".format(20 * 5)
 table = "users"
 sql_fun(f"SELECT * FROM {table} WHERE active = 1")
         "#,
-            vec![("sql_fun", "SELECT * FROM PLACEHOLDER WHERE active = 1")],
+            vec![("sql_fun", "SELECT * FROM users WHERE active = 1")],
             "function call with f-string",
         );
     }
@@ -981,7 +1023,6 @@ def get_user_query():
         );
     }
 
-    #[ignore]
     #[test]
     fn lambda_with_function_call() {
         harness_find(
@@ -1014,8 +1055,8 @@ queries = {
 }
         "#,
             vec![
-                ("queries", "SELECT * FROM users"),
-                ("queries", "SELECT * FROM orders"),
+                (r#"queries["users"]"#, "SELECT * FROM users"),
+                (r#"queries["orders"]"#, "SELECT * FROM orders"),
             ],
             "function calls in dictionary context",
         );
@@ -1401,11 +1442,35 @@ query_fun(
 
     #[test]
     fn ignore_pragma_simple() {
-        harness_find(
+        let found = harness_find_single(
             r#"query = "SELECT id, name FROM users WHERE active = 1"  # sqint: ignore "#,
-            vec![],
-            "simple assignment",
         );
+        assert_eq!(found.suppressed, Some(Suppression::All));
+    }
+
+    #[test]
+    fn ignore_pragma_scoped_codes() {
+        let found = harness_find_single(
+            "execute(\n    \"SELECT * FROM users\",\n)  # sqint: ignore[SQ302, X001]\n",
+        );
+        assert!(found.is_suppressed("SQ302"));
+        assert!(found.is_suppressed("X001"));
+        assert!(!found.is_suppressed("SQ100"));
+    }
+
+    #[test]
+    fn ignore_pragma_scoped_codes_after_space() {
+        let found = harness_find_single("query = \"SELECT 1\"  # sqint: ignore [SQ501]\n");
+        assert!(found.is_suppressed("SQ501"));
+        assert!(!found.is_suppressed("SQ100"));
+    }
+
+    #[test]
+    fn ignore_pragma_needs_whole_word() {
+        for comment in ["# sqint: ignored", "# sqint: ignore-next-line"] {
+            let found = harness_find_single(&format!("query = \"SELECT 1\"  {comment}\n"));
+            assert_eq!(found.suppressed, None, "{comment}");
+        }
     }
 
     #[test]
@@ -1423,26 +1488,38 @@ query_fun(
             GROUP BY u.id, u.username, u.email
             HAVING COUNT(o.id) > 0
             ORDER BY total_spent DESC
-            LIMIT 100  # sqint: ignore"#;
+            LIMIT 100"#;
 
-        harness_find(
-            &format!(r#"query = """{}""""#, expected_sql),
-            vec![],
-            "multiline string assignment",
-        );
+        let found = harness_find_single(&format!(
+            r#"query = """{}"""  # sqint: ignore"#,
+            expected_sql
+        ));
+        assert_eq!(found.suppressed, Some(Suppression::All));
     }
 
     #[test]
     fn ignore_fully_dynamic_var() {
         harness_find(
             r#"
-s = "SELECT * FROM users"
+s = load_sql()
 query = s
         "#,
             vec![],
             "simple assignment",
         );
     }
+
+    #[test]
+    fn alias_of_constant() {
+        harness_find(
+            r#"
+base = "SELECT id, name FROM users"
+query = base
+        "#,
+            vec![("query", "SELECT id, name FROM users")],
+            "name bound to a string constant",
+        );
+    }
     #[test]
     fn fstring_sql_join_variables() {
         harness_find(
@@ -1470,7 +1547,7 @@ query = f"SELECT u.id, u.name, {', '.join(['p.title', 'p.price', 'p.category'])}
         "#,
             vec![(
                 "query",
-                "SELECT u.id, u.name, PLACEHOLDER FROM users u JOIN PLACEHOLDER WHERE u.id = PLACEHOLDER",
+                "SELECT u.id, u.name, p.title, p.price, p.category FROM users u JOIN orders o ON u.id = o.user_id JOIN products p ON o.product_id = p.id WHERE u.id = PLACEHOLDER",
             )],
             "f-string SQL with join operations on literal lists",
         );
@@ -1486,4 +1563,2764 @@ query = not_sql % (days, days)
             "f-string SQL with join operations on literal lists",
         );
     }
+
+    #[test]
+    fn fallback_extraction_on_unparseable_file() {
+        let code = r#"
+query = "SELECT id FROM users"
+def broken(:
+    pass
+sql: str = 'DELETE FROM sessions WHERE expired = 1'
+execute("UPDATE users SET active = 0")
+# execute("SELECT commented_out")
+unrelated = "SELECT 1"
+"#;
+        let found = harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .expect("fallback extraction should recover the file");
+
+        let found: Vec<_> = found
+            .iter()
+            .map(|s| {
+                assert!(s.approximate, "{} should be approximate", s.variable_name);
+                (
+                    s.variable_name.as_str(),
+                    s.sql_content.as_str(),
+                    s.range.start.to_string(),
+                    s.range.end.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "query",
+                    "SELECT id FROM users",
+                    "2:9:".to_string(),
+                    "2:31:".to_string()
+                ),
+                (
+                    "sql",
+                    "DELETE FROM sessions WHERE expired = 1",
+                    "5:12:".to_string(),
+                    "5:52:".to_string()
+                ),
+                (
+                    "execute",
+                    "UPDATE users SET active = 0",
+                    "6:1:".to_string(),
+                    "6:38:".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn fallback_extraction_disabled() {
+        let finder = SqlFinder::new(
+            FinderConfig::new(&["query".to_string()], &[], true)
+                .with_fallback_extraction(false)
+                .into(),
+        );
+        assert!(
+            finder
+                .analyze_python_src("query = \"SELECT 1\"\ndef broken(:\n", "test.py")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parseable_file_is_not_approximate() {
+        let found = harness_create_test_finder()
+            .analyze_python_src(r#"query = "SELECT 1""#, "test.py")
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].approximate);
+    }
+
+    fn harness_find_single(code: &str) -> SqlString {
+        let mut found = harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .unwrap();
+        assert_eq!(
+            found.len(),
+            1,
+            "Expected a single SQL string, found {found:?}"
+        );
+        found.remove(0)
+    }
+
+    #[test]
+    fn implicit_concat_pure_literals() {
+        let found = harness_find_single(
+            r#"query = (
+    "SELECT id, name "
+    "FROM users "
+    "ORDER BY name"
+)"#,
+        );
+        assert_eq!(
+            found.sql_content,
+            "SELECT id, name FROM users ORDER BY name"
+        );
+        assert_eq!(found.range.start.to_string(), "2:5:");
+        assert_eq!(found.range.end.to_string(), "4:20:");
+        assert!(found.dynamic_spans.is_empty());
+    }
+
+    #[test]
+    fn implicit_concat_mixed_fstring() {
+        let found = harness_find_single(
+            r#"query = (
+    "SELECT id, name "
+    "FROM users "
+    f"WHERE org = {org_id} "
+    "ORDER BY name"
+)"#,
+        );
+        assert_eq!(
+            found.sql_content,
+            "SELECT id, name FROM users WHERE org = PLACEHOLDER ORDER BY name"
+        );
+        assert_eq!(found.range.start.to_string(), "2:5:");
+        assert_eq!(found.range.end.to_string(), "5:20:");
+        assert_eq!(
+            found.dynamic_spans,
+            vec![DynamicSpan {
+                start: 39,
+                end: 50,
+                joined: false
+            }]
+        );
+    }
+
+    #[test]
+    fn implicit_concat_with_name_segment() {
+        let found = harness_find_single(
+            r#"query = (
+    "SELECT id, name "
+    "FROM users "
+    + where_clause
+    + " ORDER BY name"
+)"#,
+        );
+        assert_eq!(
+            found.sql_content,
+            "SELECT id, name FROM users PLACEHOLDER ORDER BY name"
+        );
+        assert_eq!(found.range.start.to_string(), "2:5:");
+        assert_eq!(found.range.end.to_string(), "5:23:");
+        assert_eq!(
+            found.dynamic_spans,
+            vec![DynamicSpan {
+                start: 27,
+                end: 38,
+                joined: false
+            }]
+        );
+    }
+
+    #[test]
+    fn relative_path_under_root() {
+        let root = std::path::Path::new("/home/dev/myrepo");
+        assert_eq!(
+            relative_path(&root.join("app").join("db.py"), root),
+            "app/db.py"
+        );
+    }
+
+    #[test]
+    fn relative_path_outside_root() {
+        assert_eq!(
+            relative_path(
+                std::path::Path::new("/tmp/elsewhere/db.py"),
+                std::path::Path::new("/home/dev/myrepo")
+            ),
+            "/tmp/elsewhere/db.py"
+        );
+    }
+
+    #[test]
+    fn extract_path_is_relative_to_cwd() {
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let extract = SqlExtract::new(
+            cwd.join("app").join("db.py").to_string_lossy().to_string(),
+            vec![],
+            PreanalyzedFile::from_src("").into(),
+        );
+        assert_eq!(extract.rel_path, "app/db.py");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_separators() {
+        let root = std::path::Path::new(r"C:\repo");
+        let extract = SqlExtract {
+            file_path: r"C:\repo\app\db.py".to_string(),
+            strings: vec![],
+            rel_path: relative_path(std::path::Path::new(r"C:\repo\app\db.py"), root),
+            source: PreanalyzedFile::from_src("").into(),
+            explanations: vec![],
+        };
+        assert_eq!(extract.rel_path, "app/db.py");
+        assert_eq!(extract.display_path(), r"app\db.py");
+    }
+
+    fn harness_find_asserts(code: &str) -> Vec<(String, String, String)> {
+        harness_analyze(
+            FinderConfig::new(&[], &[], true).with_assert_comparisons(true),
+            code,
+        )
+        .into_iter()
+        .map(|s| (s.variable_name, s.sql_content, s.range.start.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn assert_comparison_literal_on_right() {
+        assert_eq!(
+            harness_find_asserts(r#"assert str(qs.query) == "SELECT id FROM users""#),
+            vec![(
+                "<assert-expected>".to_string(),
+                "SELECT id FROM users".to_string(),
+                "1:25:".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn assert_comparison_literal_on_left() {
+        assert_eq!(
+            harness_find_asserts("def test_q():\n    assert 'DELETE FROM users' == build()"),
+            vec![(
+                "<assert-expected>".to_string(),
+                "DELETE FROM users".to_string(),
+                "2:12:".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn assert_comparison_non_sql_ignored() {
+        assert!(harness_find_asserts(r#"assert user.name == "Selena""#).is_empty());
+        assert!(harness_find_asserts(r#"assert query != "SELECT 1""#).is_empty());
+    }
+
+    #[test]
+    fn preanalysis_built_once_and_kept_with_extract() {
+        let path =
+            std::env::temp_dir().join(format!("sqint_preanalysis_{}.py", std::process::id()));
+        std::fs::write(&path, "import db\n\nquery = \"SELECT id FROM users\"\n").unwrap();
+
+        let before = preanalysis::CONSTRUCTIONS.with(std::cell::Cell::get);
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), false)
+            .unwrap();
+        let after = preanalysis::CONSTRUCTIONS.with(std::cell::Cell::get);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(after - before, 1);
+        let line = extract.strings[0].range.start.line();
+        assert_eq!(
+            extract.source.line_text(line),
+            Some("query = \"SELECT id FROM users\"")
+        );
+    }
+
+    #[test]
+    fn print_and_logging_excluded_by_default() {
+        let finder = SqlFinder::new(FinderConfig::new(&[], &["*".to_string()], true).into());
+        let src = "print(\"SELECT * FROM users\")\nself.logger.info(\"SELECT 1\")\n";
+        assert!(
+            finder
+                .analyze_python_src(src, "test.py")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn emptied_exclusions_extract_print() {
+        let finder = SqlFinder::new(
+            FinderConfig::new(&[], &["*".to_string()], true)
+                .with_excluded_function_contexts(&[])
+                .into(),
+        );
+        let found = finder
+            .analyze_python_src("print(\"SELECT * FROM users\")", "test.py")
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].variable_name, "print");
+        assert_eq!(found[0].sql_content, "SELECT * FROM users");
+    }
+
+    #[test]
+    fn excluded_calls_explained() {
+        let path = std::env::temp_dir().join(format!("sqint_explain_{}.py", std::process::id()));
+        std::fs::write(
+            &path,
+            "import logging
+logging.info(\"SELECT * FROM users\")
+execute(\"SELECT 1\")
+",
+        )
+        .unwrap();
+        let analyze = |explain| {
+            SqlFinder::new(
+                FinderConfig::new(&[], &["*".to_string()], true)
+                    .with_explain_extraction(explain)
+                    .into(),
+            )
+            .analyze_file(path.to_str().unwrap(), false)
+            .unwrap()
+        };
+        let extract = analyze(true);
+        let quiet = analyze(false);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(extract.strings.len(), 1);
+        let explained: Vec<_> = extract
+            .explanations
+            .iter()
+            .map(|e| {
+                (
+                    e.function_name.as_str(),
+                    e.reason.as_str(),
+                    e.range.start.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            explained,
+            vec![(
+                "logging.info",
+                "call matches excluded_function_contexts",
+                "2:1:".to_string()
+            )]
+        );
+        assert!(quiet.explanations.is_empty());
+    }
+
+    #[test]
+    fn analyze_file_unreadable() {
+        let err = harness_create_test_finder()
+            .analyze_file("does/not/exist.py", false)
+            .unwrap_err();
+        assert!(
+            matches!(&err, AnalyzeError::Io { path, source } if path == "does/not/exist.py" && source.kind() == std::io::ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn analyze_file_python_parse_error() {
+        let path = std::env::temp_dir().join(format!("sqint_parse_err_{}.py", std::process::id()));
+        std::fs::write(&path, "query = \"SELECT 1\"\ndef broken(:\n").unwrap();
+
+        let mut finder = SqlFinder::new(
+            FinderConfig::new(&["query".to_string()], &[], true)
+                .with_fallback_extraction(false)
+                .into(),
+        );
+        let err = finder
+            .analyze_file(path.to_str().unwrap(), false)
+            .unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        let AnalyzeError::PythonParse { location, .. } = err else {
+            panic!("expected a parse error, got {err:?}");
+        };
+        assert_eq!(location.line(), 2);
+    }
+
+    #[test]
+    fn analyze_notebook_code_cells() {
+        let path =
+            std::env::temp_dir().join(format!("sqint_notebook_{}.ipynb", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": ["query = \"SELECT * FROM docs\""]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": null,
+   "metadata": {"tags": ["sql"]},
+   "outputs": [],
+   "source": [
+    "%load_ext sql\n",
+    "if ready:\n",
+    "    query = \"SELECT id FROM users\"\n",
+    "!ls"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "metadata": {},
+   "source": "sql = \"DELETE FROM sessions\"\nquery = sql"
+  }
+ ],
+ "nbformat": 4
+}
+"#,
+        )
+        .unwrap();
+
+        let extract = harness_create_test_finder().analyze_file(path.to_str().unwrap(), false);
+        std::fs::remove_file(&path).unwrap();
+        let found: Vec<(String, usize)> = extract
+            .unwrap()
+            .strings
+            .into_iter()
+            .map(|s| (s.sql_content, s.range.start.line()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("SELECT id FROM users".to_string(), 16),
+                ("DELETE FROM sessions".to_string(), 23),
+                ("DELETE FROM sessions".to_string(), 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn analyze_file_notebook_parse_error() {
+        let path = std::env::temp_dir().join(format!("sqint_bad_nb_{}.ipynb", std::process::id()));
+        std::fs::write(&path, "{\n \"cells\": [\n  {\"source\": \"x\",]\n}\n").unwrap();
+
+        let err = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), false)
+            .unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        let AnalyzeError::NotebookParse { location, .. } = err else {
+            panic!("expected a notebook parse error, got {err:?}");
+        };
+        assert_eq!(location.line(), 3);
+    }
+
+    #[test]
+    fn analyze_file_without_sql_is_empty_extract() {
+        let path = std::env::temp_dir().join(format!("sqint_no_sql_{}.py", std::process::id()));
+        std::fs::write(&path, "name = \"Selena\"\n").unwrap();
+
+        let extract = harness_create_test_finder().analyze_file(path.to_str().unwrap(), false);
+        std::fs::remove_file(&path).unwrap();
+        assert!(extract.unwrap().strings.is_empty());
+    }
+
+    fn harness_find_calls(pattern: &str, code: &str) -> Vec<String> {
+        harness_analyze(FinderConfig::new(&[], &[pattern.to_string()], true), code)
+            .into_iter()
+            .map(|s| s.variable_name)
+            .collect()
+    }
+
+    #[test]
+    fn call_through_one_intermediate_call() {
+        let code = r#"self.db.cursor().execute("UPDATE users SET active = 0")"#;
+        assert_eq!(
+            harness_find_calls("*execute", code),
+            vec!["self.db.cursor.execute"]
+        );
+        assert_eq!(
+            harness_find_calls("*.execute", code),
+            vec!["self.db.cursor.execute"]
+        );
+    }
+
+    #[test]
+    fn call_through_two_intermediate_calls() {
+        let code = r#"get_pool().connection().execute("DELETE FROM sessions")"#;
+        assert_eq!(
+            harness_find_calls("*.execute", code),
+            vec!["get_pool.connection.execute"]
+        );
+    }
+
+    #[test]
+    fn call_on_subscripted_receiver() {
+        let code = r#"pools["main"].execute("SELECT id FROM users")"#;
+        assert_eq!(harness_find_calls("*.execute", code), vec!["pools.execute"]);
+    }
+
+    #[test]
+    fn stub_ellipsis_defaults_ignored() {
+        let stub = "query: str = ...\nclass Repo:\n    sql: str = ...\n    def fetch(self, query: str = ...) -> None: ...\n";
+        assert!(
+            harness_create_test_finder()
+                .analyze_python_src(stub, "repo.pyi")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn stub_real_sql_default_found() {
+        let found = harness_find_single("query: str = \"SELECT id FROM users\"\nsql: str = ...\n");
+        assert_eq!(found.sql_content, "SELECT id FROM users");
+    }
+
+    fn harness_find_all(code: &str) -> Vec<(String, String)> {
+        harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.variable_name, s.sql_content))
+            .collect()
+    }
+
+    #[test]
+    fn aug_assign_plain_literal() {
+        let code = r#"
+def active_users(only_active):
+    query = "SELECT * FROM users"
+    if only_active:
+        query += " WHERE active = 1"
+    return query
+"#;
+        assert_eq!(
+            harness_find_all(code),
+            vec![
+                ("query".to_string(), "SELECT * FROM users".to_string()),
+                (
+                    "query".to_string(),
+                    "SELECT * FROM users WHERE active = 1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn aug_assign_fstring() {
+        let code = r#"
+query = "SELECT * FROM users WHERE 1 = 1"
+query += f" AND team = '{team}'"
+query += " ORDER BY id"
+"#;
+        assert_eq!(
+            harness_find_all(code)
+                .into_iter()
+                .map(|(_, sql)| sql)
+                .collect::<Vec<_>>(),
+            vec![
+                "SELECT * FROM users WHERE 1 = 1",
+                "SELECT * FROM users WHERE 1 = 1 AND team = 'PLACEHOLDER'",
+                "SELECT * FROM users WHERE 1 = 1 AND team = 'PLACEHOLDER' ORDER BY id",
+            ]
+        );
+    }
+
+    #[test]
+    fn aug_assign_format_call() {
+        let code = r#"
+sql = "SELECT id FROM orders"
+sql += " WHERE status = '{}'".format("open")
+"#;
+        assert_eq!(
+            harness_find_all(code)[1].1,
+            "SELECT id FROM orders WHERE status = 'open'"
+        );
+    }
+
+    #[test]
+    fn aug_assign_range_and_unknown_base() {
+        let found = harness_create_test_finder()
+            .analyze_python_src(
+                "query = \"SELECT 1\"\nquery += \" UNION SELECT 2\"\n",
+                "test.py",
+            )
+            .unwrap();
+        assert_eq!(found[1].range.start.to_string(), "2:10:");
+
+        // The initial value isn't a literal, so the fragment alone can't be checked
+        assert!(harness_find_all("query = build()\nquery += \" WHERE id = 1\"\n").is_empty());
+    }
+
+    #[test]
+    fn textwrap_dedent_spaces() {
+        harness_find(
+            "import textwrap\nquery = textwrap.dedent(\"\"\"\n    SELECT id\n      FROM users\n\n    WHERE active = 1\n\"\"\")",
+            vec![("query", "\nSELECT id\n  FROM users\n\nWHERE active = 1\n")],
+            "textwrap.dedent with spaces",
+        );
+    }
+
+    #[test]
+    fn bare_dedent_tabs_and_spaces() {
+        // Tabs and spaces aren't a common margin, only the shared tab is removed
+        harness_find(
+            "from textwrap import dedent\nquery = dedent(\"\\tSELECT id\\n\\t  FROM users\\n\\t\\tWHERE x = 1\")",
+            vec![("query", "SELECT id\n  FROM users\n\tWHERE x = 1")],
+            "dedent with tabs",
+        );
+        harness_find(
+            "query = dedent(\"\\tSELECT id\\n    FROM users\")",
+            vec![("query", "\tSELECT id\n    FROM users")],
+            "dedent with mixed margins",
+        );
+    }
+
+    #[test]
+    fn dedent_first_line_after_quotes() {
+        // The unindented first line leaves no common margin, like at runtime
+        harness_find(
+            "query = dedent(\"\"\"SELECT id\n    FROM users\"\"\")",
+            vec![("query", "SELECT id\n    FROM users")],
+            "dedent first line on the quotes",
+        );
+    }
+
+    #[test]
+    fn join_literal_fragments() {
+        harness_find(
+            r#"query = " ".join(["SELECT id", "FROM users", "WHERE active = 1"])"#,
+            vec![("query", "SELECT id FROM users WHERE active = 1")],
+            "join over a literal list",
+        );
+        harness_find(
+            r#"query = "\n".join(("SELECT id", "FROM users"))"#,
+            vec![("query", "SELECT id\nFROM users")],
+            "join over a literal tuple",
+        );
+    }
+
+    #[test]
+    fn join_literal_fragments_with_runtime_elements() {
+        harness_find(
+            r#"query = " ".join(["SELECT", columns, "FROM users WHERE id =", get_id()])"#,
+            vec![(
+                "query",
+                "SELECT PLACEHOLDER FROM users WHERE id = PLACEHOLDER",
+            )],
+            "join with names and calls",
+        );
+    }
+
+    #[test]
+    fn join_range_covers_call() {
+        let found = harness_find_single(r#"query = " ".join(["SELECT 1", "UNION SELECT 2"])"#);
+        assert_eq!(found.range.start.to_string(), "1:9:");
+        assert_eq!(found.range.end.col(), 49);
+    }
+
+    #[test]
+    fn walrus_in_if_condition() {
+        harness_find(
+            r#"
+if (query := f"SELECT * FROM {table}") and dry_run:
+    print(query)
+"#,
+            vec![("query", "SELECT * FROM PLACEHOLDER")],
+            "walrus in if condition",
+        );
+    }
+
+    #[test]
+    fn walrus_in_call_argument_and_comprehension() {
+        harness_find(
+            r#"
+def run(db):
+    db.send((sql := "DELETE FROM sessions"))
+    return [r for r in rows if (also_query := "SELECT 1")]
+"#,
+            vec![("sql", "DELETE FROM sessions"), ("also_query", "SELECT 1")],
+            "walrus in call argument and comprehension condition",
+        );
+    }
+
+    #[test]
+    fn walrus_non_sql_name_ignored() {
+        harness_find(
+            r#"
+while (chunk := "SELECT 1"):
+    pass
+"#,
+            vec![],
+            "walrus to a non sql name",
+        );
+    }
+
+    #[test]
+    fn dict_entries_named_by_key() {
+        harness_find(
+            r#"queries = {"get_user": "SELECT * FROM users WHERE id = ?", 1: "SELECT 1", **extra, "nested": {"del": "DELETE FROM users"}}"#,
+            vec![
+                (r#"queries["get_user"]"#, "SELECT * FROM users WHERE id = ?"),
+                ("queries", "SELECT 1"),
+                (r#"queries["nested"]["del"]"#, "DELETE FROM users"),
+            ],
+            "dict entries named by key",
+        );
+    }
+
+    #[test]
+    fn dict_broken_entry_range() {
+        let entries: String = (1..=20)
+            .map(|i| format!("    \"q{i}\": \"SELECT {i}\",\n"))
+            .collect();
+        let code = format!("queries = {{\n{entries}    \"broken\": \"SELEC id FROM users\",\n}}\n");
+        let found = harness_create_test_finder()
+            .analyze_python_src(&code, "test.py")
+            .unwrap();
+        let broken = found
+            .iter()
+            .find(|s| s.variable_name == r#"queries["broken"]"#)
+            .unwrap();
+        assert_eq!(broken.range.start.to_string(), "22:15:");
+        assert_eq!(broken.range.end.to_string(), "22:36:");
+    }
+
+    #[test]
+    fn subscript_assignment_matched_by_key_or_base() {
+        harness_find(
+            r#"
+QUERIES["fetch_orders"] = """SELECT * FROM orders"""
+cache["sql"] = "SELECT 1"
+cache["name"] = "SELECT 2"
+queries[key] = "SELECT 3"
+"#,
+            vec![
+                // Neither `QUERIES` nor `fetch_orders` is a variable context
+                (r#"cache["sql"]"#, "SELECT 1"),
+                ("queries[...]", "SELECT 3"),
+            ],
+            "subscript assignment matched by key or base",
+        );
+    }
+
+    #[test]
+    fn nested_subscript_assignment() {
+        harness_find(
+            r#"self.queries["orders"]["fetch"] = "SELECT * FROM orders""#,
+            vec![(r#"queries["orders"]["fetch"]"#, "SELECT * FROM orders")],
+            "nested subscript assignment",
+        );
+    }
+
+    #[test]
+    fn augmented_subscript_assignment() {
+        harness_find(
+            r#"
+queries["users"] = "SELECT * FROM users"
+queries["users"] += " WHERE active = 1"
+"#,
+            vec![
+                (r#"queries["users"]"#, "SELECT * FROM users"),
+                (
+                    r#"queries["users"]"#,
+                    "SELECT * FROM users WHERE active = 1",
+                ),
+            ],
+            "augmented subscript assignment",
+        );
+    }
+
+    fn harness_find_returns(code: &str) -> Vec<(String, String)> {
+        harness_find_with(
+            FinderConfig::new(&[], &[], true).with_return_contexts(&["get_*_query".to_string()]),
+            code,
+        )
+    }
+
+    #[test]
+    fn return_from_matching_function() {
+        let code = r#"
+def get_user_query(active):
+    if active:
+        return "SELECT * FROM users WHERE active = 1"
+    try:
+        return f"SELECT * FROM users WHERE id = {user_id}"
+    finally:
+        pass
+
+def build_report():
+    return "SELECT * FROM reports"
+"#;
+        assert_eq!(
+            harness_find_returns(code),
+            vec![
+                (
+                    "get_user_query".to_string(),
+                    "SELECT * FROM users WHERE active = 1".to_string()
+                ),
+                (
+                    "get_user_query".to_string(),
+                    "SELECT * FROM users WHERE id = PLACEHOLDER".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn return_uses_innermost_function() {
+        let code = r#"
+class Repo:
+    async def get_order_query(self):
+        def helper():
+            return "SELECT 1"
+        return "SELECT * FROM orders"
+"#;
+        assert_eq!(
+            harness_find_returns(code),
+            vec![(
+                "get_order_query".to_string(),
+                "SELECT * FROM orders".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn return_contexts_off_by_default() {
+        harness_find(
+            "def get_user_query():\n    return \"SELECT 1\"\n",
+            vec![],
+            "returns without return_contexts",
+        );
+    }
+
+    #[test]
+    fn default_argument_literal() {
+        let found = harness_find_single(
+            "def run(db, query=\"SELECT * FROM audit_log WHERE ts > ?\"):\n    db.execute(query)\n",
+        );
+        assert_eq!(found.variable_name, "query");
+        assert_eq!(found.sql_content, "SELECT * FROM audit_log WHERE ts > ?");
+        assert_eq!(found.range.start.to_string(), "1:19:");
+    }
+
+    #[test]
+    fn default_argument_fstring_and_kwonly() {
+        harness_find(
+            r#"
+async def run(db, /, sql=f"SELECT * FROM {TABLE}", *, also_query="DELETE FROM t", limit=10):
+    pass
+"#,
+            vec![
+                ("sql", "SELECT * FROM PLACEHOLDER"),
+                ("also_query", "DELETE FROM t"),
+            ],
+            "f-string and keyword-only defaults",
+        );
+    }
+
+    #[test]
+    fn default_argument_call_and_none() {
+        harness_find(
+            r#"
+def run(query=query_fun("SELECT 1"), sql=None, name="SELECT 2"):
+    pass
+"#,
+            vec![("query", "SELECT 1")],
+            "call and None defaults",
+        );
+    }
+
+    #[test]
+    fn yield_execute_call() {
+        harness_find(
+            r#"
+def batches(conn):
+    yield execute("SELECT * FROM batches WHERE id = %s")
+    yield
+"#,
+            vec![("execute", "SELECT * FROM batches WHERE id = %s")],
+            "yield of an execute call",
+        );
+    }
+
+    #[test]
+    fn yield_fstring_in_return_context() {
+        assert_eq!(
+            harness_find_returns(
+                "def get_batch_query(ids):\n    yield f\"SELECT * FROM batches WHERE id = {ids[0]}\"\n"
+            ),
+            vec![(
+                "get_batch_query".to_string(),
+                "SELECT * FROM batches WHERE id = PLACEHOLDER".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn yield_from_generator_of_calls() {
+        harness_find(
+            r#"
+def run_all(queries):
+    yield from (execute("DELETE FROM sessions") for _ in range(3))
+    yield from (execute(q) for q in queries)
+"#,
+            vec![("execute", "DELETE FROM sessions")],
+            "yield from a generator of calls",
+        );
+    }
+
+    #[test]
+    fn with_context_expression_call() {
+        harness_find(
+            r#"
+with engine.begin() as conn, execute("SELECT * FROM users") as result:
+    pass
+"#,
+            vec![("execute", "SELECT * FROM users")],
+            "call in with item",
+        );
+    }
+
+    #[test]
+    fn async_with_context_expression_call() {
+        harness_find(
+            r#"
+async def run(session):
+    async with session.execute("DELETE FROM sessions"):
+        pass
+"#,
+            vec![("session.execute", "DELETE FROM sessions")],
+            "trailing attribute matching a function context",
+        );
+        let found = harness_analyze(
+            FinderConfig::new(&[], &["*.execute".to_string()], true),
+            "async def run(session):\n    async with session.execute(\"DELETE FROM sessions\"):\n        pass\n",
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].variable_name, "session.execute");
+    }
+
+    #[test]
+    fn with_optional_vars_bound_like_assignment() {
+        harness_find(
+            r#"
+with sql_fun("SELECT id FROM users") as query:
+    pass
+"#,
+            vec![
+                ("sql_fun", "SELECT id FROM users"),
+                ("query", "SELECT id FROM users"),
+            ],
+            "with target named like a sql variable",
+        );
+    }
+
+    #[test]
+    fn match_guard_call() {
+        harness_find(
+            r#"
+match command:
+    case "purge" if execute("DELETE FROM sessions"):
+        pass
+    case _ if (query := "SELECT 1") and dry_run:
+        pass
+"#,
+            vec![("execute", "DELETE FROM sessions"), ("query", "SELECT 1")],
+            "calls and walrus bindings in match guards",
+        );
+    }
+
+    #[test]
+    fn match_subject_call_and_walrus() {
+        harness_find(
+            r#"
+match execute("SELECT id FROM users"):
+    case []:
+        pass
+
+match (sql := "SELECT 1"):
+    case _:
+        pass
+"#,
+            vec![("execute", "SELECT id FROM users"), ("sql", "SELECT 1")],
+            "call and walrus in match subject",
+        );
+    }
+
+    fn harness_find_class_members(code: &str) -> Vec<(String, String)> {
+        harness_find_with(
+            FinderConfig::new(&["*query*".to_string()], &[], true)
+                .with_class_contexts(&["*Queries".to_string()]),
+            code,
+        )
+    }
+
+    #[test]
+    fn class_context_members() {
+        let code = r#"
+class UserQueries:
+    GET_USER = "SELECT * FROM users WHERE id = ?"
+    LIST_USERS: str = "SELECT * FROM users"
+    count_query = "SELECT count(*) FROM users"
+    TIMEOUT = 30
+
+    def helper(self):
+        label = "not sql"
+
+class Config:
+    NAME = "SELECT lookalike"
+"#;
+        assert_eq!(
+            harness_find_class_members(code),
+            vec![
+                (
+                    "UserQueries.GET_USER".to_string(),
+                    "SELECT * FROM users WHERE id = ?".to_string()
+                ),
+                (
+                    "UserQueries.LIST_USERS".to_string(),
+                    "SELECT * FROM users".to_string()
+                ),
+                (
+                    "UserQueries.count_query".to_string(),
+                    "SELECT count(*) FROM users".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_members_that_look_like_sql() {
+        let code = r#"
+import enum
+
+class Statements(str, enum.Enum):
+    GET_USER = "SELECT * FROM users WHERE id = ?"
+    DELETE_USER = "DELETE FROM users WHERE id = ?"
+    LABEL = "users"
+
+class Color(Enum):
+    RED = "red"
+"#;
+        assert_eq!(
+            harness_find_class_members(code),
+            vec![
+                (
+                    "Statements.GET_USER".to_string(),
+                    "SELECT * FROM users WHERE id = ?".to_string()
+                ),
+                (
+                    "Statements.DELETE_USER".to_string(),
+                    "DELETE FROM users WHERE id = ?".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn dataclass_field_defaults() {
+        harness_find(
+            r#"
+import dataclasses
+from dataclasses import dataclass, field
+
+@dataclass
+class Settings:
+    user_sql: str = "SELECT * FROM users"
+    audit_sql: str = field(default="INSERT INTO audit (event) VALUES (?)")
+    cleanup_sql: str = dataclasses.field(default_factory=lambda: "DELETE FROM sessions")
+    retries_sql: list = field(default_factory=list)
+"#,
+            vec![
+                ("user_sql", "SELECT * FROM users"),
+                ("audit_sql", "INSERT INTO audit (event) VALUES (?)"),
+                ("cleanup_sql", "DELETE FROM sessions"),
+            ],
+            "dataclass field defaults",
+        );
+    }
+
+    #[test]
+    fn module_literals_opt_in() {
+        let code = r#""""SELECT docstrings are not queries."""
+
+"""
+SELECT id, name
+FROM users
+"""
+
+"show me"
+
+def f():
+    """SELECT nothing, a function docstring"""
+    "SELECT * FROM nested"
+"#;
+        let find = |enabled| {
+            harness_find_with(
+                FinderConfig::new(&[], &[], true).with_module_literals(enabled),
+                code,
+            )
+        };
+
+        assert!(find(false).is_empty());
+        assert_eq!(
+            find(true),
+            vec![(
+                "<module-literal>".to_string(),
+                "\nSELECT id, name\nFROM users\n".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn byte_string_literals() {
+        harness_find(
+            r#"
+query = b"COPY users FROM STDIN"
+execute(b"SELECT * FROM t WHERE id = ?")
+"#,
+            vec![
+                ("query", "COPY users FROM STDIN"),
+                ("execute", "SELECT * FROM t WHERE id = ?"),
+            ],
+            "byte literals in assignments and calls",
+        );
+    }
+
+    #[test]
+    fn assert_calls_and_walrus() {
+        harness_find(
+            r#"
+assert execute("SELECT * FORM x"), "bad query"
+assert ready, execute("SELECT 1")
+assert (sql := "SELECT * FROM users") and registered
+"#,
+            vec![
+                ("execute", "SELECT * FORM x"),
+                ("execute", "SELECT 1"),
+                ("sql", "SELECT * FROM users"),
+            ],
+            "calls and walrus bindings in asserts",
+        );
+    }
+
+    #[test]
+    fn raise_with_matched_calls() {
+        harness_find(
+            r#"
+raise QueryError(f"failed: {sql_fun('SELECT * FROM t WHERE id = ?')}")
+raise make_error(execute("DELETE FROM sessions")) from build(query_fun("SELECT 1"))
+raise QueryError("SELECT * FROM users failed")
+"#,
+            vec![
+                ("sql_fun", "SELECT * FROM t WHERE id = ?"),
+                ("execute", "DELETE FROM sessions"),
+                ("query_fun", "SELECT 1"),
+            ],
+            "matched calls in raised exceptions",
+        );
+    }
+
+    #[test]
+    fn lambdas_in_arguments_dicts_and_defaults() {
+        harness_find(
+            r#"
+retry(lambda: execute("SELECT * FROM jobs"))
+handlers = {"purge": lambda: sql_fun("DELETE FROM sessions")}
+run_later(lambda sql="SELECT 1", limit=10: run(sql))
+outer(lambda: (lambda: query_fun("SELECT 2"))())
+"#,
+            vec![
+                ("execute", "SELECT * FROM jobs"),
+                ("sql_fun", "DELETE FROM sessions"),
+                ("sql", "SELECT 1"),
+                ("query_fun", "SELECT 2"),
+            ],
+            "lambda bodies and defaults",
+        );
+    }
+
+    #[test]
+    fn lambda_ranges_point_inside() {
+        let default = harness_find_single(r#"run_later(lambda sql="SELECT 1": run(sql))"#);
+        assert_eq!(default.range.start.to_string(), "1:22:");
+
+        let call = harness_find_single(r#"retry(lambda: execute("SELECT * FROM jobs"))"#);
+        assert_eq!(call.range.start.to_string(), "1:15:");
+    }
+
+    #[test]
+    fn calls_in_comprehensions() {
+        harness_find(
+            r#"
+results = [execute(f"SELECT * FROM {t}") for t in tables]
+by_name = {name: sql_fun("SELECT 1") for name, q in pairs if query_fun("SELECT 2")}
+[execute("DELETE FROM sessions") for _ in range(3)]
+nested = [[query_fun("SELECT 3") for c in row] for row in grid]
+safe = [q for q in ["SELECT 4", "SELECT 5"]]
+"#,
+            vec![
+                ("execute", "SELECT * FROM PLACEHOLDER"),
+                ("sql_fun", "SELECT 1"),
+                ("query_fun", "SELECT 2"),
+                ("execute", "DELETE FROM sessions"),
+                ("query_fun", "SELECT 3"),
+            ],
+            "matched calls in comprehensions",
+        );
+    }
+
+    #[test]
+    fn generator_argument_over_literals() {
+        harness_find(
+            r#"
+run_all(execute(q) for q in ("SELECT 1", "SELECT 2 FORM t"))
+executemany("INSERT INTO t VALUES (%s)", (row for row in rows))
+run_all(sql_fun(q) for q in pending)
+"#,
+            vec![("execute", "SELECT 1"), ("execute", "SELECT 2 FORM t")],
+            "generator argument over literal iterable",
+        );
+    }
+
+    #[test]
+    fn passthrough_wrappers() {
+        let code = r#"
+session.execute(text("SELECT * FROM users WHERE id = :id"))
+session.execute(sa.text("SELECT 1"))
+text("SELECT 2")
+"#;
+        assert_eq!(
+            harness_find_calls("*execute", code),
+            vec!["session.execute", "session.execute"]
+        );
+
+        let unwrapped = harness_analyze(
+            FinderConfig::new(&[], &["*execute".to_string()], true).with_passthrough_wrappers(&[]),
+            code,
+        );
+        assert!(unwrapped.is_empty());
+    }
+
+    #[test]
+    fn partial_binding_sql() {
+        let code = r#"
+import functools
+from functools import partial
+
+job = functools.partial(cursor.execute, "UPDATE jobs SET state = %s WHERE id = %s")
+retry: Callable = partial(execute, sql="SELECT 1")
+noop = partial(print, "SELECT 2")
+"#;
+        assert_eq!(
+            harness_find_calls("*execute", code),
+            vec!["cursor.execute", "execute"]
+        );
+    }
+
+    #[test]
+    fn string_methods_applied() {
+        harness_find(
+            r#"
+query = "  SELECT * FROM users  ".strip()
+sql = "select * from :tbl where id = ?".replace(":tbl", "users").upper()
+also_query = "SELECT * FROM :tbl".replace(":tbl", table_name)
+queries = BASE_SQL.replace(":tbl", "users")
+"#,
+            vec![
+                ("query", "SELECT * FROM users"),
+                ("sql", "SELECT * FROM USERS WHERE ID = ?"),
+                ("also_query", "SELECT * FROM PLACEHOLDER"),
+            ],
+            "string methods on literals",
+        );
+    }
+
+    #[test]
+    fn format_map_literal_and_variable() {
+        harness_find(
+            r#"
+query = "SELECT * FROM {table} WHERE id = {id}".format_map({"table": "users", "id": 1})
+sql = "SELECT * FROM {table} WHERE id = {id}".format_map(params)
+also_query = "SELECT '{{literal}}', {name:>{width}} FROM t".format_map(params)
+"#,
+            vec![
+                ("query", "SELECT * FROM users WHERE id = 1"),
+                ("sql", "SELECT * FROM PLACEHOLDER WHERE id = PLACEHOLDER"),
+                ("also_query", "SELECT '{literal}', PLACEHOLDER FROM t"),
+            ],
+            "format_map with literal and variable mappings",
+        );
+    }
+
+    #[test]
+    fn template_substitution() {
+        harness_find(
+            r#"
+from string import Template
+import string
+
+query = Template("SELECT * FROM $table WHERE id = ${id}").substitute(table="users", id=1)
+sql = string.Template("SELECT $$1, $col FROM $table").safe_substitute({"table": "t"}, col=column)
+also_query = Template("DELETE FROM $table").substitute(mapping)
+"#,
+            vec![
+                ("query", "SELECT * FROM users WHERE id = 1"),
+                ("sql", "SELECT $1, PLACEHOLDER FROM t"),
+                ("also_query", "DELETE FROM PLACEHOLDER"),
+            ],
+            "string.Template substitution",
+        );
+    }
+
+    #[test]
+    fn fstring_format_specs_and_conversions() {
+        harness_find(
+            r#"
+query = f"SELECT * FROM t LIMIT {limit:>10}"
+sql = f"SELECT price FROM t WHERE price > {1.5:.2f}"
+also_query = f"SELECT * FROM t WHERE name = {'bob'!r}"
+queries = f"SELECT {value:{width}.{precision}} FROM t"
+"#,
+            vec![
+                ("query", "SELECT * FROM t LIMIT PLACEHOLDER"),
+                ("sql", "SELECT price FROM t WHERE price > PLACEHOLDER"),
+                ("also_query", "SELECT * FROM t WHERE name = PLACEHOLDER"),
+                ("queries", "SELECT PLACEHOLDER FROM t"),
+            ],
+            "f-string format specs and conversions",
+        );
+    }
+
+    #[test]
+    fn nested_fstrings() {
+        harness_find(
+            r#"
+query = f"SELECT * FROM {f'{schema}.{table}'} WHERE id = {user_id}"
+sql = f'''SELECT id FROM {f"{schema}.users"} AS u JOIN {f'orders_{suffix}'} o ON o.user_id = u.id'''
+"#,
+            vec![
+                ("query", "SELECT * FROM PLACEHOLDER WHERE id = PLACEHOLDER"),
+                (
+                    "sql",
+                    "SELECT id FROM PLACEHOLDER AS u JOIN PLACEHOLDER o ON o.user_id = u.id",
+                ),
+            ],
+            "nested f-strings",
+        );
+    }
+
+    #[test]
+    fn percent_formatting_specifiers() {
+        let cases = [
+            (r#""SELECT '%10s'" % "users""#, "SELECT '     users'"),
+            (r#""SELECT '%-5d|'" % 42"#, "SELECT '42   |'"),
+            (r#""SELECT %05.2f" % 3.14159"#, "SELECT 03.14"),
+            (r#""SELECT %d%% FROM t" % 50"#, "SELECT 50% FROM t"),
+            (r#""SELECT '%*d'" % (5, 42)"#, "SELECT '   42'"),
+            (r#""SELECT '%.3s'" % "abcdef""#, "SELECT 'abc'"),
+            (r#""SELECT %+d, % d" % (5, 7)"#, "SELECT +5,  7"),
+            (r#""SELECT %#x, %#o" % (255, 8)"#, "SELECT 0xff, 0o10"),
+            (r#""SELECT %(n)05d" % {"n": 7}"#, "SELECT 00007"),
+            (
+                r#""SELECT %ld, %.1e, %G" % (3, 1234.5, 0.00001)"#,
+                "SELECT 3, 1.2e+03, 1E-05",
+            ),
+            (r#""SELECT %r" % "users""#, "SELECT 'users'"),
+            (
+                r#""SELECT %5d FROM %s" % (limit, "t")"#,
+                "SELECT PLACEHOLDER FROM t",
+            ),
+        ];
+        for (expr, expected) in cases {
+            harness_find(&format!("query = {expr}"), vec![("query", expected)], expr);
+        }
+    }
+
+    #[test]
+    fn constant_concatenation() {
+        harness_find(
+            r#"
+base = "SELECT id, name FROM users"
+query = base + " WHERE active = 1"
+"#,
+            vec![("query", "SELECT id, name FROM users WHERE active = 1")],
+            "name bound to a literal resolves",
+        );
+    }
+
+    #[test]
+    fn constant_table_name_in_fstring() {
+        harness_find(
+            r#"
+def fetch(uid):
+    table = "users"
+    query = f"SELECT * FROM {table} WHERE id = {uid}"
+"#,
+            vec![("query", "SELECT * FROM users WHERE id = PLACEHOLDER")],
+            "constant in f-string, parameter stays a placeholder",
+        );
+    }
+
+    #[test]
+    fn constant_reassigned() {
+        harness_find(
+            r#"
+table = "users"
+table = get_table()
+query = f"SELECT * FROM {table}"
+"#,
+            vec![("query", "SELECT * FROM PLACEHOLDER")],
+            "reassignment to a runtime value drops the constant",
+        );
+        harness_find(
+            r#"
+table = "users"
+if archived:
+    table = "archived_users"
+query = f"SELECT * FROM {table}"
+"#,
+            vec![("query", "SELECT * FROM PLACEHOLDER")],
+            "assignment in a branch drops the constant",
+        );
+        harness_find(
+            r#"
+table = "users"
+def fetch():
+    query = f"SELECT * FROM {table}"
+"#,
+            vec![("query", "SELECT * FROM PLACEHOLDER")],
+            "constants don't leak into nested scopes",
+        );
+    }
+
+    fn harness_find_dataflow(code: &str) -> Vec<(String, String)> {
+        harness_find_with(
+            FinderConfig::new(&["query".to_string()], &["execute".to_string()], true)
+                .with_dataflow(true),
+            code,
+        )
+    }
+
+    #[test]
+    fn dataflow_branch_concatenation() {
+        let code = r#"
+def fetch(active, limit):
+    query = "SELECT * FROM users"
+    if active:
+        query = query + " WHERE active = 1"
+    query += " ORDER BY id"
+    execute(query)
+"#;
+        assert_eq!(
+            harness_find_dataflow(code),
+            vec![(
+                "query".to_string(),
+                "SELECT * FROM users WHERE active = 1 ORDER BY id".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn dataflow_diverging_branches() {
+        let code = r#"
+query = "SELECT * FROM users"
+if archived:
+    query = "SELECT * FROM archived_users"
+else:
+    query = query + " WHERE active = 1"
+query = query + " LIMIT 10"
+"#;
+        assert_eq!(
+            harness_find_dataflow(code),
+            vec![
+                (
+                    "query".to_string(),
+                    "SELECT * FROM archived_users".to_string()
+                ),
+                (
+                    "query".to_string(),
+                    "SELECT * FROM users WHERE active = 1".to_string()
+                ),
+                ("query".to_string(), "PLACEHOLDER LIMIT 10".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dataflow_disabled_keeps_fragments() {
+        let code = r#"
+query = "SELECT * FROM users"
+if active:
+    query = query + " WHERE active = 1"
+query = query + " LIMIT 10"
+"#;
+        let found: Vec<String> =
+            harness_analyze(FinderConfig::new(&["query".to_string()], &[], true), code)
+                .into_iter()
+                .map(|s| s.sql_content)
+                .collect();
+        assert_eq!(
+            found,
+            vec![
+                "SELECT * FROM users",
+                "SELECT * FROM users WHERE active = 1",
+                "PLACEHOLDER LIMIT 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_division() {
+        use crate::finder_types::FinderType;
+        let int = |i: &str| FinderType::Int(i.to_string());
+
+        let div = |a, b| (int(a) / int(b)).map(|r| r.to_string());
+        assert_eq!(div("100", "4").as_deref(), Some("25"));
+        assert_eq!(div("7", "2").as_deref(), Some("3.5"));
+        assert_eq!(div("7", "0"), None);
+
+        let floor_div = |a, b| int(a).floor_div(int(b)).map(|r| r.to_string());
+        assert_eq!(floor_div("7", "2").as_deref(), Some("3"));
+        assert_eq!(floor_div("-7", "2").as_deref(), Some("-4"));
+        assert_eq!(floor_div("7", "-2").as_deref(), Some("-4"));
+        assert_eq!(floor_div("7", "0"), None);
+
+        harness_find(
+            r#"
+query = "SELECT * FROM t LIMIT {} OFFSET {}".format(100 / 4, 9 // 2)
+"#,
+            vec![("query", "SELECT * FROM t LIMIT 25 OFFSET 4")],
+            "division in format arguments",
+        );
+    }
+
+    #[test]
+    fn tuple_of_strings_per_element() {
+        harness_find(
+            r#"
+queries = ("SELECT 1", "SELEC 2")
+"#,
+            vec![("queries", "SELECT 1"), ("queries", "SELEC 2")],
+            "frozen tuple constant",
+        );
+        harness_find(
+            r#"
+queries = ["SELECT 1", 5, "SELECT 2", None, 1.5, True]
+"#,
+            vec![("queries", "SELECT 1"), ("queries", "SELECT 2")],
+            "non-string elements are skipped",
+        );
+    }
+
+    #[test]
+    fn keyword_contexts() {
+        let finder = SqlFinder::new(
+            FinderConfig::new(&[], &["execute".to_string()], true)
+                .with_keyword_contexts(&["statement".to_string()])
+                .into(),
+        );
+        let found: Vec<(String, String)> = finder
+            .analyze_python_src(
+                r#"
+run(statement="SELECT * FROM users", retries=3)
+db.run(statement="SELECT 1", label="SELECT label")
+execute(statement="SELECT 2")
+logging.info("x", statement="SELECT 3")
+"#,
+                "test.py",
+            )
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.variable_name, s.sql_content))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "run(statement=...)".to_string(),
+                    "SELECT * FROM users".to_string()
+                ),
+                ("db.run(statement=...)".to_string(), "SELECT 1".to_string()),
+                ("execute".to_string(), "SELECT 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn function_context_argument_specs() {
+        let finder = SqlFinder::new(
+            FinderConfig::new(
+                &[],
+                &[
+                    "*executemany:0".to_string(),
+                    "audit:1".to_string(),
+                    "run_query:sql".to_string(),
+                ],
+                true,
+            )
+            .into(),
+        );
+        let found: Vec<(String, String)> = finder
+            .analyze_python_src(
+                r#"
+cursor.executemany("INSERT INTO t VALUES (?)", [("SELECT a",), ("SELECT b",)])
+cursor.executemany(sql="SELECT 1")
+audit("SELECT is logged", "DELETE FROM t WHERE id = 1")
+run_query("SELECT label", sql="SELECT 2")
+"#,
+                "test.py",
+            )
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.variable_name, s.sql_content))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "cursor.executemany".to_string(),
+                    "INSERT INTO t VALUES (?)".to_string()
+                ),
+                (
+                    "audit".to_string(),
+                    "DELETE FROM t WHERE id = 1".to_string()
+                ),
+                ("run_query".to_string(), "SELECT 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn function_context_suffix_layers() {
+        let names = |contexts: &[&str], suffix_matching: bool| -> Vec<String> {
+            let contexts: Vec<String> = contexts.iter().map(ToString::to_string).collect();
+            harness_analyze(
+                FinderConfig::new(&[], &contexts, true).with_suffix_matching(suffix_matching),
+                r#"
+self.db.cursor.execute("SELECT 1")
+conn.execute("SELECT 2")
+self.executor.submit("SELECT 3")
+"#,
+            )
+            .into_iter()
+            .map(|s| s.variable_name)
+            .collect()
+        };
+
+        // Final attribute
+        assert_eq!(
+            names(&["execute"], true),
+            vec!["self.db.cursor.execute", "conn.execute"]
+        );
+        // Dotted suffix
+        assert_eq!(
+            names(&["cursor.execute"], true),
+            vec!["self.db.cursor.execute"]
+        );
+        // Full path
+        assert_eq!(names(&["conn.execute"], true), vec!["conn.execute"]);
+        assert!(names(&["submit.*"], true).is_empty());
+
+        // Exact dotted paths only
+        assert_eq!(
+            names(&["execute", "cursor.execute"], false),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(&["self.db.cursor.execute"], false),
+            vec!["self.db.cursor.execute"]
+        );
+    }
+
+    #[test]
+    fn attribute_target_dotted_path() {
+        let contexts = ["self.db.*".to_string(), "*.sql_query".to_string()];
+        let result = harness_analyze(
+            FinderConfig::new(&contexts, &[], true),
+            r#"
+class Repo:
+    def load(self, response):
+        self.db.query = "SELECT * FROM users"
+        response.query = "page=2&sort=name"
+        self.metrics.query = "requests_total"
+        report.sql_query = "SELECT count(*) FROM orders"
+"#,
+        );
+
+        let found: Vec<(&str, &str)> = result
+            .iter()
+            .map(|s| (s.variable_name.as_str(), s.sql_content.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("query", "SELECT * FROM users"),
+                ("sql_query", "SELECT count(*) FROM orders"),
+            ]
+        );
+    }
+
+    #[test]
+    fn min_sql_length() {
+        let code = r#"
+sql = ""
+query = "  SELECT  "
+query = "SELECT id FROM users"
+execute("SELECT")
+execute("DELETE FROM sessions")
+"#;
+        let found = |min_sql_length: usize| -> Vec<String> {
+            harness_analyze(
+                FinderConfig::new(
+                    &["query".to_string(), "sql".to_string()],
+                    &["execute".to_string()],
+                    true,
+                )
+                .with_min_sql_length(min_sql_length),
+                code,
+            )
+            .into_iter()
+            .map(|s| s.sql_content)
+            .collect()
+        };
+
+        assert_eq!(found(0).len(), 5);
+        assert_eq!(
+            found(10),
+            vec!["SELECT id FROM users", "DELETE FROM sessions"]
+        );
+    }
+
+    #[test]
+    fn case_insensitive_contexts() {
+        let names = |case_sensitive: bool| -> Vec<String> {
+            harness_analyze(
+                FinderConfig::new(
+                    &[
+                        "query".to_string(),
+                        "*sql*".to_string(),
+                        "*stmt".to_string(),
+                    ],
+                    &["execute".to_string()],
+                    case_sensitive,
+                ),
+                r#"
+QUERY = "SELECT 1"
+Query = "SELECT 2"
+SqL_StMt = "SELECT 3"
+query = "SELECT 4"
+cursor.EXECUTE("SELECT 5")
+"#,
+            )
+            .into_iter()
+            .map(|s| s.variable_name)
+            .collect()
+        };
+
+        assert_eq!(
+            names(false),
+            vec!["QUERY", "Query", "SqL_StMt", "query", "cursor.EXECUTE"]
+        );
+        assert_eq!(names(true), vec!["query"]);
+    }
+
+    #[test]
+    fn regex_contexts() {
+        let result = harness_analyze(
+            FinderConfig::new(
+                &[
+                    "re:_(sql|query)$".to_string(),
+                    "re:(unclosed".to_string(),
+                    "stmt".to_string(),
+                ],
+                &["re:^(get|set)_.*_sql$".to_string()],
+                true,
+            ),
+            r#"
+users_query = "SELECT * FROM users"
+users_query_params = "SELECT * FROM ignored"
+orders_sql = "SELECT * FROM orders"
+stmt = "DELETE FROM sessions"
+get_user_sql("SELECT id FROM users")
+run_sql("SELECT 1")
+"#,
+        );
+
+        let names: Vec<&str> = result.iter().map(|s| s.variable_name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["users_query", "orders_sql", "stmt", "get_user_sql"]
+        );
+    }
+
+    #[test]
+    fn regex_contexts_without_arg_spec() {
+        let result = harness_analyze(
+            FinderConfig::new(&[], &["re:run_sql".to_string()], true),
+            "run_sql(\"SELECT 1\")\n",
+        );
+
+        let names: Vec<&str> = result.iter().map(|s| s.variable_name.as_str()).collect();
+        assert_eq!(names, vec!["run_sql"]);
+    }
+
+    #[test]
+    fn exclusion_contexts() {
+        let result = harness_analyze(
+            FinderConfig::new(
+                &[
+                    "*query*".to_string(),
+                    "!*query_params*".to_string(),
+                    "*sql*".to_string(),
+                    "!sqlalchemy*".to_string(),
+                ],
+                &["*sql*".to_string(), "!sql_escape".to_string()],
+                true,
+            ),
+            r#"
+query = "SELECT * FROM users"
+query_params = "SELECT * FROM ignored"
+sqlalchemy_url = "SELECT * FROM ignored"
+raw_sql = "SELECT * FROM orders"
+sql_escape("SELECT * FROM ignored")
+helpers.sql_escape("SELECT * FROM ignored")
+run_sql("DELETE FROM sessions")
+"#,
+        );
+
+        let names: Vec<&str> = result.iter().map(|s| s.variable_name.as_str()).collect();
+        assert_eq!(names, vec!["query", "raw_sql", "run_sql"]);
+    }
+
+    #[test]
+    fn configurable_sql_keywords() {
+        let code = r#"
+execute("MERGE INTO users USING staged ON users.id = staged.id")
+execute("-- nightly cleanup\nSELECT * FROM sessions")
+execute("/* audited */ DELETE FROM sessions")
+"#;
+        let found = |keywords: Option<&[&str]>| -> Vec<String> {
+            let mut config = FinderConfig::new(&[], &["execute".to_string()], true);
+            if let Some(keywords) = keywords {
+                let keywords: Vec<String> = keywords.iter().map(ToString::to_string).collect();
+                config = config.with_sql_keywords(&keywords);
+            }
+            harness_analyze(config, code)
+                .into_iter()
+                .map(|s| s.sql_content)
+                .collect()
+        };
+
+        assert_eq!(
+            found(None),
+            vec![
+                "-- nightly cleanup\nSELECT * FROM sessions",
+                "/* audited */ DELETE FROM sessions",
+            ]
+        );
+        assert_eq!(
+            found(Some(&["merge", "select"])),
+            vec![
+                "MERGE INTO users USING staged ON users.id = staged.id",
+                "-- nightly cleanup\nSELECT * FROM sessions",
+            ]
+        );
+    }
+
+    #[test]
+    fn require_sql_keyword_disabled() {
+        let code = r#"
+execute("WHERE tenant_id = %s AND deleted_at IS NULL")
+execute("SELECT 1")
+"#;
+        let found = |required: bool| -> Vec<String> {
+            harness_analyze(
+                FinderConfig::new(&[], &["execute".to_string()], true)
+                    .with_require_sql_keyword(required),
+                code,
+            )
+            .into_iter()
+            .map(|s| s.sql_content)
+            .collect()
+        };
+
+        assert_eq!(found(true), vec!["SELECT 1"]);
+        assert_eq!(
+            found(false),
+            vec!["WHERE tenant_id = %s AND deleted_at IS NULL", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn unextractable_values() {
+        let code = r#"
+query = build_query(user_input)
+sql = None
+queries = []
+self.query = "SELECT 1"
+other_query = self.query
+execute(query)
+execute(make_sql(), params)
+execute("SELECT 2", make_params())
+"#;
+        let found = |enabled: bool| -> Vec<(String, bool)> {
+            harness_analyze(
+                FinderConfig::new(
+                    &["*query*".to_string(), "sql".to_string()],
+                    &["execute".to_string()],
+                    true,
+                )
+                .with_unextractable_reporting(enabled),
+                code,
+            )
+            .into_iter()
+            .map(|s| (s.variable_name, s.unextractable))
+            .collect()
+        };
+
+        assert_eq!(
+            found(false),
+            vec![("query".to_string(), false), ("execute".to_string(), false)]
+        );
+        assert_eq!(
+            found(true),
+            vec![
+                ("query".to_string(), false),
+                ("execute".to_string(), false),
+                ("query".to_string(), true),
+                ("other_query".to_string(), true),
+                ("execute".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn unnamed_literals() {
+        let code = r#"
+"""SELECT * FROM module_docstring"""
+
+class Reports:
+    """SELECT * FROM class_docstring"""
+
+    def run(self):
+        """SELECT * FROM function_docstring"""
+        self.db.submit("bulk", "SELECT * FROM reports WHERE id = 1")
+        logging.info("SELECT * FROM logged")
+        query = "SELECT * FROM named"
+        dispatch(*["DELETE FROM sessions", "not sql at all"])
+        partial = "SELECT * FROM t WHERE id = " + str(id)
+"#;
+        let found = |enabled: bool| -> Vec<(String, String)> {
+            harness_find_with(
+                FinderConfig::new(&["query".to_string()], &[], true).with_unnamed_literals(enabled),
+                code,
+            )
+        };
+
+        assert_eq!(found(false).len(), 1);
+        assert_eq!(
+            found(true),
+            vec![
+                ("query".to_string(), "SELECT * FROM named".to_string()),
+                (
+                    "<literal>".to_string(),
+                    "SELECT * FROM reports WHERE id = 1".to_string()
+                ),
+                ("<literal>".to_string(), "DELETE FROM sessions".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn awaited_call_statement() {
+        let code = "async def run(conn):\n    await conn.execute(\"DELETE FROM sessions\")\n";
+        assert_eq!(harness_find_calls("*.execute", code), vec!["conn.execute"]);
+    }
+
+    #[test]
+    fn disable_enable_block() {
+        harness_find(
+            r#"
+query = "SELECT 1"
+# sqint: disable
+generated_sql = "SELECT 2"
+other_sql = "SELECT 3"
+# sqint: enable
+last_sql = "SELECT 4"
+"#,
+            vec![("query", "SELECT 1"), ("last_sql", "SELECT 4")],
+            "statements between disable and enable",
+        );
+    }
+
+    #[test]
+    fn disable_enable_repeated_directives() {
+        harness_find(
+            r#"
+# sqint: disable
+first_sql = "SELECT 1"
+# sqint: disable
+second_sql = "SELECT 2"
+# sqint: enable
+third_sql = "SELECT 3"
+# sqint: enable
+fourth_sql = "SELECT 4"
+"#,
+            vec![("third_sql", "SELECT 3"), ("fourth_sql", "SELECT 4")],
+            "directives don't nest, the first enable closes the block",
+        );
+    }
+
+    #[test]
+    fn disable_block_ending_mid_statement() {
+        harness_find(
+            r#"
+def load():
+    query = "SELECT 1"
+    # sqint: disable
+    generated_sql = (
+        "SELECT 2"
+        # sqint: enable
+        " FROM users"
+    )
+after_sql = "SELECT 3"
+"#,
+            vec![("query", "SELECT 1"), ("after_sql", "SELECT 3")],
+            "statement starting in the block, function ending in it",
+        );
+    }
+
+    #[test]
+    fn unclosed_disable_suppresses_to_eof() {
+        harness_find(
+            r#"
+query = "SELECT 1"
+# sqint: disable
+generated_sql = "SELECT 2"
+other_sql = "SELECT 3""#,
+            vec![("query", "SELECT 1")],
+            "disable without enable",
+        );
+    }
+
+    #[test]
+    fn ignore_file_pragma() {
+        let header =
+            "#!/usr/bin/env python\n# Generated by protoc, do not edit.\n# sqint: ignore-file\n";
+        assert!(harness_find_all(&format!("{header}query = \"SELECT 1\"\n")).is_empty());
+
+        // Past the first lines only as the first comment
+        let late = format!(
+            "{}# sqint: ignore-file\nquery = \"SELECT 1\"\n",
+            "x = 1\n".repeat(12)
+        );
+        assert!(harness_find_all(&late).is_empty());
+        let late = format!(
+            "# header\n{}# sqint: ignore-file\nquery = \"SELECT 1\"\n",
+            "x = 1\n".repeat(12)
+        );
+        assert_eq!(harness_find_all(&late).len(), 1);
+    }
+
+    #[test]
+    fn pragma_text_inside_strings() {
+        let code = r#"query = "SELECT * FROM tags WHERE tag = '# sqint: ignore'"; x = 1
+sql = 'SELECT 1 -- # sqint: disable'
+also_query = """SELECT 2  # sqint: ignore-file"""  # a comment
+tag_sql = "SELECT '#'"  # sqint: ignore
+"#;
+        let found = harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .unwrap();
+        let suppressed: Vec<(&str, bool)> = found
+            .iter()
+            .map(|s| (s.variable_name.as_str(), s.suppressed.is_some()))
+            .collect();
+        assert_eq!(
+            suppressed,
+            vec![
+                ("query", false),
+                ("sql", false),
+                ("also_query", false),
+                ("tag_sql", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn pragma_after_lexer_error() {
+        // The unterminated string stops the lexer, later lines still have their pragmas
+        let file = PreanalyzedFile::from_src(
+            "query = \"SELECT 1\"  # sqint: ignore\nsql = 'SELECT\nother = 1  # sqint: ignore[SQ100]\n",
+        );
+        assert_eq!(file.suppression_at(0), Some(&Suppression::All));
+        assert_eq!(file.suppressed_codes(), vec![(3, "SQ100")]);
+    }
+
+    #[test]
+    fn noqa_comments() {
+        let code = r#"query = "SELECT 1"  # noqa
+other_sql = "SELECT 2"  # noqa: E501
+third_sql = "SELECT 3"  # noqa: E501, SQ100
+"#;
+        let suppressed = |respect_noqa: bool| -> Vec<Option<Suppression>> {
+            harness_analyze(
+                FinderConfig::new(&["query".to_string(), "*_sql".to_string()], &[], true)
+                    .with_respect_noqa(respect_noqa),
+                code,
+            )
+            .into_iter()
+            .map(|s| s.suppressed)
+            .collect()
+        };
+
+        assert_eq!(suppressed(false), vec![None, None, None]);
+        assert_eq!(
+            suppressed(true),
+            vec![
+                Some(Suppression::All),
+                None,
+                Some(Suppression::Codes(["SQ100".to_string()].into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn pragma_on_any_line_of_statement() {
+        let suppressed = |code: &str| -> Vec<(String, bool)> {
+            harness_create_test_finder()
+                .analyze_python_src(code, "test.py")
+                .unwrap()
+                .into_iter()
+                .map(|s| (s.variable_name, s.suppressed.is_some()))
+                .collect()
+        };
+        let statement = |first: &str, middle: &str, last: &str| {
+            format!(
+                "before_sql = \"SELECT 0\"\nquery = (  {first}\n    \"SELECT 1 \"  {middle}\n    \"FROM users\"\n)  {last}\nafter_sql = \"SELECT 2\"\n"
+            )
+        };
+        let expected = vec![
+            ("before_sql".to_string(), false),
+            ("query".to_string(), true),
+            ("after_sql".to_string(), false),
+        ];
+
+        assert_eq!(suppressed(&statement("# sqint: ignore", "", "")), expected);
+        assert_eq!(suppressed(&statement("", "# sqint: ignore", "")), expected);
+        assert_eq!(suppressed(&statement("", "", "# sqint: ignore")), expected);
+
+        // Pragmas in a body belong to the statements there, not to the whole function
+        let code = "def load():\n    query = \"SELECT 1\"\n    other_sql = \"SELECT 2\"  # sqint: ignore\n";
+        assert_eq!(
+            suppressed(code),
+            vec![
+                ("query".to_string(), false),
+                ("other_sql".to_string(), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn dialect_pragmas() {
+        let code = r#"# sqint: dialect=sqlite
+query = "SELECT 1"
+also_query = "SELECT 2"  # sqint: dialect=mysql
+execute(
+    "SELECT 3",  # sqint: dialect = postgres
+)
+"#;
+        let dialects: Vec<Option<String>> = harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.dialect)
+            .collect();
+        assert_eq!(
+            dialects,
+            vec![
+                Some("sqlite".to_string()),
+                Some("mysql".to_string()),
+                Some("postgres".to_string()),
+            ]
+        );
+
+        // Only a pragma on a line of its own sets the file's dialect
+        let src = "query = \"SELECT 1\"  # sqint: dialect=mysql\nalso_query = \"SELECT 2\"\n";
+        let rf = PreanalyzedFile::from_src(src);
+        assert_eq!(rf.file_dialect(), None);
+        assert_eq!(rf.dialect_pragmas().len(), 1);
+        assert_eq!(rf.dialect_pragmas()[0].0.start.line(), 1);
+    }
+
+    fn positions(src: &str) -> Vec<(String, usize, usize, usize, usize)> {
+        harness_create_test_finder()
+            .analyze_python_src(src, "test.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| {
+                (
+                    s.sql_content,
+                    s.range.start.line(),
+                    s.range.start.col(),
+                    s.range.end.line(),
+                    s.range.end.col(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let lf = "import db\n\nquery = \"SELECT id FROM users\"  # sqint: ignore\nexecute(\n    \"SELECT 1\"\n)\n";
+        let crlf = lf.replace('\n', "\r\n");
+        assert_eq!(positions(&crlf), positions(lf));
+
+        let rf = PreanalyzedFile::from_src(crlf);
+        assert_eq!(
+            rf.line_text(3),
+            Some("query = \"SELECT id FROM users\"  # sqint: ignore")
+        );
+        assert_eq!(rf.line_text(4), Some("execute("));
+        assert!(rf.suppression_at(rf.src().find("query").unwrap()).is_some());
+    }
+
+    #[test]
+    fn mixed_line_endings() {
+        let src = "import db\r\nquery = \"SELECT 1\"\rexecute(\n    \"SELECT 2\"\r\n)\n";
+        assert_eq!(
+            positions(src),
+            positions("import db\nquery = \"SELECT 1\"\nexecute(\n    \"SELECT 2\"\n)\n")
+        );
+        let rf = PreanalyzedFile::from_src(src);
+        assert_eq!(rf.line_text(2), Some("query = \"SELECT 1\""));
+        assert_eq!(rf.line_text(3), Some("execute("));
+    }
+
+    #[test]
+    fn bom_before_first_line() {
+        let src = "\u{feff}query = \"SELECT id FROM users\"\n";
+        assert_eq!(positions(src), positions(&src[3..]));
+        assert_eq!(positions(src)[0].2, 9);
+        assert_eq!(
+            PreanalyzedFile::from_src(src).line_text(1),
+            Some("query = \"SELECT id FROM users\"")
+        );
+
+        // Byte columns are positions in the file, the BOM before the first line included
+        let rf = PreanalyzedFile::from_src(format!("{src}sql = \"SELECT 1\"\n"));
+        let columns = |start, end| {
+            let range = rf.byterange_to_range(preanalysis::ByteRange::new(start, end));
+            let bytes = rf.encode_range(&range, preanalysis::ColumnEncoding::Bytes);
+            (range.start.col(), bytes.start.col(), bytes.end.col())
+        };
+        assert_eq!(columns(8, 30), (9, 12, 34));
+        assert_eq!(columns(37, 47), (7, 7, 17));
+    }
+
+    #[test]
+    fn triple_quoted_range_spans_lines() {
+        let code = "def load():\n    query = \"\"\"\n        SELECT id\n        FROM users\n    \"\"\"\n    return query\n";
+        let found = harness_find_single(code);
+        assert_eq!(found.range.start.to_string(), "2:13:");
+        assert_eq!(found.range.end.to_string(), "5:8:");
+
+        let broken = format!("{code}def broken(:\n");
+        let found = harness_find_single(&broken);
+        assert!(found.approximate);
+        assert_eq!(found.range.start.to_string(), "2:13:");
+        assert_eq!(found.range.end.to_string(), "5:8:");
+        // Guessed strings aren't mapped back, nothing can point inside them
+        assert_eq!(found.content_start, None);
+    }
+
+    #[test]
+    fn content_start_offsets() {
+        let code = r#"query = r"""
+SELECT id
+FROM users"""
+execute(  "SELECT 1")
+also_query = "SELECT 'a\\nb'"
+"#;
+        let rf = PreanalyzedFile::from_src(code);
+        let found = harness_create_test_finder()
+            .analyze_python_src(code, "test.py")
+            .unwrap();
+
+        let triple = &found[0];
+        assert_eq!(triple.content_start, Some(12));
+        assert_eq!(&code[12..12 + triple.sql_content.len()], triple.sql_content);
+        assert_eq!(
+            triple.source_position(&rf, 3, 6).unwrap().to_string(),
+            "3:6:"
+        );
+        assert_eq!(
+            triple.source_position(&rf, 2, 1).unwrap().to_string(),
+            "2:1:"
+        );
+        assert!(triple.source_position(&rf, 4, 1).is_none());
+
+        let call = &found[1];
+        assert_eq!(call.byte_range().start(), code.find("execute").unwrap());
+        assert_eq!(
+            call.source_position(&rf, 1, 8).unwrap().to_string(),
+            "4:19:"
+        );
+
+        // Escapes make the content differ from the source
+        assert_eq!(found[2].content_start, None);
+    }
+
+    #[test]
+    fn column_encodings() {
+        let code = "label = \"日本😀\"; query = \"SELECT 1\"\n";
+        let rf = PreanalyzedFile::from_src(code);
+        let found = harness_find_single(code);
+        let col = |encoding| rf.encode_range(&found.range, encoding).start.col();
+
+        // `label = "日本😀"; query = ` is 23 characters, 30 bytes and 24 UTF-16 code units
+        assert_eq!(col(preanalysis::ColumnEncoding::Chars), 24);
+        assert_eq!(col(preanalysis::ColumnEncoding::Bytes), 31);
+        assert_eq!(col(preanalysis::ColumnEncoding::Utf16), 25);
+        assert_eq!(
+            rf.encode_range(&found.range, preanalysis::ColumnEncoding::Utf16)
+                .start
+                .byte_offset(),
+            found.range.start.byte_offset()
+        );
+    }
+
+    #[test]
+    fn file_ending_at_statement_boundary() {
+        let code = "x = 1\nquery = \"SELECT 1\"  # sqint: ignore";
+        let found = harness_find_single(code);
+        assert_eq!(found.range.end.to_string(), "2:19:");
+        assert_eq!(found.suppressed, Some(Suppression::All));
+
+        let code = "query = \"SELECT 'é'\"  # é";
+        let found = harness_find_single(code);
+        assert_eq!(found.sql_content, "SELECT 'é'");
+        assert_eq!(found.range.end.to_string(), "1:21:");
+    }
+
+    #[test]
+    fn offsets_past_the_end() {
+        let src = "# sqint: disable\nquery = \"SELECT 'é'\"";
+        let rf = PreanalyzedFile::from_src(src);
+        let past = src.len() + 3;
+        assert!(rf.is_disabled_at(src.len()));
+        assert!(!rf.is_disabled_at(past));
+        assert!(rf.suppression_at(past).is_none());
+        assert!(rf.suppression_in_byte_range(past, past + 1).is_none());
+
+        let range = rf.byterange_to_range(preanalysis::ByteRange::new(src.len() - 2, past));
+        // Inside of `é`, the column of the character itself
+        assert_eq!(range.start.to_string(), "2:19:");
+        assert_eq!(range.end.to_string(), "2:21:");
+    }
+
+    #[test]
+    fn tab_expanded_columns() {
+        let code = "class Queries:\n  \tquery = \"SELECT 1\"\n";
+        let rf = PreanalyzedFile::from_src(code);
+        let found = harness_find_single(code);
+        let col = |encoding| rf.encode_range(&found.range, encoding).start.col();
+
+        // Two spaces and a tab reach the first tab stop
+        assert_eq!(col(preanalysis::ColumnEncoding::Chars), 12);
+        assert_eq!(col(preanalysis::ColumnEncoding::Display(1)), 12);
+        assert_eq!(col(preanalysis::ColumnEncoding::Display(4)), 13);
+        assert_eq!(col(preanalysis::ColumnEncoding::Display(8)), 17);
+    }
+
+    fn extract_from_bytes(name: &str, bytes: &[u8]) -> SqlExtract {
+        let path = std::env::temp_dir().join(format!("sqint_{name}_{}.py", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), false)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        extract
+    }
+
+    #[test]
+    fn declared_encoding_decoded() {
+        // `ç` and `é` are a single byte in latin-1
+        let bytes = b"# -*- coding: latin-1 -*-\nville = 'Besan\xe7on'; query = \"SELEC nom FROM clients WHERE ville = 'Besan\xe7on'\"\n".to_vec();
+        let extract = extract_from_bytes("latin1", &bytes);
+        let found = &extract.strings[0];
+        assert_eq!(
+            found.sql_content,
+            "SELEC nom FROM clients WHERE ville = 'Besançon'"
+        );
+        assert_eq!(found.range.start.to_string(), "2:29:");
+        assert_eq!(
+            extract
+                .source
+                .encode_range(&found.range, preanalysis::ColumnEncoding::Bytes)
+                .start
+                .col(),
+            29
+        );
+
+        // The declaration itself may contain text in the declared encoding, `СУБД`
+        let extract = extract_from_bytes(
+            "cp1251",
+            b"# \xd1\xd3\xc1\xc4 coding: cp1251\nquery = \"SELECT '\xe7'\"\n",
+        );
+        assert_eq!(extract.strings[0].sql_content, "SELECT 'з'");
+    }
+
+    #[test]
+    fn invalid_utf8_read_lossily() {
+        let extract = extract_from_bytes("lossy", b"# caf\xe9\nquery = \"SELECT 1\"\n");
+        assert_eq!(extract.strings[0].sql_content, "SELECT 1");
+        assert_eq!(extract.strings[0].range.start.to_string(), "2:9:");
+    }
+
+    fn sql_script_statements(name: &str, src: &str) -> Vec<(String, String)> {
+        let path = std::env::temp_dir().join(format!("sqint_{name}_{}.sql", std::process::id()));
+        std::fs::write(&path, src).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        extract
+            .strings
+            .into_iter()
+            .map(|s| (s.sql_content, s.range.start.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn sql_script_semicolons_in_literals_and_comments() {
+        let src = r#"-- Seed data; run once
+INSERT INTO t (s) VALUES ('a;b', 'it''s;');
+SELECT "odd;name" FROM t; /* trailing; block */
+/* leading; block */ SELECT `x;y` FROM t -- done; really
+;
+# mysql; comment
+DELETE FROM t
+"#;
+        assert_eq!(
+            sql_script_statements("literals", src),
+            vec![
+                (
+                    "INSERT INTO t (s) VALUES ('a;b', 'it''s;')".to_string(),
+                    "2:1:".to_string()
+                ),
+                ("SELECT \"odd;name\" FROM t".to_string(), "3:1:".to_string()),
+                ("SELECT `x;y` FROM t".to_string(), "4:22:".to_string()),
+                ("DELETE FROM t".to_string(), "7:1:".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_script_dollar_quoted_bodies() {
+        let src = r#"CREATE FUNCTION bump(n integer) RETURNS integer AS $body$
+BEGIN
+    IF n > 0 THEN
+        RETURN n + 1;
+    END IF;
+    RETURN $$zero;$$;
+END;
+$body$ LANGUAGE plpgsql;
+SELECT bump($1);
+"#;
+        let statements = sql_script_statements("dollar", src);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].0.ends_with("$body$ LANGUAGE plpgsql"));
+        assert_eq!(
+            statements[1],
+            ("SELECT bump($1)".to_string(), "9:1:".to_string())
+        );
+    }
+
+    #[test]
+    fn sql_script_psql_meta_commands() {
+        let src = r#"\set ON_ERROR_STOP on
+\c analytics
+CREATE TABLE t (s text);
+  \i seed.sql
+\copy t FROM 'data.csv' WITH CSV
+INSERT INTO t VALUES ('\c not a command');
+SELECT s
+\echo between the lines
+FROM t;
+"#;
+        assert_eq!(
+            sql_script_statements("psql", src),
+            vec![
+                ("CREATE TABLE t (s text)".to_string(), "3:1:".to_string()),
+                (
+                    "INSERT INTO t VALUES ('\\c not a command')".to_string(),
+                    "6:1:".to_string()
+                ),
+                (
+                    "SELECT s\n                       \nFROM t".to_string(),
+                    "7:1:".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_script_named_statements() {
+        let src = r#"-- name: get_user_by_id :one
+-- Fetches a single user
+SELECT * FROM users WHERE id = $1;
+
+-- name: list-users
+SELECT * FROM users;
+SELECT 1;
+"#;
+        let extract = |config: FinderConfig| {
+            let path = std::env::temp_dir().join(format!("sqint_named_{}.sql", std::process::id()));
+            std::fs::write(&path, src).unwrap();
+            let extract = SqlFinder::new(std::sync::Arc::new(config))
+                .analyze_file(path.to_str().unwrap(), true)
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+            extract
+                .strings
+                .into_iter()
+                .map(|s| (s.variable_name, s.range.start.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let config = || FinderConfig::new(&[], &[], false);
+
+        assert_eq!(
+            extract(config()),
+            vec![
+                ("get_user_by_id".to_string(), "3:1:".to_string()),
+                ("list-users".to_string(), "6:1:".to_string()),
+                ("SELECT".to_string(), "7:1:".to_string()),
+            ]
+        );
+        let names: Vec<String> = extract(config().with_statement_name_pattern(None))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["SELECT users", "SELECT users", "SELECT"]);
+    }
+
+    #[test]
+    fn sql_script_statement_positions() {
+        let src = "\n\nSELECT 1;   \n\n\n-- between; statements\n  /* block */\n    SELECT 2\n    FROM t   ;\t\n\nSELECT 3   \n";
+        let path = std::env::temp_dir().join(format!("sqint_positions_{}.sql", std::process::id()));
+        std::fs::write(&path, src).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let found: Vec<(String, usize, String)> = extract
+            .strings
+            .iter()
+            .map(|s| {
+                (
+                    s.range.start.to_string(),
+                    s.range.start.byte_offset(),
+                    s.range.end.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("3:1:".to_string(), 2, "3:9:".to_string()),
+                ("8:5:".to_string(), 58, "9:11:".to_string()),
+                ("11:1:".to_string(), 84, "11:9:".to_string()),
+            ]
+        );
+        for s in &extract.strings {
+            assert_eq!(
+                &src[s.range.start.byte_offset()..s.range.end.byte_offset()],
+                s.sql_content
+            );
+        }
+    }
+
+    #[test]
+    fn templated_sql_stripped() {
+        let src = "{{ config(materialized='table') }}\n\nSELECT id, {{ col }}\nFROM {{ ref('users') }} u\n{% if only_active -%}\nWHERE u.active {# flag #}\n{%- endif %};\n{% set x = 1 %};\n";
+        let path = std::env::temp_dir().join(format!("sqint_model_{}.sql", std::process::id()));
+        std::fs::write(&path, src).unwrap();
+        let config = FinderConfig::new(&[], &[], false)
+            .with_templated_sql_file_patterns(&["*sqint_model_*.sql".to_string()]);
+        let extract = SqlFinder::new(std::sync::Arc::new(config))
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(extract.strings.len(), 1);
+        let statement = &extract.strings[0];
+        assert_eq!(
+            statement.sql_content,
+            "SELECT id, _________\nFROM __________________ u\n                     \nWHERE u.active"
+        );
+        assert_eq!(statement.range.start.to_string(), "3:1:");
+        assert_eq!(statement.range.end.to_string(), "6:15:");
+    }
+
+    #[test]
+    fn calls_in_assigned_values() {
+        harness_find(
+            r#"
+rows = execute("SELECT * FROM users")
+operations = [migrate(execute("DELETE FROM sessions")), len(items)]
+query = execute("SELECT 1")
+"#,
+            vec![
+                ("execute", "SELECT * FROM users"),
+                ("execute", "DELETE FROM sessions"),
+                ("query", "SELECT 1"),
+            ],
+            "calls in assigned values no variable takes",
+        );
+    }
+
+    #[test]
+    fn sql_in_data_files() {
+        let yaml = r#"# Reports
+reports:
+  - name: active users
+    query: |
+      SELECT id
+      FROM users
+    count_query: "SELECT count(*) FROM users"
+  - name: none
+    query: ""
+extra:
+  query:
+    - SELECT 1
+    - 'SELECT 2'
+    - 'SELECT ''a'''
+"#;
+        let json = "{\"reports\": [{\"query\": \"SELECT \\\"3\\\"\", \"title\": \"SELECT 4\"}]}";
+        let found = |name: &str, src: &str| {
+            let path =
+                std::env::temp_dir().join(format!("sqint_data_{}_{name}", std::process::id()));
+            std::fs::write(&path, src).unwrap();
+            let config = FinderConfig::new(&[], &[], false).with_data_file_keys(&[(
+                "*sqint_data_*".to_string(),
+                vec!["query".to_string(), "count_query".to_string()],
+            )]);
+            let extract = SqlFinder::new(std::sync::Arc::new(config))
+                .analyze_file(path.to_str().unwrap(), true)
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+            extract
+                .strings
+                .into_iter()
+                .map(|s| {
+                    (
+                        s.variable_name,
+                        s.sql_content,
+                        format!("{}{}", s.range.start, s.range.end),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let strings = |items: &[(&str, &str, &str)]| {
+            items
+                .iter()
+                .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found("reports.yaml", yaml),
+            strings(&[
+                ("reports[0].query", "SELECT id\nFROM users\n", "5:7:6:17:"),
+                (
+                    "reports[0].count_query",
+                    "SELECT count(*) FROM users",
+                    "7:19:7:45:"
+                ),
+                ("extra.query[0]", "SELECT 1", "12:7:12:15:"),
+                ("extra.query[1]", "SELECT 2", "13:8:13:16:"),
+                ("extra.query[2]", "SELECT 'a'", "14:8:14:20:"),
+            ])
+        );
+        assert_eq!(
+            found("reports.json", json),
+            strings(&[("reports[0].query", "SELECT \"3\"", "1:25:1:37:")])
+        );
+    }
+
+    #[test]
+    fn sql_file_encodings() {
+        // `°` and `£` are a single byte in latin-1
+        let bytes =
+            b"-- encoding: latin-1\nSELECT '20\xb0C' AS temperature;\nSELECT '\xa35' AS price;\n";
+        let path = std::env::temp_dir().join(format!("sqint_latin1_{}.sql", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        let contents: Vec<(&str, String)> = extract
+            .strings
+            .iter()
+            .map(|s| (s.sql_content.as_str(), s.range.start.to_string()))
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                ("SELECT '20°C' AS temperature", "2:1:".to_string()),
+                ("SELECT '£5' AS price", "3:1:".to_string()),
+            ]
+        );
+
+        // Without a declaration the invalid bytes are replaced, the statements are still there
+        std::fs::write(&path, &bytes[21..]).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(extract.strings.len(), 2);
+        assert_eq!(
+            extract.strings[1].sql_content,
+            "SELECT '\u{fffd}5' AS price"
+        );
+        assert_eq!(extract.strings[1].range.start.to_string(), "2:1:");
+    }
+
+    #[test]
+    fn sql_script_statement_summaries() {
+        let src = r#"SELECT u.id, (SELECT count(*) FROM orders o WHERE o.user_id = u.id)
+FROM public.users u;
+INSERT OR REPLACE INTO "audit log" (id) VALUES (1);
+update users set active = false;
+DELETE FROM sessions;
+CREATE TABLE IF NOT EXISTS accounts (id INTEGER PRIMARY KEY);
+CREATE UNIQUE INDEX CONCURRENTLY idx_users_email ON users (email);
+CREATE INDEX ON users (name);
+DROP VIEW active_users;
+SELECT 1;
+%% not a statement (((;
+CREATE TABLE a_table_with_a_name_way_longer_than_anybody_wants_to_read_in_a_finding (id int);
+"#;
+        let path = std::env::temp_dir().join(format!("sqint_summaries_{}.sql", std::process::id()));
+        std::fs::write(&path, src).unwrap();
+        let extract = harness_create_test_finder()
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<String> = extract
+            .strings
+            .into_iter()
+            .map(|s| s.variable_name)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "SELECT public.users",
+                "INSERT \"audit log\"",
+                "UPDATE users",
+                "DELETE sessions",
+                "CREATE TABLE accounts",
+                "CREATE UNIQUE INDEX idx_users_email",
+                "CREATE INDEX",
+                "DROP VIEW active_users",
+                "SELECT",
+                "sql_statement_10",
+                "CREATE TABLE a_table_with_a_name_way_longer_than_anybody_wan",
+            ]
+        );
+    }
 }
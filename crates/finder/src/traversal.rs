@@ -1,9 +1,12 @@
 #![allow(clippy::needless_collect, clippy::single_match_else)]
-use crate::finder_types::{FinderType, SqlResult};
-use crate::format::format_python_string;
-use crate::preanalysis::PreanalyzedFile;
+use crate::finder_types::{
+    ArgSpec, CallParams, Explanation, FinderType, Interpolation, PlaceholderKind, SqlResult,
+};
+use crate::format::{dedent, format_python_string, substitute_format_fields, substitute_template};
+use crate::preanalysis::{ByteRange, PreanalyzedFile};
+use crate::visit::{Visit, walk_expr, walk_exprs};
 use crate::{SqlFinder, SqlString};
-use logging::{bail, bail_with};
+use logging::{bail, bail_with, debug};
 use rustpython_parser::ast::{Operator, Ranged};
 use rustpython_parser::{
     ast::{self, Identifier},
@@ -17,16 +20,17 @@ impl SqlFinder {
         assign: &ast::StmtAssign,
         range_file: &PreanalyzedFile,
     ) -> Vec<SqlString> {
-        let mut sql_strings = vec![];
-
-        assign.targets.iter().for_each(|target| {
-            sql_strings = self
-                .process_assignment_target(target, &assign.value)
-                .into_iter()
-                .filter_map(|result| sql_result_to_string(result, range_file))
-                .collect();
-        });
-        sql_strings
+        let results: Vec<SqlResult> = assign
+            .targets
+            .iter()
+            .flat_map(|target| self.process_assignment_target(target, &assign.value))
+            .collect();
+        let calls = self.process_value_calls(&assign.value, results.is_empty());
+        results
+            .into_iter()
+            .chain(calls)
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
     }
 
     pub(super) fn analyze_stmt_expr(
@@ -46,14 +50,417 @@ impl SqlFinder {
         range_file: &PreanalyzedFile,
     ) -> Vec<SqlString> {
         assign.value.as_ref().map_or_else(Vec::new, |val| {
-            self.process_assignment_target(&assign.target, val)
+            let results = self.process_assignment_target(&assign.target, val);
+            let calls = self.process_value_calls(val, results.is_empty());
+            results
                 .into_iter()
+                .chain(calls)
                 .filter_map(|result| sql_result_to_string(result, range_file))
                 .collect()
         })
     }
+
+    /// `query += "..."`, the fragment is joined with the variable's earlier value once the
+    /// enclosing suite is done, see `resolve_appends`.
+    pub(super) fn analyze_aug_assignment(
+        &self,
+        assign: &ast::StmtAugAssign,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        if assign.op != Operator::Add {
+            return vec![];
+        }
+        self.process_assignment_target(&assign.target, &assign.value)
+            .into_iter()
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .map(|mut s| {
+                s.appended = true;
+                s
+            })
+            .collect()
+    }
+
+    /// Classes matching a class context keep every string member as SQL, enums the members that
+    /// look like SQL. Members of either are named after their class, `Queries.GET_USER`.
+    pub(super) fn analyze_class(
+        &self,
+        class: &ast::StmtClassDef,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        let mut results = self.analyze_scope(&class.body, range_file, None);
+        let in_context = self.config.is_class_context(&class.name);
+        if !in_context && !class.bases.iter().any(is_enum_base) {
+            return results;
+        }
+
+        let members: Vec<(&ast::Stmt, &ast::Identifier, &ast::Expr)> = class
+            .body
+            .iter()
+            .flat_map(|stmt| {
+                let (targets, value): (Vec<&ast::Expr>, Option<&ast::Expr>) = match stmt {
+                    ast::Stmt::Assign(a) => (a.targets.iter().collect(), Some(&a.value)),
+                    ast::Stmt::AnnAssign(a) => (vec![&a.target], a.value.as_deref()),
+                    _ => (vec![], None),
+                };
+                targets
+                    .into_iter()
+                    .filter_map(move |target| match (target, value) {
+                        (ast::Expr::Name(name), Some(value)) => Some((stmt, &name.id, value)),
+                        _ => None,
+                    })
+            })
+            .filter(|(stmt, _, _)| !range_file.is_disabled_at(stmt.range().start().to_usize()))
+            .collect();
+
+        // Members found by their own name are reported under the class name instead
+        let within = |s: &SqlString, stmt: &ast::Stmt| {
+            (stmt.range().start().to_usize()..stmt.range().end().to_usize())
+                .contains(&s.range.start.byte_offset())
+        };
+        results.retain(|s| !members.iter().any(|(stmt, _, _)| within(s, stmt)));
+
+        results.extend(members.iter().flat_map(|(_, attr, value)| {
+            self.extract_content_flattened(value, &format!("{}.{attr}", class.name))
+                .into_iter()
+                .filter(|result| matches!(result.content, FinderType::Str(_)))
+                .filter_map(|result| sql_result_to_string(result, range_file))
+                .filter(|s| {
+                    in_context
+                        || self.config.is_sql_variable_name(attr)
+                        || self.config.is_sql_str(&s.sql_content)
+                })
+        }));
+        results
+    }
+
+    /// Walrus bindings in the expressions a statement evaluates itself, nested bodies are
+    /// analyzed on their own. `if (query := "SELECT ...") and dry_run:` counts as an assignment.
+    pub(super) fn analyze_named_exprs(
+        &self,
+        stmt: &ast::Stmt,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        self.process_named_exprs(header_exprs(stmt))
+            .into_iter()
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Calls in the subject of a `match` and in the guards of its cases, plus walrus bindings
+    /// in the guards. Walrus bindings in the subject are found with the other statement headers.
+    pub(super) fn analyze_match_exprs(
+        &self,
+        stmt: &ast::StmtMatch,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        let guards: Vec<&ast::Expr> = stmt
+            .cases
+            .iter()
+            .filter_map(|c| c.guard.as_deref())
+            .collect();
+
+        self.process_calls(std::iter::once(&*stmt.subject).chain(guards.iter().copied()))
+            .into_iter()
+            .chain(self.process_named_exprs(guards.iter().copied()))
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Calls in the condition and message of an `assert`, e.g. `assert validate("SELECT ...")`.
+    pub(super) fn analyze_assert_calls(
+        &self,
+        assert: &ast::StmtAssert,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        self.process_calls(std::iter::once(&*assert.test).chain(assert.msg.as_deref()))
+            .into_iter()
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Calls matching a function context anywhere in the raised exception or its cause,
+    /// `raise QueryError(execute("..."))`. Messages themselves aren't SQL.
+    pub(super) fn analyze_raise(
+        &self,
+        raise: &ast::StmtRaise,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        self.process_nested_calls(raise.exc.iter().chain(&raise.cause).map(|e| &**e))
+            .into_iter()
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Lambdas and comprehensions in the expressions a statement evaluates, like
+    /// `retry(lambda: execute("..."))` or `[execute(q) for q in batch]`. Calls in their bodies
+    /// and defaults of SQL named lambda parameters are analyzed.
+    pub(super) fn analyze_nested_scopes(
+        &self,
+        stmt: &ast::Stmt,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        let mut calls = Calls::default();
+        walk_exprs(&mut calls, header_exprs(stmt));
+
+        calls
+            .lambdas
+            .iter()
+            .flat_map(|lambda| self.process_lambda(lambda))
+            .chain(
+                calls
+                    .comprehensions
+                    .iter()
+                    .flat_map(|comp| self.process_comprehension(comp)),
+            )
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Lambdas and comprehensions are scopes of their own, their arguments and loop variables
+    /// would shadow constants of the enclosing body.
+    fn process_lambda(&self, lambda: &ast::ExprLambda) -> Vec<SqlResult> {
+        self.within_scope(|| self.process_lambda_body(lambda))
+    }
+
+    fn process_lambda_body(&self, lambda: &ast::ExprLambda) -> Vec<SqlResult> {
+        let args = &lambda.args;
+        let defaults = args
+            .posonlyargs
+            .iter()
+            .chain(&args.args)
+            .chain(&args.kwonlyargs)
+            .filter_map(|arg| Some((&arg.def.arg, arg.default.as_deref()?)))
+            .flat_map(|(name, default)| self.process_by_ident(name, default));
+
+        defaults
+            .chain(self.process_nested_calls([&*lambda.body]))
+            .collect()
+    }
+
+    /// Calls matching a function context anywhere in `exprs`, lambdas and comprehensions
+    /// included.
+    fn process_nested_calls<'a>(
+        &self,
+        exprs: impl IntoIterator<Item = &'a ast::Expr>,
+    ) -> Vec<SqlResult> {
+        let mut calls = Calls::default();
+        walk_exprs(&mut calls, exprs);
+
+        calls
+            .found
+            .iter()
+            .flat_map(|call| self.process_call_expr(call))
+            .chain(calls.lambdas.iter().flat_map(|l| self.process_lambda(l)))
+            .chain(
+                calls
+                    .comprehensions
+                    .iter()
+                    .flat_map(|comp| self.process_comprehension(comp)),
+            )
+            .collect()
+    }
+
+    /// Calls in the elements and conditions of a comprehension, the iterables aren't SQL. A
+    /// matched call taking the loop variable over a literal iterable,
+    /// `execute(q) for q in ("SELECT 1", "SELECT 2")`, is checked with each of its items.
+    fn process_comprehension(&self, comp: &Comprehension) -> Vec<SqlResult> {
+        self.within_scope(|| self.process_comprehension_body(comp))
+    }
+
+    fn process_comprehension_body(&self, comp: &Comprehension) -> Vec<SqlResult> {
+        let conditions = comp.generators.iter().flat_map(|g| &g.ifs);
+        let mut results = self.process_nested_calls(comp.elts.iter().copied().chain(conditions));
+
+        for (call, generator) in comp
+            .elts
+            .iter()
+            .filter_map(|elt| elt.as_call_expr())
+            .flat_map(|call| comp.generators.iter().map(move |g| (call, g)))
+        {
+            let function_name = Self::extract_function_name(&call.func);
+            let (ast::Expr::Name(target), Some(items)) =
+                (&generator.target, literal_items(&generator.iter))
+            else {
+                continue;
+            };
+            let takes_target = call
+                .args
+                .iter()
+                .any(|arg| matches!(arg, ast::Expr::Name(n) if n.id == target.id));
+            if !takes_target || !self.config.is_sql_function_name(&function_name) {
+                continue;
+            }
+
+            results.extend(items.iter().filter_map(|item| {
+                let content = self.extract_content(item)?;
+                content
+                    .get_str()
+                    .is_some_and(|s| self.config.is_context_sql_str(s))
+                    .then(|| SqlResult {
+                        byte_range: item.range().into(),
+                        variable_name: function_name.clone(),
+                        content,
+                        params: None,
+                        interpolation: None,
+                    })
+            }));
+        }
+        results
+    }
+
+    fn process_calls<'a>(&self, exprs: impl IntoIterator<Item = &'a ast::Expr>) -> Vec<SqlResult> {
+        exprs
+            .into_iter()
+            .flat_map(|expr| match expr {
+                ast::Expr::Call(call) => self.process_call_expr(call),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn process_named_exprs<'a>(
+        &self,
+        exprs: impl IntoIterator<Item = &'a ast::Expr>,
+    ) -> Vec<SqlResult> {
+        let mut named = NamedExprs::default();
+        walk_exprs(&mut named, exprs);
+        named
+            .found
+            .iter()
+            .flat_map(|n| self.process_assignment_target(&n.target, &n.value))
+            .collect()
+    }
+
+    /// `with conn.execute("SELECT ...") as result:`, the context expressions are checked like
+    /// call statements and bound like assignments to their `as` targets.
+    pub(super) fn analyze_with_items(
+        &self,
+        items: &[ast::WithItem],
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        items
+            .iter()
+            .flat_map(|item| {
+                let calls = match &item.context_expr {
+                    ast::Expr::Call(call) => self.process_call_expr(call),
+                    _ => vec![],
+                };
+                let bound = item
+                    .optional_vars
+                    .as_deref()
+                    .map_or_else(Vec::new, |target| {
+                        self.process_assignment_target(target, &item.context_expr)
+                    });
+                calls.into_iter().chain(bound)
+            })
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// `def run(query="SELECT ...")`, defaults of parameters named like SQL variables.
+    pub(super) fn analyze_arg_defaults(
+        &self,
+        args: &ast::Arguments,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        args.posonlyargs
+            .iter()
+            .chain(&args.args)
+            .chain(&args.kwonlyargs)
+            .filter_map(|arg| Some((&arg.def.arg, arg.default.as_deref()?)))
+            .flat_map(|(name, default)| self.process_by_ident(name, default))
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// `return "SELECT ..."` or `yield "SELECT ..."` in a function matching `return_contexts`,
+    /// named after the function.
+    pub(super) fn analyze_returned(
+        &self,
+        value: &ast::Expr,
+        function: &str,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        self.extract_content_flattened(value, function)
+            .into_iter()
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// `assert generated == "SELECT ..."` in tests, the expected literal is SQL written by hand.
+    pub(super) fn analyze_assert(
+        &self,
+        assert: &ast::StmtAssert,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        let ast::Expr::Compare(compare) = &*assert.test else {
+            return vec![];
+        };
+        if !compare.ops.iter().all(|op| matches!(op, ast::CmpOp::Eq)) {
+            return vec![];
+        }
+
+        std::iter::once(&*compare.left)
+            .chain(&compare.comparators)
+            .filter_map(|side| match side {
+                ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(s),
+                    range,
+                    ..
+                }) if self.config.is_sql_str(s) => Some(SqlResult {
+                    byte_range: (*range).into(),
+                    variable_name: ASSERT_EXPECTED_NAME.to_string(),
+                    content: FinderType::Str(s.clone()),
+                    params: None,
+                    interpolation: None,
+                }),
+                _ => None,
+            })
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
+
+    /// Bare string statements at module level that look like SQL, for modules used as query
+    /// catalogs. The module docstring is skipped.
+    pub(super) fn analyze_module_literals(
+        &self,
+        suite: &ast::Suite,
+        range_file: &PreanalyzedFile,
+    ) -> Vec<SqlString> {
+        suite
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, stmt)| match stmt {
+                ast::Stmt::Expr(ast::StmtExpr { value, range }) if idx > 0 => match &**value {
+                    ast::Expr::Constant(ast::ExprConstant {
+                        value: ast::Constant::Str(s),
+                        ..
+                    }) if !range_file.is_disabled_at(range.start().to_usize())
+                        && s.trim().len() >= MIN_MODULE_LITERAL_LEN
+                        && self.config.is_sql_str(s) =>
+                    {
+                        Some(SqlResult {
+                            byte_range: value.range().into(),
+                            variable_name: MODULE_LITERAL_NAME.to_string(),
+                            content: FinderType::Str(s.clone()),
+                            params: None,
+                            interpolation: None,
+                        })
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .filter_map(|result| sql_result_to_string(result, range_file))
+            .collect()
+    }
 }
 
+const ASSERT_EXPECTED_NAME: &str = "<assert-expected>";
+const MODULE_LITERAL_NAME: &str = "<module-literal>";
+/// Bare literals shorter than this are rarely SQL, `"SHOW TABLES"` is about as short as it gets.
+const MIN_MODULE_LITERAL_LEN: usize = 10;
+
 // Internal processing
 impl SqlFinder {
     fn process_expr_stmt(&self, value: &ast::Expr) -> Vec<SqlResult> {
@@ -63,58 +470,279 @@ impl SqlFinder {
                 ast::Expr::Call(call) => self.process_call_expr(call),
                 _ => bail_with!(vec![], "Unhandled expr_stmt value pattern: {value:?}"),
             },
-            ast::Expr::Constant(_) => vec![],
+            // Lambdas and comprehensions are covered by `analyze_nested_scopes`
+            ast::Expr::Constant(_)
+            | ast::Expr::Lambda(_)
+            | ast::Expr::ListComp(_)
+            | ast::Expr::SetComp(_)
+            | ast::Expr::DictComp(_)
+            | ast::Expr::GeneratorExp(_) => vec![],
+            ast::Expr::Yield(ast::ExprYield { value: None, .. }) => vec![],
+            ast::Expr::Yield(ast::ExprYield {
+                value: Some(value), ..
+            })
+            | ast::Expr::YieldFrom(ast::ExprYieldFrom { value, .. })
+            | ast::Expr::Await(ast::ExprAwait { value, .. }) => self.process_expr_stmt(value),
             _ => {
                 bail_with!(vec![], "Unhandled expr_stmt value pattern: {value:?}")
             }
         }
     }
 
+    /// Calls in an assigned value. Where no target took the value, any call matching a function
+    /// context is checked, `operations = [migrations.RunSQL("...")]`. Otherwise the calls were
+    /// extracted through the targets already and only `partial` bindings are left, the bound
+    /// arguments of `job = functools.partial(cursor.execute, "UPDATE ...")` are checked like
+    /// arguments of the bound function. Lambdas and comprehensions are left to
+    /// `analyze_nested_scopes`.
+    fn process_value_calls(&self, value: &ast::Expr, untaken: bool) -> Vec<SqlResult> {
+        match value {
+            _ if untaken => {
+                let mut calls = Calls::default();
+                walk_expr(&mut calls, value);
+                calls
+                    .found
+                    .iter()
+                    .flat_map(|call| self.process_call_expr(call))
+                    .collect()
+            }
+            ast::Expr::Call(call) if partial_target(call).is_some() => self.process_call_expr(call),
+            _ => vec![],
+        }
+    }
+
     fn process_call_expr(&self, call: &ast::ExprCall) -> Vec<SqlResult> {
-        let function_name = Self::extract_function_name(&call.func);
+        let (function_name, args) = partial_target(call).map_or_else(
+            || (Self::extract_function_name(&call.func), &call.args[..]),
+            |bound| (Self::extract_function_name(bound), &call.args[1..]),
+        );
 
-        if !self.config.is_sql_function_name(&function_name) {
+        if self.config.is_excluded_function_name(&function_name) {
+            debug!("Skipping excluded function call '{function_name}'");
+            self.explain(
+                &function_name,
+                "call matches excluded_function_contexts",
+                call.range.into(),
+            );
             return vec![];
         }
+        if !self.config.is_sql_function_name(&function_name) {
+            return self.process_keyword_contexts(call, &function_name);
+        }
 
         let process_expr = |expr: &ast::Expr| -> Option<SqlResult> {
+            // SQL variables passed along were already checked where they were assigned
+            if matches!(expr, ast::Expr::Name(n) if self.config.is_sql_variable_name(&n.id)) {
+                return None;
+            }
             self.extract_content(expr).and_then(|content| {
                 content
                     .get_str()
-                    .is_some_and(|s| self.config.is_sql_str(s))
+                    .is_some_and(|s| {
+                        self.config.is_context_sql_str(s) && self.config.is_long_enough(&content)
+                    })
                     .then_some(SqlResult {
                         byte_range: call.range.into(),
                         variable_name: function_name.clone(),
                         content,
+                        params: None,
+                        interpolation: None,
                     })
             })
         };
-        let kwargs = call
-            .keywords
+        let specs = self.config.sql_arguments(&function_name);
+        let is_sql_arg = |spec: ArgSpec| specs.as_ref().is_none_or(|s| s.contains(&&spec));
+        let kwargs = call.keywords.iter().filter(|kw| {
+            kw.arg
+                .as_ref()
+                .is_none_or(|name| is_sql_arg(ArgSpec::Keyword(name.to_string())))
+        });
+        // Arguments the context names may hold a list of statements, like Django's
+        // `RunSQL(["...", "..."])`. Parameters passed to other contexts aren't taken apart.
+        let sql_args: Vec<&ast::Expr> = args
             .iter()
-            .filter_map(|kw| process_expr(&kw.value));
+            .enumerate()
+            .filter(|(i, _)| is_sql_arg(ArgSpec::Position(*i)))
+            .map(|(_, arg)| arg)
+            .chain(kwargs.map(|kw| &kw.value))
+            .flat_map(|arg| {
+                if specs.is_some() {
+                    flatten_sequences(arg)
+                } else {
+                    vec![arg]
+                }
+            })
+            .collect();
 
-        call.args
+        let mut results: Vec<(SqlResult, &ast::Expr)> = sql_args
             .iter()
-            .filter_map(process_expr)
-            .chain(kwargs)
+            .filter_map(|arg| Some((process_expr(arg)?, *arg)))
+            .collect();
+        // Several strings of one call are reported at their own argument to tell them apart
+        if results.len() > 1 {
+            for (result, arg) in &mut results {
+                result.byte_range = arg.range().into();
+            }
+        }
+        // Parameters follow the SQL, `cursor.execute(sql, (a, b))`
+        if let [(result, arg)] = &mut results[..]
+            && args.first().is_some_and(|first| std::ptr::eq(*arg, first))
+        {
+            let rows = function_name.rsplit('.').next() == Some("executemany");
+            result.params = args.get(1).and_then(|params| call_params(params, rows));
+        }
+        if self.config.interpolation_detection() {
+            for (result, arg) in &mut results {
+                result.interpolation = self.interpolation(arg);
+            }
+        }
+        let results: Vec<SqlResult> = results.into_iter().map(|(result, _)| result).collect();
+        // Only the first argument is expected to hold the SQL, the rest are usually parameters
+        if results.is_empty()
+            && let Some(first) = sql_args.first()
+            && !matches!(first, ast::Expr::Name(n) if self.config.is_sql_variable_name(&n.id))
+        {
+            let extracted = self
+                .extract_content(first)
+                .is_some_and(|content| !content.is_placeholder());
+            self.note_unextractable(&function_name, first, extracted);
+        }
+        results
+    }
+
+    /// How the SQL of `expr` is put together, looking through passthrough wrappers.
+    fn interpolation(&self, expr: &ast::Expr) -> Option<Interpolation> {
+        match expr {
+            ast::Expr::JoinedStr(j)
+                if j.values
+                    .iter()
+                    .any(|value| matches!(value, ast::Expr::FormattedValue(_))) =>
+            {
+                Some(Interpolation::Fstring)
+            }
+            ast::Expr::BinOp(ast::ExprBinOp {
+                op: Operator::Mod, ..
+            }) => Some(Interpolation::Percent),
+            ast::Expr::BinOp(ast::ExprBinOp {
+                op: Operator::Add, ..
+            }) => Some(Interpolation::Concat),
+            ast::Expr::Call(call) => match &*call.func {
+                ast::Expr::Attribute(ast::ExprAttribute { attr, .. })
+                    if matches!(
+                        attr.as_str(),
+                        "format" | "format_map" | "substitute" | "safe_substitute"
+                    ) =>
+                {
+                    Some(Interpolation::Format)
+                }
+                ast::Expr::Attribute(ast::ExprAttribute { attr, .. })
+                    if attr.as_str() == "join" =>
+                {
+                    Some(Interpolation::Concat)
+                }
+                func if self
+                    .config
+                    .is_passthrough_wrapper(&Self::extract_function_name(func)) =>
+                {
+                    self.interpolation(call.args.first()?)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Keyword arguments matching a keyword context in calls of any other function, reported as
+    /// `run(statement=...)`.
+    fn process_keyword_contexts(
+        &self,
+        call: &ast::ExprCall,
+        function_name: &str,
+    ) -> Vec<SqlResult> {
+        call.keywords
+            .iter()
+            .filter_map(|kw| {
+                let name = kw.arg.as_ref()?;
+                if !self.config.is_keyword_context(name) {
+                    return None;
+                }
+                let content = self.extract_content(&kw.value)?;
+                content
+                    .get_str()
+                    .is_some_and(|s| {
+                        self.config.is_context_sql_str(s) && self.config.is_long_enough(&content)
+                    })
+                    .then(|| SqlResult {
+                        byte_range: call.range.into(),
+                        variable_name: format!("{function_name}({name}=...)"),
+                        content,
+                        params: None,
+                        interpolation: None,
+                    })
+            })
             .collect()
     }
 
     fn extract_content_flattened(&self, expr: &ast::Expr, variable_name: &str) -> Vec<SqlResult> {
+        // Numbers, `None` and the like in a collection of queries aren't SQL
+        let is_non_str_const = |elem: &ast::Expr| match elem {
+            ast::Expr::Constant(c) => {
+                !matches!(c.value, ast::Constant::Str(_) | ast::Constant::Tuple(_))
+            }
+            _ => false,
+        };
         match expr {
-            ast::Expr::List(ast::ExprList { elts, .. }) => elts
+            ast::Expr::List(ast::ExprList { elts, .. })
+            | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => elts
                 .iter()
+                .filter(|elem| !is_non_str_const(elem))
                 .flat_map(|elem| self.extract_content_flattened(elem, variable_name))
                 .collect(),
-            ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => elts
+            // Folded tuple constants don't keep the ranges of their items, they share the
+            // range of the whole tuple
+            ast::Expr::Constant(ast::ExprConstant {
+                value: ast::Constant::Tuple(items),
+                range,
+                ..
+            }) => items
                 .iter()
-                .flat_map(|elem| self.extract_content_flattened(elem, variable_name))
+                .filter_map(|item| match item {
+                    ast::Constant::Str(s) => Some(SqlResult {
+                        byte_range: (*range).into(),
+                        variable_name: variable_name.to_string(),
+                        content: FinderType::Str(s.clone()),
+                        params: None,
+                        interpolation: None,
+                    }),
+                    _ => None,
+                })
+                .filter(|result| self.config.is_long_enough(&result.content))
                 .collect(),
 
-            ast::Expr::Dict(ast::ExprDict { values, .. }) => values
+            // A name on its own carries over the string constant bound to it or the SQL of
+            // another SQL variable, whatever else was bound to it is left alone like a fully
+            // dynamic value
+            ast::Expr::Name(name)
+                if self.constant(&name.id).is_none()
+                    && !self.config.is_sql_variable_name(&name.id) =>
+            {
+                vec![]
+            }
+
+            // Entries are named after their key when it's a string, `queries["get_user"]`
+            ast::Expr::Dict(ast::ExprDict { keys, values, .. }) => keys
                 .iter()
-                .flat_map(|elem| self.extract_content_flattened(elem, variable_name))
+                .zip(values)
+                .flat_map(|(key, value)| {
+                    let name = match key {
+                        Some(ast::Expr::Constant(ast::ExprConstant {
+                            value: ast::Constant::Str(key),
+                            ..
+                        })) => format!("{variable_name}[\"{key}\"]"),
+                        _ => variable_name.to_string(),
+                    };
+                    self.extract_content_flattened(value, &name)
+                })
                 .collect(),
             ast::Expr::BoolOp(ast::ExprBoolOp { values, .. }) => values
                 .iter()
@@ -128,39 +756,68 @@ impl SqlFinder {
                     || *op == Operator::Div =>
             {
                 self.extract_from_bin_op(bin)
+                    .filter(|content| self.config.is_long_enough(content))
                     .map_or_else(Vec::new, |content| {
                         vec![SqlResult {
                             byte_range: expr.range().into(),
                             variable_name: variable_name.to_string(),
                             content,
+                            params: None,
+                            interpolation: None,
                         }]
                     })
             }
 
-            _ => self.extract_content(expr).map_or_else(Vec::new, |content| {
-                vec![SqlResult {
-                    byte_range: expr.range().into(),
-                    variable_name: variable_name.to_string(),
-                    content,
-                }]
-            }),
+            _ => self
+                .extract_content(expr)
+                .filter(|content| self.config.is_long_enough(content))
+                .map_or_else(Vec::new, |content| {
+                    vec![SqlResult {
+                        byte_range: expr.range().into(),
+                        variable_name: variable_name.to_string(),
+                        content,
+                        params: None,
+                        interpolation: None,
+                    }]
+                }),
         }
     }
 
     fn process_assignment_target(&self, target: &ast::Expr, value: &ast::Expr) -> Vec<SqlResult> {
         match target {
             ast::Expr::Name(name) => self.process_by_ident(&name.id, value),
-            ast::Expr::Attribute(att) => self.process_by_ident(&att.attr, value),
+            ast::Expr::Attribute(att) => {
+                if self.is_sql_attribute_target(att) {
+                    let results = self.extract_content_flattened(value, &att.attr);
+                    self.note_unextractable(&att.attr, value, !all_placeholders(&results));
+                    return results;
+                }
+                vec![]
+            }
             ast::Expr::Tuple(tuple) => self.handle_tuple_assignment(&tuple.elts, value),
             ast::Expr::List(list) => self.handle_tuple_assignment(&list.elts, value),
-            ast::Expr::Subscript(_) => vec![],
+            ast::Expr::Subscript(_) => self.process_subscript_target(target, value),
             _ => bail_with!(vec![], "Unhandled assignment target pattern: {:?}", target),
         }
     }
 
+    /// `QUERIES["fetch_orders"] = "..."`, matched on the container name or any string key and
+    /// named like dict entries. Keys that aren't string literals show up as `[...]`.
+    fn process_subscript_target(&self, target: &ast::Expr, value: &ast::Expr) -> Vec<SqlResult> {
+        let Some((name, idents)) = subscript_target_name(target) else {
+            return vec![];
+        };
+        if idents.iter().any(|i| self.config.is_sql_variable_name(i)) {
+            return self.extract_content_flattened(value, &name);
+        }
+        vec![]
+    }
+
     fn process_by_ident(&self, name: &Identifier, value: &ast::Expr) -> Vec<SqlResult> {
         if self.config.is_sql_variable_name(name) {
-            return self.extract_content_flattened(value, name);
+            let results = self.extract_content_flattened(value, name);
+            self.note_unextractable(name, value, !all_placeholders(&results));
+            return results;
         }
         vec![]
     }
@@ -224,10 +881,78 @@ impl SqlFinder {
         results
     }
 
+    /// Records `value` of a matched variable or call unless something was `extracted` from it.
+    /// Values that only reset the variable, `None` or an empty collection, are left out.
+    fn note_unextractable(&self, name: &str, value: &ast::Expr, extracted: bool) {
+        let is_unset = match value {
+            ast::Expr::Constant(c) => c.value.is_none(),
+            ast::Expr::List(ast::ExprList { elts, .. })
+            | ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+            | ast::Expr::Set(ast::ExprSet { elts, .. }) => elts.is_empty(),
+            ast::Expr::Dict(d) => d.keys.is_empty(),
+            _ => false,
+        };
+        if !self.config.report_unextractable() || is_unset || extracted {
+            return;
+        }
+        self.unextractable
+            .borrow_mut()
+            .push((name.to_string(), value.range().into()));
+    }
+
+    /// The values recorded by `note_unextractable`, as strings without content.
+    pub(crate) fn take_unextractable(&self, range_file: &PreanalyzedFile) -> Vec<SqlString> {
+        self.unextractable
+            .take()
+            .into_iter()
+            .map(|(name, byte_range)| {
+                let mut s = SqlString::new(
+                    name,
+                    String::new(),
+                    range_file.byterange_to_range(byte_range),
+                );
+                s.unextractable = true;
+                s
+            })
+            .collect()
+    }
+
+    /// Records why the call of `name` at `byte_range` was skipped, when extraction is explained.
+    fn explain(&self, name: &str, reason: &str, byte_range: ByteRange) {
+        if self.config.explain_extraction() {
+            self.explanations
+                .borrow_mut()
+                .push((name.to_string(), reason.to_string(), byte_range));
+        }
+    }
+
+    /// The skipped calls recorded by `explain`.
+    pub(crate) fn take_explanations(&self, range_file: &PreanalyzedFile) -> Vec<Explanation> {
+        self.explanations
+            .take()
+            .into_iter()
+            .map(|(function_name, reason, byte_range)| Explanation {
+                function_name,
+                reason,
+                range: range_file.byterange_to_range(byte_range),
+            })
+            .collect()
+    }
+
+    /// `self.db.query = ...` matches on `query` as well as on the whole `self.db.query`.
+    fn is_sql_attribute_target(&self, att: &ast::ExprAttribute) -> bool {
+        self.config.is_sql_variable_name(&att.attr)
+            || self.config.is_sql_variable_name(&format!(
+                "{}.{}",
+                Self::extract_function_name(&att.value),
+                att.attr
+            ))
+    }
+
     fn target_contains_sql_variable(&self, target: &ast::Expr) -> bool {
         match target {
             ast::Expr::Name(name) => self.config.is_sql_variable_name(&name.id),
-            ast::Expr::Attribute(att) => self.config.is_sql_variable_name(&att.attr),
+            ast::Expr::Attribute(att) => self.is_sql_attribute_target(att),
             ast::Expr::Tuple(tuple) => tuple
                 .elts
                 .iter()
@@ -244,7 +969,7 @@ impl SqlFinder {
 
 // Content extraction
 impl SqlFinder {
-    fn extract_function_name(func_expr: &ast::Expr) -> String {
+    pub(crate) fn extract_function_name(func_expr: &ast::Expr) -> String {
         match func_expr {
             ast::Expr::Name(name) => name.id.to_string(),
             ast::Expr::Attribute(attr) => {
@@ -252,6 +977,9 @@ impl SqlFinder {
                     format!("{}.{}", Self::extract_function_name(&attr.value), attr.attr);
                 full_name
             }
+            // `a().b()` and `pools["main"]` render as their callee and receiver, `a.b` and `pools`
+            ast::Expr::Call(call) => Self::extract_function_name(&call.func),
+            ast::Expr::Subscript(sub) => Self::extract_function_name(&sub.value),
             _ => bail_with!(
                 String::new(),
                 "Unknown function expression: {:?}",
@@ -260,15 +988,29 @@ impl SqlFinder {
         }
     }
 
-    fn extract_content(&self, expr: &ast::Expr) -> Option<FinderType> {
+    pub(crate) fn extract_content(&self, expr: &ast::Expr) -> Option<FinderType> {
         match expr {
             ast::Expr::Constant(c) => Self::extract_expr_const(c),
             ast::Expr::Call(c) => self.extract_call(c),
+            // `{limit:>10}` and `{name!r}` render differently than the value, the whole field
+            // is a single placeholder, nested fields in the spec included. So is a nested
+            // f-string, `{f'{schema}.{table}'}` is one interpolation as far as the SQL goes.
+            ast::Expr::FormattedValue(f)
+                if f.format_spec.is_some()
+                    || f.conversion != ast::ConversionFlag::None
+                    || matches!(&*f.value, ast::Expr::JoinedStr(_)) =>
+            {
+                Some(FinderType::Placeholder(PlaceholderKind::Value))
+            }
             ast::Expr::FormattedValue(f) => self.extract_content(&f.value),
             ast::Expr::BinOp(b) => self.extract_from_bin_op(b),
 
-            ast::Expr::Subscript(_) | ast::Expr::Name(_) | ast::Expr::Attribute(_) => {
-                Some(FinderType::Placeholder)
+            ast::Expr::Name(name) => Some(self.constant(&name.id).map_or(
+                FinderType::Placeholder(PlaceholderKind::Value),
+                FinderType::Str,
+            )),
+            ast::Expr::Subscript(_) | ast::Expr::Attribute(_) => {
+                Some(FinderType::Placeholder(PlaceholderKind::Value))
             }
 
             ast::Expr::JoinedStr(j) => {
@@ -319,6 +1061,12 @@ impl SqlFinder {
                         let kwargs: Vec<_> = keys.into_iter().zip(values).collect();
                         (vec![], kwargs)
                     }
+                    ast::Expr::Call(_)
+                    | ast::Expr::Name(_)
+                    | ast::Expr::Attribute(_)
+                    | ast::Expr::Subscript(_) => {
+                        (self.extract_content(&v.right).into_iter().collect(), vec![])
+                    }
                     _ => bail_with!((vec![], vec![]), "Unhandled rhs expr type: {:?}", v.right),
                 };
                 match expr_content {
@@ -346,6 +1094,7 @@ impl SqlFinder {
             ast::Operator::Sub => lhs_content - rhs_content,
             ast::Operator::Mult => lhs_content * rhs_content,
             ast::Operator::Div => lhs_content / rhs_content,
+            ast::Operator::FloorDiv => lhs_content.floor_div(rhs_content),
             _ => bail!(None, "Unexpected operator in extraction: {:?}", op),
         }
     }
@@ -355,8 +1104,44 @@ impl SqlFinder {
             ast::Expr::Call(nested_call) => self.extract_call(nested_call),
             ast::Expr::Attribute(ast::ExprAttribute { attr, value, .. }) => match attr.as_str() {
                 "format" => self.extract_format_call(&v.args, &v.keywords, value),
-                _ => Some(FinderType::Placeholder),
+                "format_map" => self.extract_format_map_call(&v.args, value),
+                "substitute" | "safe_substitute" => {
+                    self.extract_template_call(&v.args, &v.keywords, value)
+                }
+                "join" => Some(
+                    self.extract_join_call(&v.args, value)
+                        .unwrap_or(FinderType::Placeholder(PlaceholderKind::Joined)),
+                ),
+                "dedent" if matches!(&**value, ast::Expr::Name(m) if m.id.as_str() == "textwrap") => {
+                    self.extract_dedent_call(&v.args)
+                }
+                "field" if matches!(&**value, ast::Expr::Name(m) if m.id.as_str() == "dataclasses") => {
+                    self.extract_field_call(&v.keywords)
+                }
+                "strip" | "lstrip" | "rstrip" | "upper" | "lower" | "replace" => {
+                    self.extract_str_method(attr, &v.args, value)
+                }
+                _ if self
+                    .config
+                    .is_passthrough_wrapper(&Self::extract_function_name(&v.func)) =>
+                {
+                    self.extract_content(v.args.first()?)
+                }
+                // Other methods of a literal keep it as is, anything else is a runtime value
+                _ => match self.extract_content(value) {
+                    Some(FinderType::Str(s)) => Some(FinderType::Str(s)),
+                    _ => Some(FinderType::Placeholder(PlaceholderKind::Value)),
+                },
             },
+            ast::Expr::Name(name) if name.id.as_str() == "dedent" => {
+                self.extract_dedent_call(&v.args)
+            }
+            ast::Expr::Name(name) if name.id.as_str() == "field" => {
+                self.extract_field_call(&v.keywords)
+            }
+            ast::Expr::Name(name) if self.config.is_passthrough_wrapper(&name.id) => {
+                self.extract_content(v.args.first()?)
+            }
             ast::Expr::Name(name) => {
                 if self.config.is_sql_function_name(&name.id) {
                     v.args.iter().find_map(|arg| self.extract_content(arg))
@@ -368,6 +1153,96 @@ impl SqlFinder {
         }
     }
 
+    /// `" ".join([...])` over a literal list, elements only known at runtime become placeholders.
+    /// Anything else is a runtime list and left to the caller.
+    fn extract_join_call(&self, args: &[ast::Expr], separator: &ast::Expr) -> Option<FinderType> {
+        let ast::Expr::Constant(ast::ExprConstant {
+            value: ast::Constant::Str(separator),
+            ..
+        }) = separator
+        else {
+            return None;
+        };
+        let (ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. })) = args.first()?
+        else {
+            return None;
+        };
+
+        let parts: Vec<String> = elts
+            .iter()
+            .map(|elt| {
+                self.extract_content(elt)
+                    .unwrap_or(FinderType::Placeholder(PlaceholderKind::Value))
+                    .to_string()
+            })
+            .collect();
+        Some(FinderType::Str(parts.join(separator)))
+    }
+
+    fn extract_dedent_call(&self, args: &[ast::Expr]) -> Option<FinderType> {
+        match self.extract_content(args.first()?)? {
+            FinderType::Str(s) => Some(FinderType::Str(dedent(&s))),
+            other => Some(other),
+        }
+    }
+
+    /// `"  SELECT ...  ".strip()` or `BASE_SQL.replace(":tbl", "users")` applied to the
+    /// extracted string. Replacements only known at runtime become placeholders, receivers that
+    /// aren't strings are passed through.
+    fn extract_str_method(
+        &self,
+        method: &str,
+        args: &[ast::Expr],
+        receiver: &ast::Expr,
+    ) -> Option<FinderType> {
+        let content = self.extract_content(receiver)?;
+        let FinderType::Str(s) = &content else {
+            return Some(content);
+        };
+        let literal = |idx: usize| match args.get(idx) {
+            Some(ast::Expr::Constant(ast::ExprConstant {
+                value: ast::Constant::Str(s),
+                ..
+            })) => Some(s.as_str()),
+            _ => None,
+        };
+        let strip_chars = |c: char| literal(0).map_or(c.is_whitespace(), |chars| chars.contains(c));
+
+        let result = match method {
+            "strip" => s.trim_matches(strip_chars).to_string(),
+            "lstrip" => s.trim_start_matches(strip_chars).to_string(),
+            "rstrip" => s.trim_end_matches(strip_chars).to_string(),
+            "upper" => s.to_uppercase(),
+            "lower" => s.to_lowercase(),
+            "replace" => {
+                let Some(old) = literal(0) else {
+                    return Some(content);
+                };
+                let new = literal(1).map_or_else(
+                    || FinderType::Placeholder(PlaceholderKind::Value).to_string(),
+                    str::to_string,
+                );
+                s.replace(old, &new)
+            }
+            _ => return Some(content),
+        };
+        Some(FinderType::Str(result))
+    }
+
+    /// Dataclass `field(default="...")`, or the value of a `default_factory=lambda: "..."`.
+    fn extract_field_call(&self, kwargs: &[ast::Keyword]) -> Option<FinderType> {
+        kwargs
+            .iter()
+            .find_map(|kw| match (kw.arg.as_deref(), &kw.value) {
+                (Some("default"), value) => self.extract_content(value),
+                (Some("default_factory"), ast::Expr::Lambda(lambda)) => {
+                    self.extract_content(&lambda.body)
+                }
+                _ => None,
+            })
+    }
+
     fn extract_format_call(
         &self,
         args: &[ast::Expr],
@@ -376,12 +1251,11 @@ impl SqlFinder {
     ) -> Option<FinderType> {
         let mut pos_fills = vec![];
         let mut kw_fills = vec![];
-        let mut has_unpacked_dict = false;
         for a in args {
             let parsed = match a {
                 ast::Expr::Constant(c) => Self::extract_expr_const(c).into_iter().collect(),
                 ast::Expr::Subscript(_) | ast::Expr::Name(_) | ast::Expr::Call(_) => {
-                    vec![FinderType::Placeholder]
+                    vec![FinderType::Placeholder(PlaceholderKind::Value)]
                 }
                 ast::Expr::List(els) => els
                     .elts
@@ -390,33 +1264,105 @@ impl SqlFinder {
                     .collect(),
                 ast::Expr::BinOp(b) => self
                     .extract_from_bin_op(b)
-                    .map_or_else(|| vec![], |content| vec![content]),
+                    .map_or_else(Vec::new, |content| vec![content]),
                 _ => bail_with!(vec![], "Unhandled value in args: {a:?}"),
             };
             pos_fills.extend(parsed.iter().map(std::string::ToString::to_string));
         }
+        // Whatever `**mapping` fills in is only known at runtime, its fields stay placeholders
         for kw in kwargs {
-            match &kw.arg {
-                Some(name) => {
-                    if let Some(val) = self.extract_content(&kw.value) {
-                        kw_fills.push((name.clone(), val));
-                    }
-                }
-                None => has_unpacked_dict = true,
+            if let Some(name) = &kw.arg
+                && let Some(val) = self.extract_content(&kw.value)
+            {
+                kw_fills.push((name.to_string(), val.to_string()));
             }
         }
         let base_content = self.extract_content(value)?;
-        let mut result = base_content.to_string();
-        if !has_unpacked_dict {
-            for f in pos_fills {
-                result = result.replacen("{}", &f, 1);
+        Some(FinderType::Str(substitute_format_fields(
+            &base_content.to_string(),
+            &pos_fills,
+            &kw_fills,
+        )))
+    }
+
+    /// `Template("... $table ...").substitute(table="users")`, values come from literal keyword
+    /// arguments or a literal dict. Anything only known at runtime becomes a placeholder.
+    fn extract_template_call(
+        &self,
+        args: &[ast::Expr],
+        kwargs: &[ast::Keyword],
+        receiver: &ast::Expr,
+    ) -> Option<FinderType> {
+        let ast::Expr::Call(template) = receiver else {
+            return Some(FinderType::Placeholder(PlaceholderKind::Value));
+        };
+        let is_template = match &*template.func {
+            ast::Expr::Name(n) => n.id.as_str() == "Template",
+            ast::Expr::Attribute(a) => {
+                a.attr.as_str() == "Template"
+                    && matches!(&*a.value, ast::Expr::Name(m) if m.id.as_str() == "string")
             }
-            for (kw_name, val) in &kw_fills {
-                let pat = format!("{{{kw_name}}}");
-                result = result.replace(&pat, &val.to_string());
+            _ => false,
+        };
+        if !is_template {
+            return Some(FinderType::Placeholder(PlaceholderKind::Value));
+        }
+        let Some(FinderType::Str(text)) = self.extract_content(template.args.first()?) else {
+            return Some(FinderType::Placeholder(PlaceholderKind::Value));
+        };
+
+        let mut values = vec![];
+        if let Some(ast::Expr::Dict(ast::ExprDict {
+            keys, values: vals, ..
+        })) = args.first()
+        {
+            for (key, val) in keys.iter().zip(vals) {
+                if let Some(ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(key),
+                    ..
+                })) = key
+                    && let Some(val) = self.extract_content(val)
+                {
+                    values.push((key.clone(), val));
+                }
             }
         }
-        Some(FinderType::Str(result))
+        values.extend(kwargs.iter().filter_map(|kw| {
+            Some((
+                kw.arg.as_ref()?.to_string(),
+                self.extract_content(&kw.value)?,
+            ))
+        }));
+
+        substitute_template(&text, &values).map(FinderType::Str)
+    }
+
+    /// `"...".format_map(mapping)`, fields are filled from a literal dict with string keys and
+    /// become placeholders otherwise.
+    fn extract_format_map_call(&self, args: &[ast::Expr], value: &ast::Expr) -> Option<FinderType> {
+        let base_content = self.extract_content(value)?;
+        let FinderType::Str(text) = base_content else {
+            return Some(base_content);
+        };
+
+        let mut kw_fills = vec![];
+        if let Some(ast::Expr::Dict(ast::ExprDict { keys, values, .. })) = args.first() {
+            for (key, val) in keys.iter().zip(values) {
+                if let Some(ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(key),
+                    ..
+                })) = key
+                    && let Some(val) = self.extract_content(val)
+                {
+                    kw_fills.push((key.clone(), val.to_string()));
+                }
+            }
+        }
+        Some(FinderType::Str(substitute_format_fields(
+            &text,
+            &[],
+            &kw_fills,
+        )))
     }
 
     fn extract_expr_const(c: &ast::ExprConstant<TextRange>) -> Option<FinderType> {
@@ -424,19 +1370,22 @@ impl SqlFinder {
     }
 
     fn extract_const(c: &ast::Constant) -> Option<FinderType> {
-        let result = match c {
+        match c {
             ast::Constant::Str(s) => Some(FinderType::Str(s.clone())),
+            // `cursor.execute(b"SELECT ...")` for drivers that want bytes
+            ast::Constant::Bytes(b) => {
+                Some(FinderType::Str(String::from_utf8_lossy(b).into_owned()))
+            }
             ast::Constant::Int(i) => Some(FinderType::Int(i.to_string())),
             ast::Constant::Bool(b) => Some(FinderType::Bool(*b)),
             ast::Constant::Float(f) => Some(FinderType::Float(*f)),
-            ast::Constant::None => None,
+            // `...` defaults in stubs and overloads
+            ast::Constant::None | ast::Constant::Ellipsis => None,
             ast::Constant::Tuple(t) => Some(FinderType::Tuple(
                 t.iter().filter_map(Self::extract_const).collect(),
             )),
             _ => bail_with!(None, "Unhandled Constant: {:?}", c),
-        };
-
-        result
+        }
     }
 }
 
@@ -444,9 +1393,257 @@ fn sql_result_to_string(res: SqlResult, range_file: &PreanalyzedFile) -> Option<
     if res.content.is_placeholder() {
         return None;
     }
-    Some(SqlString {
-        variable_name: res.variable_name,
-        range: range_file.byterange_to_range(res.byte_range),
-        sql_content: res.content.to_string(),
-    })
+    let mut sql_string = SqlString::from_rendered(
+        res.variable_name,
+        &res.content.to_string(),
+        range_file.byterange_to_range(res.byte_range),
+    );
+    sql_string.params = res.params;
+    sql_string.interpolation = res.interpolation;
+    Some(sql_string)
+}
+
+/// Parameters of a literal tuple, list or dict with string keys, for `rows` a list of those.
+/// Unpacked elements leave the count unknown.
+fn call_params(expr: &ast::Expr, rows: bool) -> Option<CallParams> {
+    match expr {
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+        | ast::Expr::List(ast::ExprList { elts, .. }) => {
+            if rows {
+                let rows = elts.iter().map(|row| call_params(row, false));
+                return rows.collect::<Option<_>>().map(CallParams::Rows);
+            }
+            (!elts.iter().any(|elt| matches!(elt, ast::Expr::Starred(_))))
+                .then_some(CallParams::Positional(elts.len()))
+        }
+        ast::Expr::Dict(ast::ExprDict { keys, .. }) if !rows => keys
+            .iter()
+            .map(|key| match key {
+                Some(ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(key),
+                    ..
+                })) => Some(key.clone()),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .map(CallParams::Named),
+        _ => None,
+    }
+}
+
+/// Nothing but placeholders came out of a value, `query = build_query(user_input)`.
+fn all_placeholders(results: &[SqlResult]) -> bool {
+    results.iter().all(|r| r.content.is_placeholder())
+}
+
+fn subscript_target_name(target: &ast::Expr) -> Option<(String, Vec<&str>)> {
+    match target {
+        ast::Expr::Name(name) => Some((name.id.to_string(), vec![name.id.as_str()])),
+        ast::Expr::Attribute(att) => Some((att.attr.to_string(), vec![att.attr.as_str()])),
+        ast::Expr::Subscript(sub) => {
+            let (base, mut idents) = subscript_target_name(&sub.value)?;
+            match &*sub.slice {
+                ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(key),
+                    ..
+                }) => {
+                    idents.push(key);
+                    Some((format!("{base}[\"{key}\"]"), idents))
+                }
+                _ => Some((format!("{base}[...]"), idents)),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct NamedExprs<'a> {
+    found: Vec<&'a ast::ExprNamedExpr>,
+}
+
+impl<'a> Visit<'a> for NamedExprs<'a> {
+    fn enter_expr(&mut self, expr: &'a ast::Expr) -> bool {
+        if let ast::Expr::NamedExpr(named) = expr {
+            self.found.push(named);
+        }
+        true
+    }
+}
+
+/// Every call in an expression, nested ones included. Lambdas and comprehensions are collected
+/// without descending, their bodies are analyzed on their own.
+#[derive(Default)]
+struct Calls<'a> {
+    found: Vec<&'a ast::ExprCall>,
+    lambdas: Vec<&'a ast::ExprLambda>,
+    comprehensions: Vec<Comprehension<'a>>,
+}
+
+/// Element expressions of a list, set, dict or generator comprehension, `key` and `value` for
+/// dicts.
+struct Comprehension<'a> {
+    elts: Vec<&'a ast::Expr>,
+    generators: &'a [ast::Comprehension],
+}
+
+impl<'a> Visit<'a> for Calls<'a> {
+    fn enter_expr(&mut self, expr: &'a ast::Expr) -> bool {
+        let (elts, generators) = match expr {
+            ast::Expr::Call(call) => {
+                self.found.push(call);
+                return true;
+            }
+            ast::Expr::Lambda(lambda) => {
+                self.lambdas.push(lambda);
+                return false;
+            }
+            ast::Expr::ListComp(ast::ExprListComp {
+                elt, generators, ..
+            })
+            | ast::Expr::SetComp(ast::ExprSetComp {
+                elt, generators, ..
+            })
+            | ast::Expr::GeneratorExp(ast::ExprGeneratorExp {
+                elt, generators, ..
+            }) => (vec![&**elt], generators),
+            ast::Expr::DictComp(ast::ExprDictComp {
+                key,
+                value,
+                generators,
+                ..
+            }) => (vec![&**key, &**value], generators),
+            _ => return true,
+        };
+        self.comprehensions.push(Comprehension { elts, generators });
+        false
+    }
+}
+
+/// The function bound by `partial(f, ...)` or `functools.partial(f, ...)`.
+fn partial_target(call: &ast::ExprCall) -> Option<&ast::Expr> {
+    let is_partial = match &*call.func {
+        ast::Expr::Name(n) => n.id.as_str() == "partial",
+        ast::Expr::Attribute(a) => {
+            a.attr.as_str() == "partial"
+                && matches!(&*a.value, ast::Expr::Name(m) if m.id.as_str() == "functools")
+        }
+        _ => false,
+    };
+    call.args
+        .first()
+        .filter(|f| is_partial && matches!(f, ast::Expr::Name(_) | ast::Expr::Attribute(_)))
+}
+
+fn literal_items(expr: &ast::Expr) -> Option<&[ast::Expr]> {
+    match expr {
+        ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+        | ast::Expr::Set(ast::ExprSet { elts, .. }) => Some(elts),
+        _ => None,
+    }
+}
+
+/// `Enum`, `StrEnum` and their `enum.` qualified forms.
+fn is_enum_base(base: &ast::Expr) -> bool {
+    let name = match base {
+        ast::Expr::Name(n) => n.id.as_str(),
+        ast::Expr::Attribute(a) => a.attr.as_str(),
+        _ => return false,
+    };
+    matches!(name, "Enum" | "StrEnum")
+}
+
+fn header_exprs(stmt: &ast::Stmt) -> Vec<&ast::Expr> {
+    match stmt {
+        ast::Stmt::Expr(ast::StmtExpr { value, .. })
+        | ast::Stmt::Assign(ast::StmtAssign { value, .. })
+        | ast::Stmt::AugAssign(ast::StmtAugAssign { value, .. })
+        | ast::Stmt::If(ast::StmtIf { test: value, .. })
+        | ast::Stmt::While(ast::StmtWhile { test: value, .. })
+        | ast::Stmt::For(ast::StmtFor { iter: value, .. })
+        | ast::Stmt::AsyncFor(ast::StmtAsyncFor { iter: value, .. })
+        | ast::Stmt::Match(ast::StmtMatch { subject: value, .. }) => vec![value],
+        ast::Stmt::Assert(ast::StmtAssert { test, msg, .. }) => {
+            std::iter::once(&**test).chain(msg.as_deref()).collect()
+        }
+        ast::Stmt::AnnAssign(ast::StmtAnnAssign { value, .. })
+        | ast::Stmt::Return(ast::StmtReturn { value, .. }) => value.iter().map(|v| &**v).collect(),
+        ast::Stmt::With(ast::StmtWith { items, .. })
+        | ast::Stmt::AsyncWith(ast::StmtAsyncWith { items, .. }) => {
+            items.iter().map(|item| &item.context_expr).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Joins every `+=` fragment with the latest earlier string of the same variable. Fragments
+/// without one are kept, an enclosing suite may still hold the initial assignment.
+pub(crate) fn resolve_appends(strings: &mut [SqlString]) {
+    for i in 0..strings.len() {
+        if !strings[i].appended {
+            continue;
+        }
+        let (before, rest) = strings.split_at_mut(i);
+        let fragment = &mut rest[0];
+        let Some(base) = before
+            .iter()
+            .rev()
+            .find(|s| !s.appended && s.variable_name == fragment.variable_name)
+        else {
+            continue;
+        };
+
+        let offset = base.sql_content.len();
+        fragment.sql_content = base.sql_content.clone() + &fragment.sql_content;
+        fragment.dynamic_spans = base
+            .dynamic_spans
+            .iter()
+            .copied()
+            .chain(
+                fragment
+                    .dynamic_spans
+                    .iter()
+                    .map(|span| crate::DynamicSpan {
+                        start: span.start + offset,
+                        end: span.end + offset,
+                        joined: span.joined,
+                    }),
+            )
+            .collect();
+        fragment.appended = false;
+        fragment.extends = true;
+    }
+}
+
+/// Removes every string that a later string of the same variable extends, leaving the final
+/// value of a query built over several statements. The base is matched by content, the latest
+/// string of the variable may come from a branch the extending one doesn't follow.
+pub(crate) fn drop_superseded(strings: &mut Vec<SqlString>) {
+    let mut superseded = vec![false; strings.len()];
+    for i in 0..strings.len() {
+        if !strings[i].extends {
+            continue;
+        }
+        if let Some(base) = (0..i).rev().find(|&j| {
+            !superseded[j]
+                && strings[j].variable_name == strings[i].variable_name
+                && strings[i].sql_content.starts_with(&strings[j].sql_content)
+        }) {
+            superseded[base] = true;
+        }
+    }
+    let mut flags = superseded.into_iter();
+    strings.retain(|_| !flags.next().unwrap_or(false));
+}
+
+/// Items of list and tuple literals, nested ones included, anything else as it is.
+fn flatten_sequences(expr: &ast::Expr) -> Vec<&ast::Expr> {
+    match expr {
+        ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => {
+            elts.iter().flat_map(flatten_sequences).collect()
+        }
+        _ => vec![expr],
+    }
 }
@@ -0,0 +1,362 @@
+use rustpython_parser::ast;
+
+// Walks the AST by reference. The generated `rustpython_ast::Visitor` takes every node by value,
+// so passes over statements still needed afterwards had to clone them first. Every expression
+// and statement is visited, keyword arguments, parameter defaults, comprehensions and match
+// cases included.
+
+pub(crate) trait Visit<'a> {
+    /// Called for each statement, what's within it is visited when this returns `true`.
+    fn enter_stmt(&mut self, _stmt: &'a ast::Stmt) -> bool {
+        true
+    }
+
+    /// Called for each expression, what's within it is visited when this returns `true`.
+    fn enter_expr(&mut self, _expr: &'a ast::Expr) -> bool {
+        true
+    }
+}
+
+pub(crate) fn walk_body<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, body: &'a [ast::Stmt]) {
+    for stmt in body {
+        walk_stmt(visitor, stmt);
+    }
+}
+
+pub(crate) fn walk_exprs<'a, V: Visit<'a> + ?Sized>(
+    visitor: &mut V,
+    exprs: impl IntoIterator<Item = &'a ast::Expr>,
+) {
+    for expr in exprs {
+        walk_expr(visitor, expr);
+    }
+}
+
+pub(crate) fn walk_stmt<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, stmt: &'a ast::Stmt) {
+    if !visitor.enter_stmt(stmt) {
+        return;
+    }
+    match stmt {
+        ast::Stmt::FunctionDef(ast::StmtFunctionDef {
+            args,
+            body,
+            decorator_list,
+            returns,
+            type_params,
+            ..
+        })
+        | ast::Stmt::AsyncFunctionDef(ast::StmtAsyncFunctionDef {
+            args,
+            body,
+            decorator_list,
+            returns,
+            type_params,
+            ..
+        }) => {
+            walk_exprs(visitor, decorator_list);
+            walk_type_params(visitor, type_params);
+            walk_arguments(visitor, args);
+            walk_exprs(visitor, returns.as_deref());
+            walk_body(visitor, body);
+        }
+        ast::Stmt::ClassDef(ast::StmtClassDef {
+            bases,
+            keywords,
+            body,
+            decorator_list,
+            type_params,
+            ..
+        }) => {
+            walk_exprs(visitor, decorator_list);
+            walk_type_params(visitor, type_params);
+            walk_exprs(visitor, bases);
+            walk_exprs(visitor, keywords.iter().map(|k| &k.value));
+            walk_body(visitor, body);
+        }
+        ast::Stmt::Return(ast::StmtReturn { value, .. }) => {
+            walk_exprs(visitor, value.as_deref());
+        }
+        ast::Stmt::Delete(ast::StmtDelete { targets, .. }) => walk_exprs(visitor, targets),
+        ast::Stmt::Assign(ast::StmtAssign { targets, value, .. }) => {
+            walk_exprs(visitor, targets);
+            walk_expr(visitor, value);
+        }
+        ast::Stmt::TypeAlias(ast::StmtTypeAlias {
+            name,
+            type_params,
+            value,
+            ..
+        }) => {
+            walk_expr(visitor, name);
+            walk_type_params(visitor, type_params);
+            walk_expr(visitor, value);
+        }
+        ast::Stmt::AugAssign(ast::StmtAugAssign { target, value, .. }) => {
+            walk_expr(visitor, target);
+            walk_expr(visitor, value);
+        }
+        ast::Stmt::AnnAssign(ast::StmtAnnAssign {
+            target,
+            annotation,
+            value,
+            ..
+        }) => {
+            walk_expr(visitor, target);
+            walk_expr(visitor, annotation);
+            walk_exprs(visitor, value.as_deref());
+        }
+        ast::Stmt::For(ast::StmtFor {
+            target,
+            iter,
+            body,
+            orelse,
+            ..
+        })
+        | ast::Stmt::AsyncFor(ast::StmtAsyncFor {
+            target,
+            iter,
+            body,
+            orelse,
+            ..
+        }) => {
+            walk_expr(visitor, target);
+            walk_expr(visitor, iter);
+            walk_body(visitor, body);
+            walk_body(visitor, orelse);
+        }
+        ast::Stmt::While(ast::StmtWhile {
+            test, body, orelse, ..
+        })
+        | ast::Stmt::If(ast::StmtIf {
+            test, body, orelse, ..
+        }) => {
+            walk_expr(visitor, test);
+            walk_body(visitor, body);
+            walk_body(visitor, orelse);
+        }
+        ast::Stmt::With(ast::StmtWith { items, body, .. })
+        | ast::Stmt::AsyncWith(ast::StmtAsyncWith { items, body, .. }) => {
+            for item in items {
+                walk_expr(visitor, &item.context_expr);
+                walk_exprs(visitor, item.optional_vars.as_deref());
+            }
+            walk_body(visitor, body);
+        }
+        ast::Stmt::Match(ast::StmtMatch { subject, cases, .. }) => {
+            walk_expr(visitor, subject);
+            for case in cases {
+                walk_pattern(visitor, &case.pattern);
+                walk_exprs(visitor, case.guard.as_deref());
+                walk_body(visitor, &case.body);
+            }
+        }
+        ast::Stmt::Raise(ast::StmtRaise { exc, cause, .. }) => {
+            walk_exprs(visitor, exc.as_deref().into_iter().chain(cause.as_deref()));
+        }
+        ast::Stmt::Try(ast::StmtTry {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        })
+        | ast::Stmt::TryStar(ast::StmtTryStar {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        }) => {
+            walk_body(visitor, body);
+            for ast::ExceptHandler::ExceptHandler(handler) in handlers {
+                walk_exprs(visitor, handler.type_.as_deref());
+                walk_body(visitor, &handler.body);
+            }
+            walk_body(visitor, orelse);
+            walk_body(visitor, finalbody);
+        }
+        ast::Stmt::Assert(ast::StmtAssert { test, msg, .. }) => {
+            walk_expr(visitor, test);
+            walk_exprs(visitor, msg.as_deref());
+        }
+        ast::Stmt::Expr(ast::StmtExpr { value, .. }) => walk_expr(visitor, value),
+        ast::Stmt::Import(_)
+        | ast::Stmt::ImportFrom(_)
+        | ast::Stmt::Global(_)
+        | ast::Stmt::Nonlocal(_)
+        | ast::Stmt::Pass(_)
+        | ast::Stmt::Break(_)
+        | ast::Stmt::Continue(_) => {}
+    }
+}
+
+pub(crate) fn walk_expr<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, expr: &'a ast::Expr) {
+    if !visitor.enter_expr(expr) {
+        return;
+    }
+    match expr {
+        ast::Expr::BoolOp(ast::ExprBoolOp { values, .. }) => walk_exprs(visitor, values),
+        ast::Expr::NamedExpr(ast::ExprNamedExpr { target, value, .. }) => {
+            walk_expr(visitor, target);
+            walk_expr(visitor, value);
+        }
+        ast::Expr::BinOp(ast::ExprBinOp { left, right, .. }) => {
+            walk_expr(visitor, left);
+            walk_expr(visitor, right);
+        }
+        ast::Expr::UnaryOp(ast::ExprUnaryOp { operand, .. }) => walk_expr(visitor, operand),
+        ast::Expr::Lambda(ast::ExprLambda { args, body, .. }) => {
+            walk_arguments(visitor, args);
+            walk_expr(visitor, body);
+        }
+        ast::Expr::IfExp(ast::ExprIfExp {
+            test, body, orelse, ..
+        }) => {
+            walk_expr(visitor, test);
+            walk_expr(visitor, body);
+            walk_expr(visitor, orelse);
+        }
+        ast::Expr::Dict(ast::ExprDict { keys, values, .. }) => {
+            walk_exprs(visitor, keys.iter().flatten());
+            walk_exprs(visitor, values);
+        }
+        ast::Expr::Set(ast::ExprSet { elts, .. })
+        | ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => walk_exprs(visitor, elts),
+        ast::Expr::ListComp(ast::ExprListComp {
+            elt, generators, ..
+        })
+        | ast::Expr::SetComp(ast::ExprSetComp {
+            elt, generators, ..
+        })
+        | ast::Expr::GeneratorExp(ast::ExprGeneratorExp {
+            elt, generators, ..
+        }) => {
+            walk_generators(visitor, generators);
+            walk_expr(visitor, elt);
+        }
+        ast::Expr::DictComp(ast::ExprDictComp {
+            key,
+            value,
+            generators,
+            ..
+        }) => {
+            walk_generators(visitor, generators);
+            walk_expr(visitor, key);
+            walk_expr(visitor, value);
+        }
+        ast::Expr::Await(ast::ExprAwait { value, .. })
+        | ast::Expr::YieldFrom(ast::ExprYieldFrom { value, .. })
+        | ast::Expr::Attribute(ast::ExprAttribute { value, .. })
+        | ast::Expr::Starred(ast::ExprStarred { value, .. }) => walk_expr(visitor, value),
+        ast::Expr::Yield(ast::ExprYield { value, .. }) => walk_exprs(visitor, value.as_deref()),
+        ast::Expr::Compare(ast::ExprCompare {
+            left, comparators, ..
+        }) => {
+            walk_expr(visitor, left);
+            walk_exprs(visitor, comparators);
+        }
+        ast::Expr::Call(ast::ExprCall {
+            func,
+            args,
+            keywords,
+            ..
+        }) => {
+            walk_expr(visitor, func);
+            walk_exprs(visitor, args);
+            walk_exprs(visitor, keywords.iter().map(|k| &k.value));
+        }
+        ast::Expr::FormattedValue(ast::ExprFormattedValue {
+            value, format_spec, ..
+        }) => {
+            walk_expr(visitor, value);
+            walk_exprs(visitor, format_spec.as_deref());
+        }
+        ast::Expr::JoinedStr(ast::ExprJoinedStr { values, .. }) => walk_exprs(visitor, values),
+        ast::Expr::Subscript(ast::ExprSubscript { value, slice, .. }) => {
+            walk_expr(visitor, value);
+            walk_expr(visitor, slice);
+        }
+        ast::Expr::Slice(ast::ExprSlice {
+            lower, upper, step, ..
+        }) => walk_exprs(
+            visitor,
+            [lower, upper, step]
+                .into_iter()
+                .filter_map(|e| e.as_deref()),
+        ),
+        ast::Expr::Constant(_) | ast::Expr::Name(_) => {}
+    }
+}
+
+fn walk_generators<'a, V: Visit<'a> + ?Sized>(
+    visitor: &mut V,
+    generators: &'a [ast::Comprehension],
+) {
+    for generator in generators {
+        walk_expr(visitor, &generator.target);
+        walk_expr(visitor, &generator.iter);
+        walk_exprs(visitor, &generator.ifs);
+    }
+}
+
+fn walk_arguments<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, args: &'a ast::Arguments) {
+    let with_defaults = args
+        .posonlyargs
+        .iter()
+        .chain(&args.args)
+        .chain(&args.kwonlyargs);
+    for arg in with_defaults.clone() {
+        walk_exprs(visitor, arg.def.annotation.as_deref());
+    }
+    for arg in args.vararg.iter().chain(&args.kwarg) {
+        walk_exprs(visitor, arg.annotation.as_deref());
+    }
+    walk_exprs(
+        visitor,
+        with_defaults.filter_map(|arg| arg.default.as_deref()),
+    );
+}
+
+fn walk_type_params<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, params: &'a [ast::TypeParam]) {
+    for param in params {
+        if let ast::TypeParam::TypeVar(ast::TypeParamTypeVar { bound, .. }) = param {
+            walk_exprs(visitor, bound.as_deref());
+        }
+    }
+}
+
+fn walk_pattern<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, pattern: &'a ast::Pattern) {
+    match pattern {
+        ast::Pattern::MatchValue(ast::PatternMatchValue { value, .. }) => walk_expr(visitor, value),
+        ast::Pattern::MatchSequence(ast::PatternMatchSequence { patterns, .. })
+        | ast::Pattern::MatchOr(ast::PatternMatchOr { patterns, .. }) => {
+            for p in patterns {
+                walk_pattern(visitor, p);
+            }
+        }
+        ast::Pattern::MatchMapping(ast::PatternMatchMapping { keys, patterns, .. }) => {
+            walk_exprs(visitor, keys);
+            for p in patterns {
+                walk_pattern(visitor, p);
+            }
+        }
+        ast::Pattern::MatchClass(ast::PatternMatchClass {
+            cls,
+            patterns,
+            kwd_patterns,
+            ..
+        }) => {
+            walk_expr(visitor, cls);
+            for p in patterns.iter().chain(kwd_patterns) {
+                walk_pattern(visitor, p);
+            }
+        }
+        ast::Pattern::MatchAs(ast::PatternMatchAs { pattern, .. }) => {
+            if let Some(p) = pattern {
+                walk_pattern(visitor, p);
+            }
+        }
+        ast::Pattern::MatchSingleton(_) | ast::Pattern::MatchStar(_) => {}
+    }
+}
@@ -7,6 +7,8 @@ use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
 static GLOBAL_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Error as u8);
 static LOGGER_INITIALIZED: OnceLock<()> = OnceLock::new();
 static HAS_ERROR_OCCURRED: AtomicBool = AtomicBool::new(false);
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+static QUIET: AtomicBool = AtomicBool::new(false);
 static SQL_ERROR_TOTAL_CNT: AtomicU64 = AtomicU64::new(0);
 static SQL_VAR_TOTAL_CNT: AtomicU64 = AtomicU64::new(0);
 
@@ -48,9 +50,10 @@ impl LogLevel {
     }
 
     fn should_use_color() -> bool {
-        !(std::env::var("NO_COLOR").is_ok()
-            || std::env::var("CI").is_ok()
-            || !atty::is(atty::Stream::Stdout))
+        COLOR_ENABLED.load(Ordering::Relaxed)
+            && !(std::env::var("NO_COLOR").is_ok()
+                || std::env::var("CI").is_ok()
+                || !atty::is(atty::Stream::Stdout))
     }
 }
 
@@ -63,6 +66,15 @@ impl Logger {
         });
     }
 
+    pub fn set_colored(enabled: bool) {
+        COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Suppresses `Always` level messages such as banners and summaries.
+    pub fn set_quiet(enabled: bool) {
+        QUIET.store(enabled, Ordering::Relaxed);
+    }
+
     pub fn current_level() -> LogLevel {
         let level_u8 = GLOBAL_LOG_LEVEL.load(Ordering::Relaxed);
         match level_u8 {
@@ -76,6 +88,9 @@ impl Logger {
     }
 
     pub fn should_log(level: LogLevel) -> bool {
+        if level == LogLevel::Always && QUIET.load(Ordering::Relaxed) {
+            return false;
+        }
         let current_level = GLOBAL_LOG_LEVEL.load(Ordering::Relaxed);
         (level as u8) <= current_level
     }
@@ -150,6 +165,11 @@ impl Logger {
         HAS_ERROR_OCCURRED.load(Ordering::Relaxed)
     }
 
+    /// Fails the run for errors that are reported without going through `error!`.
+    pub fn mark_error_occurred() {
+        HAS_ERROR_OCCURRED.store(true, Ordering::Relaxed);
+    }
+
     #[must_use]
     pub fn exit_code() -> i32 {
         i32::from(Self::has_error_occurred())
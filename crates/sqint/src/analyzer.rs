@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
 
 use sqlparser::dialect::{
     AnsiDialect, BigQueryDialect, ClickHouseDialect, DuckDbDialect, GenericDialect, HiveDialect,
@@ -6,10 +7,21 @@ use sqlparser::dialect::{
     SnowflakeDialect,
 };
 
+use regex::{Captures, Regex};
 use sqlparser::parser::{Parser, ParserError};
 
+use finder::preanalysis::PreanalyzedFile;
 use finder::{SqlExtract, SqlString};
-use logging::{sql_error, sql_info};
+use logging::{Logger, debug, info, warn};
+
+use crate::cache::StringCache;
+use crate::config::ParamStyle;
+use crate::rules::{self, Diagnostic, Finding, Rule, RuleContext, Severity};
+
+#[cfg(test)]
+thread_local! {
+    pub static PARSES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
 
 #[derive(Debug, Clone)]
 pub enum SqlDialect {
@@ -30,28 +42,23 @@ pub enum SqlDialect {
 pub struct SqlAnalyzer {
     dialect: Box<dyn sqlparser::dialect::Dialect>,
     mappings: HashMap<String, String>,
+    paramstyle: ParamStyle,
+    driver_markers: Regex,
+    param_markers: Vec<String>,
+    fragments: bool,
+    split_statements: bool,
+    rules: Arc<Vec<Box<dyn Rule>>>,
 }
 
 impl SqlAnalyzer {
     pub fn new(
         dialect: &SqlDialect,
         mut dialect_mappings: HashMap<String, String>,
+        paramstyle: ParamStyle,
         placeholders: &[String],
+        rules: Arc<Vec<Box<dyn Rule>>>,
     ) -> Self {
-        let dialect: Box<dyn sqlparser::dialect::Dialect> = match dialect {
-            SqlDialect::Generic => Box::new(GenericDialect {}),
-            SqlDialect::PostgreSQL => Box::new(PostgreSqlDialect {}),
-            SqlDialect::SQLite => Box::new(SQLiteDialect {}),
-            SqlDialect::Ansi => Box::new(AnsiDialect {}),
-            SqlDialect::BigQuery => Box::new(BigQueryDialect {}),
-            SqlDialect::ClickHouse => Box::new(ClickHouseDialect {}),
-            SqlDialect::DuckDb => Box::new(DuckDbDialect {}),
-            SqlDialect::Hive => Box::new(HiveDialect {}),
-            SqlDialect::MsSql => Box::new(MsSqlDialect {}),
-            SqlDialect::MySql => Box::new(MySqlDialect {}),
-            SqlDialect::RedshiftSql => Box::new(RedshiftSqlDialect {}),
-            SqlDialect::Snowflake => Box::new(SnowflakeDialect {}),
-        };
+        let dialect = dialect.parser_dialect();
         for p in placeholders {
             dialect_mappings.insert(p.clone(), "PLACEHOLDER".to_string());
         }
@@ -59,51 +66,343 @@ impl SqlAnalyzer {
         Self {
             dialect,
             mappings: dialect_mappings,
+            paramstyle,
+            driver_markers: driver_markers(paramstyle),
+            param_markers: placeholders.to_vec(),
+            fragments: false,
+            split_statements: false,
+            rules,
         }
     }
 
-    pub fn analyze_sql_extract(&self, extract: &SqlExtract) {
-        extract
-            .strings
-            .iter()
-            .for_each(|sql_string| self.analyze_sql_string(sql_string, &extract.rel_path));
+    /// Parses fragments like `WHERE tenant_id = %s` as part of a dummy statement, for strings
+    /// extracted without a leading SQL keyword.
+    #[must_use]
+    pub const fn with_fragments(mut self, enabled: bool) -> Self {
+        self.fragments = enabled;
+        self
+    }
+
+    /// Checks each statement of a string holding several on its own, like migrations running
+    /// a whole script through `op.execute`.
+    #[must_use]
+    pub const fn with_statement_splitting(mut self, enabled: bool) -> Self {
+        self.split_statements = enabled;
+        self
     }
 
-    fn analyze_sql_string(&self, sql_string: &SqlString, filename: &str) {
-        let filled_sql = self.fill_placeholders(&sql_string.sql_content);
+    pub fn analyze_sql_extract(
+        &self,
+        extract: &SqlExtract,
+        cache: Option<&StringCache>,
+    ) -> Vec<Diagnostic> {
+        let path = extract.display_path();
+        for (line, code) in self.unknown_suppressed_codes(&extract.source) {
+            warn!("{path}:{line}: Unknown rule code `{code}` in `# sqint: ignore[...]`");
+        }
+        let pragma_errors = extract
+            .source
+            .dialect_pragmas()
+            .into_iter()
+            .filter(|(_, name)| SqlDialect::from_str(name).is_none())
+            .map(|(range, name)| Diagnostic {
+                code: rules::INVALID_PRAGMA_CODE.to_string(),
+                severity: Severity::Error,
+                message: format!(
+                    "Unknown dialect `{name}` in `# sqint: dialect=...`. Supported: {}",
+                    SqlDialect::supported_dialects().join(", ")
+                ),
+                file: path.clone(),
+                variable_name: "pragma".to_string(),
+                range,
+                approximate: false,
+                fix: None,
+            });
+        let strings: Vec<SqlString> = if self.split_statements {
+            extract
+                .strings
+                .iter()
+                .flat_map(|s| s.split_statements(&extract.source))
+                .collect()
+        } else {
+            extract.strings.clone()
+        };
+        let string_diagnostics = strings.iter().flat_map(|sql_string| {
+            let Some(cache) = cache else {
+                return self.analyze_sql_string(sql_string, &extract.source, &path);
+            };
+            if let Some(cached) =
+                cache.lookup(&extract.rel_path, &extract.source, &path, sql_string)
+            {
+                Logger::increment_sql_var_count();
+                return cached;
+            }
+            let diagnostics = self.analyze_sql_string(sql_string, &extract.source, &path);
+            cache.store(&extract.rel_path, sql_string, &diagnostics);
+            diagnostics
+        });
+        pragma_errors.chain(string_diagnostics).collect()
+    }
 
-        match Parser::parse_sql(&*self.dialect, &filled_sql) {
+    /// Codes of `# sqint: ignore[...]` pragmas no rule reports, most likely typos.
+    fn unknown_suppressed_codes<'a>(&self, source: &'a PreanalyzedFile) -> Vec<(usize, &'a str)> {
+        source
+            .suppressed_codes()
+            .into_iter()
+            .filter(|(_, code)| {
+                ![
+                    rules::INVALID_PRAGMA_CODE,
+                    rules::SYNTAX_ERROR_CODE,
+                    rules::UNEXTRACTABLE_CODE,
+                ]
+                .contains(code)
+                    && !self.rules.iter().any(|r| r.code() == *code)
+            })
+            .collect()
+    }
+
+    /// Diagnostics of a string, without the ones its `# sqint: ignore` pragmas suppress.
+    pub fn analyze_sql_string(
+        &self,
+        sql_string: &SqlString,
+        source: &PreanalyzedFile,
+        filename: &str,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = self.check_sql_string(sql_string, source, filename);
+        diagnostics.retain(|d| !sql_string.is_suppressed(&d.code));
+        diagnostics
+    }
+
+    fn check_sql_string(
+        &self,
+        sql_string: &SqlString,
+        source: &PreanalyzedFile,
+        filename: &str,
+    ) -> Vec<Diagnostic> {
+        let diagnostic = |code: &str, severity: Severity, finding: Finding| Diagnostic {
+            code: code.to_string(),
+            severity,
+            message: finding.message,
+            file: filename.to_string(),
+            variable_name: sql_string.variable_name.clone(),
+            range: finding
+                .offset
+                .and_then(|offset| sql_string.content_range(source, offset))
+                .unwrap_or_else(|| sql_string.range.clone()),
+            approximate: sql_string.approximate,
+            fix: finding
+                .fix
+                .and_then(|fix| fix.in_source(sql_string, source)),
+        };
+        if sql_string.unextractable {
+            return vec![diagnostic(
+                rules::UNEXTRACTABLE_CODE,
+                Severity::Warning,
+                format!(
+                    "SQL of `{}` could not be statically extracted",
+                    sql_string.variable_name
+                )
+                .into(),
+            )];
+        }
+
+        Logger::increment_sql_var_count();
+        let filled_sql = self.prepare_sql(&sql_string.sql_content);
+
+        #[cfg(test)]
+        PARSES.with(|p| p.set(p.get() + 1));
+        // Pragmas naming an unknown dialect are reported on their own, the string falls back
+        // to the configured one
+        let overridden = sql_string
+            .dialect
+            .as_deref()
+            .and_then(SqlDialect::from_str)
+            .map(|d| d.parser_dialect());
+        let dialect = overridden.as_deref().unwrap_or(&*self.dialect);
+        let parsed = Parser::parse_sql(dialect, &filled_sql);
+        let mut diagnostics = vec![];
+        match &parsed {
             Ok(_) => {
-                sql_info!(
-                    "./{}:{}:{}: Valid sql string: `{}`",
-                    filename,
-                    sql_string.range.start,
-                    sql_string.variable_name,
-                    filled_sql
+                info!(
+                    "{}:{}:{}: Valid sql string: `{}`",
+                    filename, sql_string.range.start, sql_string.variable_name, filled_sql
                 );
             }
-            Err(e) => {
-                sql_error!(
-                    "./{}:{}:{}: `{}` => {}",
+            Err(e) => match BENIGN_STATEMENT.find(&filled_sql) {
+                Some(kind) => debug!(
+                    "{}:{}:{}: Skipping {} statement the parser doesn't support: `{}`",
                     filename,
                     sql_string.range.start,
                     sql_string.variable_name,
-                    filled_sql,
-                    SqlError::from_parser_error(e).reason
-                );
-            }
+                    kind.as_str().trim().to_uppercase(),
+                    filled_sql
+                ),
+                None => diagnostics.push(diagnostic(
+                    rules::SYNTAX_ERROR_CODE,
+                    Severity::Error,
+                    format!(
+                        "`{}` => {}",
+                        filled_sql,
+                        SqlError::from_parser_error(e.clone()).reason
+                    )
+                    .into(),
+                )),
+            },
         }
+
+        let placeholders = if sql_string.params.is_some() {
+            self.placeholders(&sql_string.sql_content)
+        } else {
+            vec![]
+        };
+        let ctx = RuleContext {
+            sql: sql_string,
+            dialect,
+            statements: parsed.as_deref().ok(),
+            placeholders: &placeholders,
+        };
+        for rule in self.rules.iter() {
+            diagnostics.extend(
+                rule.check(&ctx)
+                    .into_iter()
+                    .map(|finding| diagnostic(rule.code(), rule.severity(), finding)),
+            );
+        }
+        diagnostics
+    }
+
+    /// What actually gets parsed, placeholders filled and fragments completed.
+    fn prepare_sql(&self, sql: &str) -> String {
+        let filled = self.fill_placeholders(sql);
+        if self.fragments {
+            complete_fragment(filled)
+        } else {
+            filled
+        }
+    }
+
+    /// The driver's placeholders and the configured `param_markers` in `sql`, in order, escapes
+    /// and casts left out. `%` styles are filled in by Python formatting, in literals too, the
+    /// others only outside of them.
+    fn placeholders<'s>(&self, sql: &'s str) -> Vec<&'s str> {
+        let literals = literal_ranges(sql);
+        let in_literal = |start: usize| literals.iter().any(|literal| literal.contains(&start));
+        let formatted = matches!(self.paramstyle, ParamStyle::Format | ParamStyle::Pyformat);
+
+        let mut found: Vec<(usize, &str)> = self
+            .driver_markers
+            .find_iter(sql)
+            .filter(|m| !matches!(m.as_str(), "%%" | "::"))
+            .filter(|m| formatted || !in_literal(m.start()))
+            .map(|m| (m.start(), m.as_str()))
+            .collect();
+        for marker in self.param_markers.iter().filter(|m| !m.is_empty()) {
+            let taken = found.clone();
+            found.extend(
+                sql.match_indices(marker.as_str())
+                    .filter(|(start, _)| !in_literal(*start))
+                    .filter(|(start, _)| {
+                        !taken
+                            .iter()
+                            .any(|(s, text)| (*s..s + text.len()).contains(start))
+                    }),
+            );
+        }
+        found.sort_unstable();
+        found.into_iter().map(|(_, marker)| marker).collect()
     }
 
     // Multipass fill doesnt' seem to induce much of a performance loss on a reasonable scale.
     // So singlepass is probably not needed for now.
     fn fill_placeholders(&self, sql: &str) -> String {
+        let filled = self
+            .driver_markers
+            .replace_all(sql, |caps: &Captures| match &caps[0] {
+                "%%" => "%",
+                "::" => "::",
+                marker if marker.ends_with('d') => "0",
+                _ => "PLACEHOLDER",
+            });
         self.mappings
             .iter()
-            .fold(sql.to_string(), |acc, (k, v)| acc.replace(k, v))
+            .fold(filled.into_owned(), |acc, (k, v)| acc.replace(k, v))
+    }
+}
+
+/// Maintenance statements of dumps and seed files, like `PRAGMA foreign_keys=OFF` or a bare
+/// `ANALYZE`, that the parser only partly supports. They aren't reported when failing to parse.
+static BENIGN_STATEMENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^\s*(?:pragma|analyze|vacuum)\b").unwrap());
+
+static FRAGMENT_START: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(?:(?:(?:left|right|full|inner|cross)\s+(?:outer\s+)?)?join|where|order\s+by|set)\b",
+    )
+    .unwrap()
+});
+
+/// `WHERE ...`, `JOIN ...` and `ORDER BY ...` continue a `SELECT * FROM _`, `SET ...` an
+/// `UPDATE _`. Anything else is left as is.
+fn complete_fragment(sql: String) -> String {
+    let Some(start) = FRAGMENT_START.find(&sql) else {
+        return sql;
+    };
+    if start.as_str().trim().eq_ignore_ascii_case("set") {
+        format!("UPDATE _ {sql}")
+    } else {
+        format!("SELECT * FROM _ {sql}")
     }
 }
 
+/// Byte ranges of the quoted literals, identifiers and comments of `sql`.
+fn literal_ranges(sql: &str) -> Vec<std::ops::Range<usize>> {
+    let bytes = sql.as_bytes();
+    let mut ranges = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        pos = match (bytes[pos], bytes.get(pos + 1)) {
+            (quote @ (b'\'' | b'"'), _) => {
+                let mut end = pos + 1;
+                while end < bytes.len() {
+                    end += 1;
+                    if bytes[end - 1] == quote && bytes.get(end) != Some(&quote) {
+                        break;
+                    }
+                    if bytes[end - 1] == quote {
+                        end += 1;
+                    }
+                }
+                end
+            }
+            (b'-', Some(b'-')) => sql[pos..].find('\n').map_or(bytes.len(), |end| pos + end),
+            (b'/', Some(b'*')) => sql[pos + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| pos + end + 4),
+            _ => {
+                pos += 1;
+                continue;
+            }
+        };
+        ranges.push(start..pos);
+    }
+    ranges
+}
+
+/// Matches the driver's placeholders, escapes and casts that merely look like one included so
+/// they are consumed before a marker could start inside them.
+fn driver_markers(paramstyle: ParamStyle) -> Regex {
+    let pattern = match paramstyle {
+        ParamStyle::Qmark => r"\?",
+        ParamStyle::Format => r"%%|%[sdbt]",
+        ParamStyle::Pyformat => r"%%|%(?:\([^)]*\))?[sdbt]",
+        ParamStyle::Numeric => r"::|:\d+",
+        ParamStyle::Named => r"::|:[A-Za-z_]\w*",
+        ParamStyle::Dollar => r"\$\d+",
+    };
+    Regex::new(pattern).unwrap()
+}
+
 #[derive(Debug, Default)]
 struct SqlError {
     pub reason: String,
@@ -172,6 +471,23 @@ impl SqlDialect {
         }
     }
 
+    fn parser_dialect(&self) -> Box<dyn sqlparser::dialect::Dialect> {
+        match self {
+            Self::Generic => Box::new(GenericDialect {}),
+            Self::PostgreSQL => Box::new(PostgreSqlDialect {}),
+            Self::SQLite => Box::new(SQLiteDialect {}),
+            Self::Ansi => Box::new(AnsiDialect {}),
+            Self::BigQuery => Box::new(BigQueryDialect {}),
+            Self::ClickHouse => Box::new(ClickHouseDialect {}),
+            Self::DuckDb => Box::new(DuckDbDialect {}),
+            Self::Hive => Box::new(HiveDialect {}),
+            Self::MsSql => Box::new(MsSqlDialect {}),
+            Self::MySql => Box::new(MySqlDialect {}),
+            Self::RedshiftSql => Box::new(RedshiftSqlDialect {}),
+            Self::Snowflake => Box::new(SnowflakeDialect {}),
+        }
+    }
+
     pub fn supported_dialects() -> Vec<&'static str> {
         vec![
             "postgres",
@@ -190,3 +506,315 @@ impl SqlDialect {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer(paramstyle: ParamStyle) -> SqlAnalyzer {
+        SqlAnalyzer::new(
+            &SqlDialect::PostgreSQL,
+            HashMap::new(),
+            paramstyle,
+            &[],
+            Arc::new(vec![]),
+        )
+    }
+
+    fn parses(paramstyle: ParamStyle, sql: &str) -> bool {
+        let filled = analyzer(paramstyle).fill_placeholders(sql);
+        Parser::parse_sql(&PostgreSqlDialect {}, &filled).is_ok()
+    }
+
+    #[test]
+    fn driver_placeholders_filled() {
+        let cases = [
+            (ParamStyle::Qmark, "SELECT * FROM users WHERE id = ?"),
+            (
+                ParamStyle::Format,
+                "SELECT * FROM users WHERE id = %s AND age > %d",
+            ),
+            (
+                ParamStyle::Pyformat,
+                "SELECT * FROM users WHERE id = %(id)s AND name LIKE 'a%%' AND age > %s",
+            ),
+            (
+                ParamStyle::Numeric,
+                "SELECT * FROM users WHERE id = :1::int",
+            ),
+            (
+                ParamStyle::Named,
+                "SELECT * FROM users WHERE id = :user_id::int",
+            ),
+            (ParamStyle::Dollar, "SELECT * FROM users WHERE id = $1"),
+        ];
+        for (paramstyle, sql) in cases {
+            assert!(parses(paramstyle, sql), "{paramstyle:?}: {sql}");
+        }
+    }
+
+    #[test]
+    fn typed_dummy_values() {
+        assert_eq!(
+            analyzer(ParamStyle::Pyformat).fill_placeholders("LIMIT %(n)d OFFSET %s, '100%%'"),
+            "LIMIT 0 OFFSET PLACEHOLDER, '100%'"
+        );
+    }
+
+    #[test]
+    fn fragments_completed() {
+        let fragments = analyzer(ParamStyle::Pyformat).with_fragments(true);
+        let valid = |sql: &str| {
+            Parser::parse_sql(&PostgreSqlDialect {}, &fragments.prepare_sql(sql)).is_ok()
+        };
+
+        assert!(valid("WHERE tenant_id = %s AND deleted_at IS NULL"));
+        assert!(valid(
+            "LEFT JOIN orders o ON o.user_id = users.id WHERE o.total > %s"
+        ));
+        assert!(valid("ORDER BY created_at DESC"));
+        assert!(valid("SET active = %s WHERE id = %s"));
+        assert!(!valid("WHERE tenant_id = = %s"));
+        assert_eq!(fragments.prepare_sql("SELECT 1"), "SELECT 1");
+        assert_eq!(
+            analyzer(ParamStyle::Pyformat).prepare_sql("WHERE id = %s"),
+            "WHERE id = PLACEHOLDER"
+        );
+    }
+
+    #[test]
+    fn unextractable_reported_without_parsing() {
+        let src = "query = build_query(user_input)\nexecute(make_sql())\n";
+        let diagnostics = crate::rules::test_utils::diagnostics_for(
+            crate::rules::test_utils::finder_config().with_unextractable_reporting(true),
+            vec![],
+            src,
+        );
+
+        let found: Vec<(&str, &str, usize)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.code.as_str(),
+                    d.variable_name.as_str(),
+                    d.range.start.line(),
+                )
+            })
+            .collect();
+        assert_eq!(found, vec![("SQ402", "query", 1), ("SQ402", "execute", 2)]);
+    }
+
+    #[test]
+    fn scoped_pragmas_filter_matching_codes() {
+        let src = r#"
+query = "SELEC 1"  # sqint: ignore[SQ302]
+other_query = "SELEC 1"  # sqint: ignore[SQ100]
+any_query = "SELEC 1"  # sqint: ignore
+in_query = "SELEC * FROM t WHERE id IN (" + ",".join(ids) + ")"  # sqint: ignore[SQ302]
+"#;
+        let diagnostics = crate::rules::test_utils::diagnostics_for(
+            crate::rules::test_utils::finder_config(),
+            crate::rules::builtin_rules(),
+            src,
+        );
+
+        let found: Vec<(&str, usize)> = diagnostics
+            .iter()
+            .map(|d| (d.code.as_str(), d.range.start.line()))
+            .collect();
+        assert_eq!(found, vec![("SQ100", 2), ("SQ100", 5)]);
+    }
+
+    #[test]
+    fn ignored_file_not_parsed() {
+        let before = PARSES.with(std::cell::Cell::get);
+        let diagnostics = crate::rules::test_utils::diagnostics_for(
+            crate::rules::test_utils::finder_config(),
+            crate::rules::builtin_rules(),
+            "# sqint: ignore-file\nquery = \"SELEC id FROM users\"\n",
+        );
+        assert!(diagnostics.is_empty());
+        assert_eq!(PARSES.with(std::cell::Cell::get), before);
+    }
+
+    #[test]
+    fn unknown_suppressed_codes() {
+        let analyzer = SqlAnalyzer::new(
+            &SqlDialect::Generic,
+            HashMap::new(),
+            ParamStyle::default(),
+            &[],
+            Arc::new(crate::rules::builtin_rules()),
+        );
+        let source = PreanalyzedFile::from_src(
+            "query = \"SELECT 1\"  # sqint: ignore[SQ100, SQ30]\nexecute(q)  # sqint: ignore[SQ402]\n",
+        );
+        assert_eq!(
+            analyzer.unknown_suppressed_codes(&source),
+            vec![(1, "SQ30")]
+        );
+    }
+
+    #[test]
+    fn other_paramstyles_left_alone() {
+        assert!(!parses(
+            ParamStyle::Qmark,
+            "SELECT * FROM users WHERE id = %(id)s"
+        ));
+    }
+
+    #[test]
+    fn dialect_pragmas() {
+        let src = r#"# sqint: dialect=mysql
+query = "SELECT `id` FROM users"
+other_query = "SELECT `id` FROM users"  # sqint: dialect=postgres
+any_query = "SELECT 1"  # sqint: dialect=oracle
+"#;
+        let finder = finder::SqlFinder::new(Arc::new(crate::rules::test_utils::finder_config()));
+        let extract = SqlExtract::new(
+            "test.py".to_string(),
+            finder.analyze_python_src(src, "test.py").unwrap(),
+            PreanalyzedFile::from_src(src).into(),
+        );
+        let diagnostics = analyzer(ParamStyle::Qmark).analyze_sql_extract(&extract, None);
+
+        let found: Vec<(&str, usize)> = diagnostics
+            .iter()
+            .map(|d| (d.code.as_str(), d.range.start.line()))
+            .collect();
+        assert_eq!(found, vec![("SQ001", 4), ("SQ100", 3)]);
+        assert!(
+            diagnostics[0]
+                .message
+                .starts_with("Unknown dialect `oracle`")
+        );
+    }
+
+    #[test]
+    fn dollar_quoted_function_body() {
+        let path = std::env::temp_dir().join(format!("sqint_plpgsql_{}.sql", std::process::id()));
+        std::fs::write(
+            &path,
+            "CREATE FUNCTION clamp(n integer) RETURNS integer AS $$\nBEGIN\n    IF n < 0 THEN\n        RETURN 0;\n    END IF;\n    RETURN n;\nEND;\n$$ LANGUAGE plpgsql;\n",
+        )
+        .unwrap();
+        let mut finder =
+            finder::SqlFinder::new(Arc::new(crate::rules::test_utils::finder_config()));
+        let extract = finder.analyze_file(path.to_str().unwrap(), true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(extract.strings.len(), 1);
+        assert!(
+            analyzer(ParamStyle::Qmark)
+                .analyze_sql_extract(&extract, None)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn templated_sql_parses() {
+        let path = std::env::temp_dir().join(format!("sqint_dbt_{}.sql", std::process::id()));
+        std::fs::write(
+            &path,
+            "{{ config(materialized='view') }}\n\nSELECT u.id, u.email\nFROM {{ ref('users') }} AS u\n{% if var('only_active', true) %}\nWHERE u.active\n{% endif %}\n",
+        )
+        .unwrap();
+        let mut finder = finder::SqlFinder::new(Arc::new(
+            crate::rules::test_utils::finder_config()
+                .with_templated_sql_file_patterns(&["*sqint_dbt_*.sql".to_string()]),
+        ));
+        let extract = finder.analyze_file(path.to_str().unwrap(), true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(extract.strings.len(), 1);
+        assert!(
+            analyzer(ParamStyle::Qmark)
+                .analyze_sql_extract(&extract, None)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn multi_statement_strings_split() {
+        let src = "op.execute(\"\"\"\nCREATE TABLE t (id int);\nINSERT INTO t VALUES (1);\n  UPDTE t SET id = 2;\nDELETE FROM t WHERE note = 'a;b'\n\"\"\")\n";
+        let finder = finder::SqlFinder::new(Arc::new(finder::FinderConfig::new(
+            &[],
+            &["op.execute".to_string()],
+            true,
+        )));
+        let extract = SqlExtract::new(
+            "migration.py".to_string(),
+            finder.analyze_python_src(src, "migration.py").unwrap(),
+            PreanalyzedFile::from_src(src).into(),
+        );
+
+        let whole = analyzer(ParamStyle::Qmark).analyze_sql_extract(&extract, None);
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].range.start.to_string(), "1:1:");
+
+        let split = analyzer(ParamStyle::Qmark)
+            .with_statement_splitting(true)
+            .analyze_sql_extract(&extract, None);
+        assert_eq!(split.len(), 1);
+        assert_eq!(split[0].code, "SQ100");
+        assert_eq!(split[0].range.start.to_string(), "4:3:");
+        assert!(split[0].message.starts_with("`UPDTE t SET id = 2`"));
+    }
+
+    #[test]
+    fn sqlite_dump_parses() {
+        let path = std::env::temp_dir().join(format!("sqint_dump_{}.sql", std::process::id()));
+        std::fs::write(
+            &path,
+            "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);\nINSERT INTO users VALUES(1,'alice');\nINSERT INTO users VALUES(2,'o''brien');\nCREATE TABLE sqlite_stat1(tbl,idx,stat);\nINSERT INTO sqlite_stat1 VALUES('users',NULL,'2');\nDELETE FROM sqlite_sequence;\nINSERT INTO sqlite_sequence VALUES('users',2);\nCREATE INDEX idx_users_name ON users (name);\nPRAGMA table_info(users);\nANALYZE;\nCOMMIT;\n",
+        )
+        .unwrap();
+        let mut finder =
+            finder::SqlFinder::new(Arc::new(crate::rules::test_utils::finder_config()));
+        let extract = finder.analyze_file(path.to_str().unwrap(), true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let sqlite = SqlAnalyzer::new(
+            &SqlDialect::SQLite,
+            HashMap::new(),
+            ParamStyle::Qmark,
+            &[],
+            Arc::new(rules::builtin_rules()),
+        );
+        assert_eq!(extract.strings.len(), 13);
+        let diagnostics = sqlite.analyze_sql_extract(&extract, None);
+        // Only the dump emptying the sequence table, which isn't a parse failure
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "SQ201");
+        assert_eq!(diagnostics[0].range.start.line(), 8);
+    }
+
+    #[test]
+    fn latin1_sql_file_passes() {
+        let path = std::env::temp_dir().join(format!("sqint_vendor_{}.sql", std::process::id()));
+        std::fs::write(
+            &path,
+            b"-- encoding: latin-1\nINSERT INTO readings (label, unit) VALUES ('outside', '\xb0C');\nSELECT label FROM readings WHERE unit = '\xb0F';\n",
+        )
+        .unwrap();
+        let mut finder =
+            finder::SqlFinder::new(Arc::new(crate::rules::test_utils::finder_config()));
+        let extract = finder.analyze_file(path.to_str().unwrap(), true).unwrap();
+        assert_eq!(extract.strings.len(), 2);
+        assert!(
+            analyzer(ParamStyle::Qmark)
+                .analyze_sql_extract(&extract, None)
+                .is_empty()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let error = finder
+            .analyze_file(path.to_str().unwrap(), true)
+            .unwrap_err();
+        let diagnostic = Diagnostic::for_file_error("vendor.sql".to_string(), &error);
+        assert_eq!(diagnostic.code, rules::FILE_ERROR_CODE);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.range.start.to_string(), "1:1:");
+        assert!(diagnostic.message.starts_with("Failed to read file: "));
+    }
+}
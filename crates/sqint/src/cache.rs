@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use finder::SqlString;
+use finder::preanalysis::PreanalyzedFile;
+use logging::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{Diagnostic, Fix, Severity};
+
+/// Below the project root
+pub const STRING_CACHE_PATH: &str = ".sqint_cache/strings.json";
+
+/// Findings of a string without anything positional, those come from the current extraction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFinding {
+    code: String,
+    severity: Severity,
+    message: String,
+    /// Byte offset into the string content of findings about a part of it
+    offset: Option<usize>,
+    /// Relative to the string content as well
+    fix: Option<Fix>,
+}
+
+type FileEntries = HashMap<String, Vec<CachedFinding>>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    ruleset: u64,
+    // Keys are string hashes, JSON object keys have to be strings.
+    files: HashMap<String, FileEntries>,
+}
+
+/// Validation results of the previous run keyed by file and string content, so strings that
+/// didn't change aren't parsed and checked again when their file did.
+pub struct StringCache {
+    path: Option<PathBuf>,
+    ruleset: u64,
+    previous: HashMap<String, FileEntries>,
+    current: Mutex<HashMap<String, FileEntries>>,
+}
+
+impl StringCache {
+    /// Loads the cache at `path`, a missing, unreadable or outdated cache starts empty.
+    pub fn load(path: Option<PathBuf>, ruleset: u64) -> Self {
+        let previous = path
+            .as_deref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|cache| cache.ruleset == ruleset)
+            .map(|cache| cache.files)
+            .unwrap_or_default();
+        debug!("Loaded string cache with {} files", previous.len());
+
+        Self {
+            path,
+            ruleset,
+            previous,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Diagnostics for `sql` from the previous run, re-anchored at its current position.
+    pub fn lookup(
+        &self,
+        file: &str,
+        source: &PreanalyzedFile,
+        display_path: &str,
+        sql: &SqlString,
+    ) -> Option<Vec<Diagnostic>> {
+        let findings = self.previous.get(file)?.get(&string_key(sql))?;
+        self.remember(file, sql, findings.clone());
+
+        Some(
+            findings
+                .iter()
+                .map(|f| Diagnostic {
+                    code: f.code.clone(),
+                    severity: f.severity,
+                    message: f.message.clone(),
+                    file: display_path.to_string(),
+                    variable_name: sql.variable_name.clone(),
+                    range: f
+                        .offset
+                        .and_then(|offset| sql.content_range(source, offset))
+                        .unwrap_or_else(|| sql.range.clone()),
+                    approximate: sql.approximate,
+                    fix: f.fix.clone().and_then(|fix| fix.in_source(sql, source)),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn store(&self, file: &str, sql: &SqlString, diagnostics: &[Diagnostic]) {
+        let findings = diagnostics
+            .iter()
+            .map(|d| CachedFinding {
+                code: d.code.clone(),
+                severity: d.severity,
+                message: d.message.clone(),
+                offset: sql
+                    .content_start
+                    .filter(|_| d.range != sql.range)
+                    .and_then(|start| d.range.start.byte_offset().checked_sub(start)),
+                fix: d.fix.as_ref().and_then(|fix| fix.in_content(sql)),
+            })
+            .collect();
+        self.remember(file, sql, findings);
+    }
+
+    fn remember(&self, file: &str, sql: &SqlString, findings: Vec<CachedFinding>) {
+        if let Ok(mut current) = self.current.lock() {
+            current
+                .entry(file.to_string())
+                .or_default()
+                .insert(string_key(sql), findings);
+        }
+    }
+
+    /// Writes this run's results, files that weren't processed keep their previous entries.
+    pub fn save(self) {
+        let Some(path) = self.path else {
+            return;
+        };
+        let mut files = self.previous;
+        files.extend(self.current.into_inner().unwrap_or_default());
+
+        let content = match serde_json::to_string(&CacheFile {
+            ruleset: self.ruleset,
+            files,
+        }) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to serialize string cache: {e}");
+                return;
+            }
+        };
+        if let Err(e) = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, content))
+        {
+            warn!("Failed to write string cache '{}': {e}", path.display());
+        }
+    }
+}
+
+/// FNV-1a. Unlike `DefaultHasher` its algorithm is fixed, so keys of a saved cache stay valid
+/// across Rust releases. Integers are hashed little endian and sizes as 64 bits on every platform.
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+macro_rules! write_le {
+    ($($name:ident: $ty:ty),*) => {
+        $(fn $name(&mut self, i: $ty) {
+            self.write(&i.to_le_bytes());
+        })*
+    };
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    write_le!(write_u16: u16, write_u32: u32, write_u64: u64, write_u128: u128);
+    write_le!(write_i16: i16, write_i32: i32, write_i64: i64, write_i128: i128);
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Everything the rules get to see of a string, positions aside.
+fn string_key(sql: &SqlString) -> String {
+    let mut hasher = StableHasher::default();
+    sql.variable_name.hash(&mut hasher);
+    sql.sql_content.hash(&mut hasher);
+    sql.approximate.hash(&mut hasher);
+    sql.unreferenced.hash(&mut hasher);
+    sql.unextractable.hash(&mut hasher);
+    sql.suppressed.hash(&mut hasher);
+    sql.dialect.hash(&mut hasher);
+    sql.params.hash(&mut hasher);
+    sql.interpolation.hash(&mut hasher);
+    for span in &sql.dynamic_spans {
+        (span.start, span.end, span.joined).hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Changes whenever the validation of an unchanged string could produce a different result.
+pub fn ruleset_hash(config: &crate::Config) -> u64 {
+    let mut hasher = StableHasher::default();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    config.dialect.hash(&mut hasher);
+    config.paramstyle.hash(&mut hasher);
+    config.param_markers.hash(&mut hasher);
+    config.require_sql_keyword.hash(&mut hasher);
+    config.split_multi_statement_strings.hash(&mut hasher);
+    config.overrides.hash(&mut hasher);
+    config.missing_where_allowed_tables.hash(&mut hasher);
+    config.missing_where_severity.hash(&mut hasher);
+    config.unordered_limit_severity.hash(&mut hasher);
+    config.duplicate_column_severity.hash(&mut hasher);
+    config.keyword_case.hash(&mut hasher);
+
+    let mut mappings: Vec<_> = config.dialect_mappings.iter().collect();
+    mappings.sort();
+    mappings.hash(&mut hasher);
+
+    for path in &config.custom_rules {
+        path.hash(&mut hasher);
+        fs::read_to_string(Path::new(path))
+            .unwrap_or_default()
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use finder::{SqlExtract, SqlFinder, preanalysis::PreanalyzedFile};
+
+    use super::*;
+    use crate::analyzer::{PARSES, SqlAnalyzer, SqlDialect};
+    use crate::config::ParamStyle;
+    use crate::rules::builtin_rules;
+    use crate::rules::test_utils::finder_config;
+
+    fn run(src: &str, cache: &StringCache) -> (Vec<Diagnostic>, usize) {
+        let finder = SqlFinder::new(Arc::new(finder_config()));
+        let extract = SqlExtract::new(
+            "fixture.py".to_string(),
+            finder.analyze_python_src(src, "fixture.py").unwrap(),
+            PreanalyzedFile::from_src(src).into(),
+        );
+        let analyzer = SqlAnalyzer::new(
+            &SqlDialect::Generic,
+            HashMap::new(),
+            ParamStyle::default(),
+            &["?".to_string()],
+            Arc::new(builtin_rules()),
+        );
+
+        let before = PARSES.with(std::cell::Cell::get);
+        let diagnostics = analyzer.analyze_sql_extract(&extract, Some(cache));
+        (diagnostics, PARSES.with(std::cell::Cell::get) - before)
+    }
+
+    #[test]
+    fn unchanged_strings_are_not_reparsed() {
+        let path =
+            std::env::temp_dir().join(format!("sqint_string_cache_{}.json", std::process::id()));
+        let v1 =
+            "\"\"\"Reports.\"\"\"\nquery = \"SELEC id FROM users\"\nother_query = \"SELECT 1\"\n";
+        let v2 = "\"\"\"Reports.\n\nNow with a longer docstring.\"\"\"\nquery = \"SELEC id FROM users\"\nother_query = \"SELECT 2\"\n";
+
+        let cache = StringCache::load(Some(path.clone()), 1);
+        let (first, parses) = run(v1, &cache);
+        cache.save();
+        assert_eq!(parses, 2);
+        assert_eq!(first[0].range.start.line(), 2);
+
+        let cache = StringCache::load(Some(path.clone()), 1);
+        let (second, parses) = run(v2, &cache);
+        fs::remove_file(&path).unwrap();
+
+        // Only `other_query` changed
+        assert_eq!(parses, 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].code, "SQ100");
+        assert_eq!(second[0].range.start.line(), 4);
+    }
+
+    #[test]
+    fn ruleset_change_invalidates() {
+        let path =
+            std::env::temp_dir().join(format!("sqint_string_cache_rs_{}.json", std::process::id()));
+        let src = "query = \"SELECT 1\"\n";
+
+        let cache = StringCache::load(Some(path.clone()), 1);
+        run(src, &cache);
+        cache.save();
+
+        let (_, parses) = run(src, &StringCache::load(Some(path.clone()), 2));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(parses, 1);
+    }
+
+    #[test]
+    fn keys_use_a_fixed_hash() {
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+        let mut hasher = StableHasher::default();
+        hasher.write_usize(1);
+        let mut expected = StableHasher::default();
+        expected.write(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(hasher.finish(), expected.finish());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::config::{ColumnEncoding, Config, OutputFormat};
 
 use super::config::DEFAULT_CONFIG_NAME;
 use clap::{Args, Parser, Subcommand};
@@ -25,6 +25,26 @@ pub struct Cli {
     #[arg(short, long, global = true, value_enum)]
     pub loglevel: Option<LogLevel>,
 
+    /// Output format, "compact" prints one line per failing file and nothing on success
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Maximum message width in compact output
+    #[arg(long, global = true)]
+    pub compact_width: Option<usize>,
+
+    /// Unit reported columns are counted in, "auto" picks the one of the output format
+    #[arg(long, global = true, value_enum)]
+    pub column_encoding: Option<ColumnEncoding>,
+
+    /// Print the source line of each finding with a caret under its position
+    #[arg(long, global = true)]
+    pub show_source: bool,
+
+    /// Print the calls skipped on purpose during extraction and why, like excluded logging calls
+    #[arg(long, global = true)]
+    pub explain_extraction: bool,
+
     #[arg(long, global = true)]
     pub incremental: bool,
 
@@ -34,6 +54,14 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub include_staged: bool,
 
+    /// Reuse the previous run's results for SQL strings that didn't change
+    #[arg(long, global = true)]
+    pub only_changed_strings: bool,
+
+    /// Don't read or write the string cache, even if enabled in the config
+    #[arg(long, global = true)]
+    pub no_string_cache: bool,
+
     #[arg(long, global = true)]
     pub include_hidden_files: bool,
 
@@ -43,6 +71,7 @@ pub struct Cli {
 
 impl Cli {
     pub fn merge_with_config(&self, cfg: Config) -> Config {
+        let args = self.check_args();
         Config {
             variable_contexts: cfg.variable_contexts,
             baseline_branch: self.baseline_branch.clone().unwrap_or(cfg.baseline_branch),
@@ -51,23 +80,75 @@ impl Cli {
             exclude_patterns: cfg.exclude_patterns,
             file_patterns: cfg.file_patterns,
             raw_sql_file_patterns: cfg.raw_sql_file_patterns,
+            templated_sql_file_patterns: cfg.templated_sql_file_patterns,
+            sql_in_data_files: cfg.sql_in_data_files,
+            statement_name_pattern: cfg.statement_name_pattern,
             function_contexts: cfg.function_contexts,
+            presets: cfg.presets,
+            function_suffix_matching: cfg.function_suffix_matching,
+            case_sensitive: cfg.case_sensitive,
+            excluded_function_contexts: cfg.excluded_function_contexts,
+            return_contexts: cfg.return_contexts,
+            class_contexts: cfg.class_contexts,
+            keyword_contexts: cfg.keyword_contexts,
+            passthrough_wrappers: cfg.passthrough_wrappers,
+            sql_keywords: cfg.sql_keywords,
+            extra_sql_keywords: cfg.extra_sql_keywords,
+            fallback_extraction: cfg.fallback_extraction,
+            detect_dead_sql: cfg.detect_dead_sql,
+            dead_sql_private_only: cfg.dead_sql_private_only,
+            check_assert_comparisons: cfg.check_assert_comparisons,
+            detect_bare_string_literals: cfg.detect_bare_string_literals,
+            detect_unnamed_sql: cfg.detect_unnamed_sql,
+            dataflow: cfg.dataflow,
+            require_sql_keyword: cfg.require_sql_keyword,
+            report_unextractable: cfg.report_unextractable,
+            respect_noqa: cfg.respect_noqa,
+            split_multi_statement_strings: cfg.split_multi_statement_strings,
+            detect_interpolated_sql: cfg.detect_interpolated_sql,
+            min_sql_length: args.min_sql_length.unwrap_or(cfg.min_sql_length),
+            missing_where_allowed_tables: cfg.missing_where_allowed_tables,
+            missing_where_severity: cfg.missing_where_severity,
+            unordered_limit_severity: cfg.unordered_limit_severity,
+            duplicate_column_severity: cfg.duplicate_column_severity,
+            keyword_case: cfg.keyword_case,
+            custom_rules: cfg.custom_rules,
+            select: if args.select.is_empty() {
+                cfg.select
+            } else {
+                args.select.clone()
+            },
+            ignore: cfg.ignore.into_iter().chain(args.ignore.clone()).collect(),
+            overrides: cfg.overrides,
             include_hidden_files: self.include_hidden_files || cfg.include_hidden_files,
+            check_stub_files: cfg.check_stub_files,
             include_staged: self.include_staged || cfg.include_staged,
             incremental_mode: self.incremental || cfg.incremental_mode,
+            string_cache: !self.no_string_cache && (self.only_changed_strings || cfg.string_cache),
             loglevel: self.loglevel.unwrap_or(cfg.loglevel),
-            max_threads: self.check_args.max_threads.unwrap_or(cfg.max_threads),
-            parallel_processing: self
-                .check_args
-                .parallel_processing
-                .unwrap_or(cfg.parallel_processing),
+            duplicate_findings: cfg.duplicate_findings,
+            output_format: self.format.unwrap_or(cfg.output_format),
+            compact_width: self.compact_width.unwrap_or(cfg.compact_width),
+            column_encoding: self.column_encoding.unwrap_or(cfg.column_encoding),
+            tab_width: cfg.tab_width,
+            show_source: self.show_source || cfg.show_source,
+            explain_extraction: self.explain_extraction || cfg.explain_extraction,
+            max_threads: args.max_threads.unwrap_or(cfg.max_threads),
+            parallel_processing: args.parallel_processing.unwrap_or(cfg.parallel_processing),
+            paramstyle: cfg.paramstyle,
             param_markers: cfg.param_markers,
             respect_git_exclude: cfg.respect_git_exclude,
-            respect_gitignore: self
-                .check_args
-                .respect_gitignore
-                .unwrap_or(cfg.respect_gitignore),
+            respect_gitignore: args.respect_gitignore.unwrap_or(cfg.respect_gitignore),
             respect_global_gitignore: cfg.respect_global_gitignore,
+            set_keys: cfg.set_keys,
+        }
+    }
+
+    /// Arguments of the `check` subcommand, or the top level ones when it was left out.
+    pub fn check_args(&self) -> &CheckArgs {
+        match &self.command {
+            Some(Commands::Check(args)) => args,
+            _ => &self.check_args,
         }
     }
 
@@ -78,11 +159,21 @@ impl Cli {
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// DEFAULT, Check Python files for SQL issues
     Check(CheckArgs),
     /// Initialize a new configuration file
     Init(InitArgs),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommands),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Check the configuration and custom rule files for errors
+    Validate,
 }
 
 #[derive(Args, Debug)]
@@ -122,6 +213,36 @@ pub struct CheckArgs {
     /// Function names with arguments to validate (e.g., "execute,execute_*,fetchall")
     #[arg(long, value_delimiter = ',')]
     pub function_contexts: Vec<String>,
+
+    /// Only report these rule codes or code prefixes (e.g. "SQ100,SQ2"), replacing `select`
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+
+    /// Rule codes or code prefixes not to report (e.g. "SQ202,SQ5"), added to `ignore`
+    #[arg(long, value_delimiter = ',')]
+    pub ignore: Vec<String>,
+
+    /// Shortest SQL string to check, after trimming (0 = no limit)
+    #[arg(long)]
+    pub min_sql_length: Option<usize>,
+
+    /// Check the source read from stdin instead of files
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Language of the source read from stdin
+    #[arg(long = "type", value_enum, default_value = "sql", requires = "stdin")]
+    pub stdin_type: StdinType,
+
+    /// File name reported for stdin, also matched against overrides and templated patterns
+    #[arg(long, requires = "stdin")]
+    pub stdin_filename: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StdinType {
+    /// Statements of a raw SQL file
+    Sql,
 }
 
 #[derive(Args, Debug)]
@@ -161,4 +282,65 @@ mod tests {
     fn verify_cli() {
         Cli::command().debug_assert()
     }
+
+    #[test]
+    fn stdin_args_of_check_subcommand() {
+        let cli = Cli::parse_from([
+            "sqint",
+            "check",
+            "--stdin",
+            "--type",
+            "sql",
+            "--stdin-filename",
+            "queries.sql",
+        ]);
+        let args = cli.check_args();
+        assert!(args.stdin);
+        assert_eq!(args.stdin_type, StdinType::Sql);
+        assert_eq!(args.stdin_filename.as_deref(), Some("queries.sql"));
+
+        assert!(Cli::try_parse_from(["sqint", "check", "--stdin-filename", "q.sql"]).is_err());
+    }
+
+    #[test]
+    fn check_subcommand_args_merged() {
+        let cli = Cli::parse_from(["sqint", "check", "--min-sql-length", "5"]);
+        assert_eq!(cli.merge_with_config(Config::default()).min_sql_length, 5);
+
+        let cli = Cli::parse_from([
+            "sqint",
+            "check",
+            "--max-threads",
+            "3",
+            "--parallel-processing",
+            "false",
+            "--respect-gitignore",
+            "false",
+        ]);
+        let merged = cli.merge_with_config(Config::default());
+        assert_eq!(merged.max_threads, 3);
+        assert!(!merged.parallel_processing);
+        assert!(!merged.respect_gitignore);
+
+        let config = Config {
+            select: vec!["SQ1".to_string()],
+            ignore: vec!["SQ5".to_string()],
+            ..Config::default()
+        };
+        let cli = Cli::parse_from([
+            "sqint",
+            "check",
+            "--select",
+            "SQ2",
+            "--ignore",
+            "SQ202,SQ204",
+        ]);
+        let merged = cli.merge_with_config(config);
+        assert_eq!(merged.select, vec!["SQ2"]);
+        assert_eq!(merged.ignore, vec!["SQ5", "SQ202", "SQ204"]);
+
+        // Without the subcommand the top level arguments still apply
+        let cli = Cli::parse_from(["sqint", "--min-sql-length", "7"]);
+        assert_eq!(cli.merge_with_config(Config::default()).min_sql_length, 7);
+    }
 }
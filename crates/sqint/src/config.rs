@@ -1,9 +1,13 @@
+use globset::Glob;
 use logging::LogLevel;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
+use crate::rules::Severity;
+
 pub const DEFAULT_CONFIG_NAME: &str = "sqint.toml";
 pub const PYPROJECT_CONFIG_NAME: &str = "pyproject.toml";
 pub const DEFAULT_CONFIG: &str = include_str!("./assets/default.toml");
@@ -15,15 +19,42 @@ pub struct Config {
     // Detection Settings
     pub variable_contexts: Vec<String>,
     pub function_contexts: Vec<String>,
+    pub presets: Vec<String>,
+    pub function_suffix_matching: bool,
+    pub case_sensitive: bool,
+    pub excluded_function_contexts: Vec<String>,
+    pub return_contexts: Vec<String>,
+    pub class_contexts: Vec<String>,
+    pub keyword_contexts: Vec<String>,
+    pub passthrough_wrappers: Vec<String>,
+    pub sql_keywords: Vec<String>,
+    pub extra_sql_keywords: Vec<String>,
+    pub fallback_extraction: bool,
+    pub detect_dead_sql: bool,
+    pub dead_sql_private_only: bool,
+    pub check_assert_comparisons: bool,
+    pub detect_bare_string_literals: bool,
+    pub detect_unnamed_sql: bool,
+    pub dataflow: bool,
+    pub min_sql_length: usize,
+    pub require_sql_keyword: bool,
+    pub report_unextractable: bool,
+    pub respect_noqa: bool,
+    pub split_multi_statement_strings: bool,
+    pub detect_interpolated_sql: bool,
 
     // File Processing
     pub file_patterns: Vec<String>,
     pub raw_sql_file_patterns: Vec<String>,
+    pub templated_sql_file_patterns: Vec<String>,
+    pub sql_in_data_files: HashMap<String, Vec<String>>,
+    pub statement_name_pattern: String,
     pub exclude_patterns: Vec<String>,
     pub respect_gitignore: bool,
     pub respect_global_gitignore: bool,
     pub respect_git_exclude: bool,
     pub include_hidden_files: bool,
+    pub check_stub_files: bool,
 
     // Threading Settings
     pub parallel_processing: bool,
@@ -33,14 +64,136 @@ pub struct Config {
     pub incremental_mode: bool,
     pub baseline_branch: String,
     pub include_staged: bool,
+    pub string_cache: bool,
 
     // Output Settings
     pub loglevel: LogLevel,
+    pub duplicate_findings: DuplicateFindings,
+    pub output_format: OutputFormat,
+    pub compact_width: usize,
+    pub column_encoding: ColumnEncoding,
+    pub tab_width: usize,
+    pub show_source: bool,
+    pub explain_extraction: bool,
 
     // SQL Parsing Settings
     pub dialect: String,
+    pub paramstyle: ParamStyle,
     pub param_markers: Vec<String>,
     pub dialect_mappings: HashMap<String, String>,
+
+    // Rules
+    pub missing_where_allowed_tables: Vec<String>,
+    pub missing_where_severity: Severity,
+    pub unordered_limit_severity: Severity,
+    pub duplicate_column_severity: Severity,
+    pub keyword_case: KeywordCase,
+    pub custom_rules: Vec<String>,
+    pub select: Vec<String>,
+    pub ignore: Vec<String>,
+
+    // Per-path Settings
+    pub overrides: Vec<Override>,
+
+    /// Top level keys of the file the config was read from, `merge_with` only takes those
+    #[serde(skip)]
+    pub set_keys: HashSet<String>,
+}
+
+/// Settings replacing the global ones for files matching `path`, a `[[overrides]]` section
+#[derive(Debug, Clone, Default, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// Glob over file paths relative to the project root, e.g. "migrations/**"
+    pub path: String,
+    pub variable_contexts: Option<Vec<String>>,
+    pub function_contexts: Option<Vec<String>>,
+    pub dialect: Option<String>,
+    pub param_markers: Option<Vec<String>>,
+    pub detect_interpolated_sql: Option<bool>,
+}
+
+/// What to do with identical findings reported for several variables, e.g. `query = sql = "..."`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateFindings {
+    All,
+    #[default]
+    Collapse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Every finding on its own line, with banners and summaries
+    #[default]
+    Full,
+    /// One line per file with findings, nothing on success
+    Compact,
+}
+
+/// Unit the columns of reported positions are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnEncoding {
+    /// Whatever the output format is read with, characters with tabs expanded to
+    /// `tab_width` for the terminal
+    #[default]
+    Auto,
+    /// Characters, a tab is one of them
+    Chars,
+    /// UTF-8 bytes
+    Bytes,
+    /// UTF-16 code units, as LSP clients count them
+    Utf16,
+}
+
+impl ColumnEncoding {
+    pub const fn for_output(
+        self,
+        format: OutputFormat,
+        tab_width: usize,
+    ) -> finder::preanalysis::ColumnEncoding {
+        use finder::preanalysis::ColumnEncoding as Encoding;
+        match (self, format) {
+            (Self::Auto, OutputFormat::Full | OutputFormat::Compact) => {
+                Encoding::Display(tab_width)
+            }
+            (Self::Chars, _) => Encoding::Chars,
+            (Self::Bytes, _) => Encoding::Bytes,
+            (Self::Utf16, _) => Encoding::Utf16,
+        }
+    }
+}
+
+/// Placeholder syntax of the database driver, following the DB-API `paramstyle` names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamStyle {
+    /// `?`
+    Qmark,
+    /// `%s`, `%d`, `%b`
+    Format,
+    /// `%(name)s`, positional `%s` included as psycopg accepts both
+    #[default]
+    Pyformat,
+    /// `:1`
+    Numeric,
+    /// `:name`
+    Named,
+    /// `$1`
+    Dollar,
+}
+
+/// Case SQL keywords are expected in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+    /// Keywords aren't checked
+    #[default]
+    Off,
 }
 
 /// Wrapper for pyproject.toml structure
@@ -66,6 +219,33 @@ impl Default for Config {
                 "*stmt*".to_string(),
             ],
             function_contexts: vec![],
+            presets: vec![],
+            function_suffix_matching: true,
+            case_sensitive: false,
+            excluded_function_contexts: finder::DEFAULT_EXCLUDED_FUNCTION_CONTEXTS
+                .map(String::from)
+                .to_vec(),
+            return_contexts: vec![],
+            class_contexts: vec![],
+            keyword_contexts: vec![],
+            passthrough_wrappers: finder::DEFAULT_PASSTHROUGH_WRAPPERS
+                .map(String::from)
+                .to_vec(),
+            sql_keywords: finder::DEFAULT_SQL_KEYWORDS.map(String::from).to_vec(),
+            extra_sql_keywords: vec![],
+            fallback_extraction: true,
+            detect_dead_sql: false,
+            dead_sql_private_only: false,
+            check_assert_comparisons: false,
+            detect_bare_string_literals: false,
+            detect_unnamed_sql: false,
+            dataflow: false,
+            min_sql_length: 0,
+            require_sql_keyword: true,
+            report_unextractable: false,
+            respect_noqa: false,
+            split_multi_statement_strings: false,
+            detect_interpolated_sql: true,
 
             // File Processing
             file_patterns: vec![
@@ -74,11 +254,15 @@ impl Default for Config {
                 "*.ipynb".to_string(),
             ],
             raw_sql_file_patterns: vec!["*.sql".to_string()],
+            templated_sql_file_patterns: vec![],
+            sql_in_data_files: HashMap::new(),
+            statement_name_pattern: finder::DEFAULT_STATEMENT_NAME_PATTERN.to_string(),
             exclude_patterns: vec![],
             respect_gitignore: true,
             respect_global_gitignore: false,
             respect_git_exclude: true,
             include_hidden_files: false,
+            check_stub_files: false,
 
             // Performance Settings
             parallel_processing: true,
@@ -88,12 +272,21 @@ impl Default for Config {
             incremental_mode: false,
             baseline_branch: "main".to_string(),
             include_staged: true,
+            string_cache: false,
 
             // Output Settings
             loglevel: LogLevel::default(),
+            duplicate_findings: DuplicateFindings::default(),
+            output_format: OutputFormat::default(),
+            compact_width: 80,
+            column_encoding: ColumnEncoding::default(),
+            tab_width: 1,
+            show_source: false,
+            explain_extraction: false,
 
             // SQL Parsing Settings
             dialect: "generic".to_string(),
+            paramstyle: ParamStyle::default(),
             param_markers: vec!["?".to_string()],
             dialect_mappings: {
                 let mut map = HashMap::new();
@@ -101,6 +294,21 @@ impl Default for Config {
                 map.insert("ISNULL".to_string(), "IS NULL".to_string());
                 map
             },
+
+            // Rules
+            missing_where_allowed_tables: vec![],
+            missing_where_severity: Severity::Error,
+            unordered_limit_severity: Severity::Warning,
+            duplicate_column_severity: Severity::Warning,
+            keyword_case: KeywordCase::default(),
+            custom_rules: vec![],
+            select: vec![],
+            ignore: vec![],
+
+            // Per-path Settings
+            overrides: vec![],
+
+            set_keys: HashSet::new(),
         }
     }
 }
@@ -120,8 +328,12 @@ impl Config {
     }
 
     pub fn from_toml(toml_content: &str) -> Result<Self, ConfigError> {
-        toml::from_str(toml_content)
-            .map_err(|e| ConfigError::Parse(format!("Failed to parse TOML: {e}")))
+        let mut config: Self = toml::from_str(toml_content)
+            .map_err(|e| ConfigError::Parse(format!("Failed to parse TOML: {e}")))?;
+        config.set_keys = toml::from_str::<toml::Table>(toml_content)
+            .map(|table| table.keys().cloned().collect())
+            .unwrap_or_default();
+        Ok(config)
     }
 
     /// Parse configuration from pyproject.toml file
@@ -130,7 +342,15 @@ impl Config {
             .map_err(|e| ConfigError::Parse(format!("Failed to parse pyproject.toml: {e}")))?;
 
         match pyproject.tool.and_then(|tool| tool.sqint) {
-            Some(config) => Ok(config),
+            Some(mut config) => {
+                config.set_keys = toml::from_str::<toml::Table>(toml_content)
+                    .ok()
+                    .and_then(|mut table| table.remove("tool"))
+                    .and_then(|mut tool| tool.as_table_mut()?.remove("sqint"))
+                    .and_then(|sqint| sqint.as_table().map(|t| t.keys().cloned().collect()))
+                    .unwrap_or_default();
+                Ok(config)
+            }
             None => Err(ConfigError::Parse(
                 "No [tool.sqint] section found in pyproject.toml".to_string(),
             )),
@@ -138,7 +358,10 @@ impl Config {
     }
 
     /// Merge this config with another, preferring values from the other config
-    pub fn merge_with(&mut self, other: Self) {
+    pub fn merge_with(&mut self, mut other: Self) {
+        // Settings `other` leaves out are at their defaults and keep the current values
+        let set = std::mem::take(&mut other.set_keys);
+
         // Detection Settings
         if !other.variable_contexts.is_empty() {
             self.variable_contexts = other.variable_contexts;
@@ -146,6 +369,76 @@ impl Config {
         if !other.function_contexts.is_empty() {
             self.function_contexts = other.function_contexts;
         }
+        if !other.presets.is_empty() {
+            self.presets = other.presets;
+        }
+        if set.contains("function_suffix_matching") {
+            self.function_suffix_matching = other.function_suffix_matching;
+        }
+        if set.contains("case_sensitive") {
+            self.case_sensitive = other.case_sensitive;
+        }
+        if !other.return_contexts.is_empty() {
+            self.return_contexts = other.return_contexts;
+        }
+        if !other.class_contexts.is_empty() {
+            self.class_contexts = other.class_contexts;
+        }
+        if !other.keyword_contexts.is_empty() {
+            self.keyword_contexts = other.keyword_contexts;
+        }
+        if set.contains("fallback_extraction") {
+            self.fallback_extraction = other.fallback_extraction;
+        }
+        // Also taken when empty, that re-enables extraction from every function
+        if set.contains("excluded_function_contexts") {
+            self.excluded_function_contexts = other.excluded_function_contexts;
+        }
+        if set.contains("passthrough_wrappers") {
+            self.passthrough_wrappers = other.passthrough_wrappers;
+        }
+        if set.contains("sql_keywords") {
+            self.sql_keywords = other.sql_keywords;
+        }
+        if set.contains("extra_sql_keywords") {
+            self.extra_sql_keywords = other.extra_sql_keywords;
+        }
+        if set.contains("detect_dead_sql") {
+            self.detect_dead_sql = other.detect_dead_sql;
+        }
+        if set.contains("dead_sql_private_only") {
+            self.dead_sql_private_only = other.dead_sql_private_only;
+        }
+        if set.contains("check_assert_comparisons") {
+            self.check_assert_comparisons = other.check_assert_comparisons;
+        }
+        if set.contains("detect_bare_string_literals") {
+            self.detect_bare_string_literals = other.detect_bare_string_literals;
+        }
+        if set.contains("detect_unnamed_sql") {
+            self.detect_unnamed_sql = other.detect_unnamed_sql;
+        }
+        if set.contains("dataflow") {
+            self.dataflow = other.dataflow;
+        }
+        if set.contains("min_sql_length") {
+            self.min_sql_length = other.min_sql_length;
+        }
+        if set.contains("require_sql_keyword") {
+            self.require_sql_keyword = other.require_sql_keyword;
+        }
+        if set.contains("report_unextractable") {
+            self.report_unextractable = other.report_unextractable;
+        }
+        if set.contains("respect_noqa") {
+            self.respect_noqa = other.respect_noqa;
+        }
+        if set.contains("split_multi_statement_strings") {
+            self.split_multi_statement_strings = other.split_multi_statement_strings;
+        }
+        if set.contains("detect_interpolated_sql") {
+            self.detect_interpolated_sql = other.detect_interpolated_sql;
+        }
 
         // File Processing
         if !other.file_patterns.is_empty() {
@@ -154,6 +447,15 @@ impl Config {
         if !other.raw_sql_file_patterns.is_empty() {
             self.raw_sql_file_patterns = other.raw_sql_file_patterns;
         }
+        if set.contains("templated_sql_file_patterns") {
+            self.templated_sql_file_patterns = other.templated_sql_file_patterns;
+        }
+        if set.contains("sql_in_data_files") {
+            self.sql_in_data_files = other.sql_in_data_files;
+        }
+        if set.contains("statement_name_pattern") {
+            self.statement_name_pattern = other.statement_name_pattern;
+        }
         if !other.exclude_patterns.is_empty() {
             self.exclude_patterns = other.exclude_patterns;
         }
@@ -169,6 +471,9 @@ impl Config {
         if other.include_hidden_files {
             self.include_hidden_files = other.include_hidden_files;
         }
+        if set.contains("check_stub_files") {
+            self.check_stub_files = other.check_stub_files;
+        }
 
         // Threading Settings
         if other.parallel_processing {
@@ -179,6 +484,27 @@ impl Config {
         }
 
         self.loglevel = other.loglevel;
+        if set.contains("duplicate_findings") {
+            self.duplicate_findings = other.duplicate_findings;
+        }
+        if set.contains("output_format") {
+            self.output_format = other.output_format;
+        }
+        if set.contains("compact_width") {
+            self.compact_width = other.compact_width;
+        }
+        if set.contains("column_encoding") {
+            self.column_encoding = other.column_encoding;
+        }
+        if set.contains("tab_width") {
+            self.tab_width = other.tab_width;
+        }
+        if set.contains("show_source") {
+            self.show_source = other.show_source;
+        }
+        if other.explain_extraction {
+            self.explain_extraction = other.explain_extraction;
+        }
 
         // Incremental Mode
         if other.incremental_mode {
@@ -190,14 +516,122 @@ impl Config {
         if other.include_staged {
             self.include_staged = other.include_staged;
         }
+        if set.contains("string_cache") {
+            self.string_cache = other.string_cache;
+        }
 
         // SQL Parsing Settings
+        if set.contains("paramstyle") {
+            self.paramstyle = other.paramstyle;
+        }
         if !other.param_markers.is_empty() {
             self.param_markers = other.param_markers;
         }
         if !other.dialect_mappings.is_empty() {
             self.dialect_mappings = other.dialect_mappings;
         }
+
+        // Rules
+        if set.contains("missing_where_allowed_tables") {
+            self.missing_where_allowed_tables = other.missing_where_allowed_tables;
+        }
+        if set.contains("missing_where_severity") {
+            self.missing_where_severity = other.missing_where_severity;
+        }
+        if set.contains("unordered_limit_severity") {
+            self.unordered_limit_severity = other.unordered_limit_severity;
+        }
+        if set.contains("duplicate_column_severity") {
+            self.duplicate_column_severity = other.duplicate_column_severity;
+        }
+        if set.contains("keyword_case") {
+            self.keyword_case = other.keyword_case;
+        }
+        if !other.custom_rules.is_empty() {
+            self.custom_rules = other.custom_rules;
+        }
+        if set.contains("select") {
+            self.select = other.select;
+        }
+        if set.contains("ignore") {
+            self.ignore = other.ignore;
+        }
+
+        // Per-path Settings
+        if !other.overrides.is_empty() {
+            self.overrides = other.overrides;
+        }
+
+        self.set_keys.extend(set);
+    }
+
+    /// The config for `path`, relative to the project root, with every matching override
+    /// applied in order, later sections win. `None` when no override matches.
+    pub fn for_path(&self, path: &str) -> Option<Self> {
+        let mut matching = self
+            .overrides
+            .iter()
+            .filter(|o| Glob::new(&o.path).is_ok_and(|glob| glob.compile_matcher().is_match(path)))
+            .peekable();
+        matching.peek()?;
+
+        let mut config = self.clone();
+        for o in matching {
+            if let Some(contexts) = &o.variable_contexts {
+                config.variable_contexts.clone_from(contexts);
+            }
+            if let Some(contexts) = &o.function_contexts {
+                config.function_contexts.clone_from(contexts);
+            }
+            if let Some(dialect) = &o.dialect {
+                config.dialect.clone_from(dialect);
+            }
+            if let Some(markers) = &o.param_markers {
+                config.param_markers.clone_from(markers);
+            }
+            if let Some(enabled) = o.detect_interpolated_sql {
+                config.detect_interpolated_sql = enabled;
+            }
+        }
+        // Contexts an override replaces keep the entries of the presets
+        config.add_preset_contexts();
+        Some(config)
+    }
+
+    /// Whether findings with `code` are reported. Entries of `select` and `ignore` match the
+    /// codes starting with them, "SQ2" covers SQ201 to SQ204. An empty `select` takes them all.
+    pub fn reports_code(&self, code: &str) -> bool {
+        let matches = |prefix: &String| code.starts_with(prefix.as_str());
+        (self.select.is_empty() || self.select.iter().any(matches))
+            && !self.ignore.iter().any(matches)
+    }
+
+    /// Pattern of the comments naming raw SQL statements, `None` when it's empty or invalid.
+    pub fn statement_name_regex(&self) -> Option<Regex> {
+        if self.statement_name_pattern.is_empty() {
+            return None;
+        }
+        Regex::new(&self.statement_name_pattern).ok()
+    }
+
+    /// One message per override whose `path` isn't a valid glob, and for an invalid
+    /// `statement_name_pattern`.
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self
+            .overrides
+            .iter()
+            .filter_map(|o| {
+                Glob::new(&o.path)
+                    .err()
+                    .map(|e| format!("invalid override path '{}': {e}", o.path))
+            })
+            .collect();
+        if let Some(Err(e)) = (!self.statement_name_pattern.is_empty())
+            .then(|| Regex::new(&self.statement_name_pattern))
+        {
+            errors.push(format!("invalid statement_name_pattern: {e}"));
+        }
+        errors
     }
 }
 
@@ -274,4 +708,94 @@ parallel_processing = false
         assert_eq!(config.file_patterns, vec!["*.py"]);
         assert!(!config.parallel_processing);
     }
+
+    #[test]
+    fn test_overrides_resolve_in_order() {
+        let config = Config::from_toml(
+            r#"
+function_contexts = ["execute"]
+dialect = "generic"
+
+[[overrides]]
+path = "migrations/**"
+function_contexts = ["op.execute"]
+dialect = "postgres"
+detect_interpolated_sql = false
+
+[[overrides]]
+path = "migrations/legacy/**"
+dialect = "mysql"
+param_markers = ["%s"]
+"#,
+        )
+        .unwrap();
+
+        assert!(config.for_path("app/models.py").is_none());
+
+        let migration = config.for_path("migrations/versions/0001_init.py").unwrap();
+        assert_eq!(migration.function_contexts, vec!["op.execute"]);
+        assert_eq!(migration.dialect, "postgres");
+        assert_eq!(migration.param_markers, vec!["?"]);
+        assert!(!migration.detect_interpolated_sql);
+
+        // Both match, the later section wins where they overlap
+        let legacy = config.for_path("migrations/legacy/0001_init.py").unwrap();
+        assert_eq!(legacy.function_contexts, vec!["op.execute"]);
+        assert_eq!(legacy.dialect, "mysql");
+        assert_eq!(legacy.param_markers, vec!["%s"]);
+    }
+
+    #[test]
+    fn test_class_contexts_merge() {
+        let mut config = Config::default();
+        config.merge_with(Config::from_toml(r#"class_contexts = ["*Queries"]"#).unwrap());
+        assert_eq!(config.class_contexts, vec!["*Queries"]);
+
+        // Files that don't mention them keep the configured ones
+        config.merge_with(Config::from_toml("").unwrap());
+        assert_eq!(config.class_contexts, vec!["*Queries"]);
+    }
+
+    #[test]
+    fn merge_takes_only_set_keys() {
+        let mut config = Config::default();
+        config.merge_with(Config::from_toml("fallback_extraction = false").unwrap());
+
+        // Left out, the inherited value stays
+        config.merge_with(Config::from_toml("tab_width = 4").unwrap());
+        assert!(!config.fallback_extraction);
+
+        // Set back to the default, it's taken all the same
+        config.merge_with(Config::from_toml("fallback_extraction = true").unwrap());
+        assert!(config.fallback_extraction);
+
+        let pyproject = Config::from_pyproject_toml(
+            "[tool.sqint]\nfallback_extraction = false\n\n[tool.other]\ndialect = \"mysql\"\n",
+        )
+        .unwrap();
+        assert!(pyproject.set_keys.contains("fallback_extraction"));
+        assert!(!pyproject.set_keys.contains("dialect"));
+    }
+
+    #[test]
+    fn select_and_ignore_codes() {
+        let config =
+            Config::from_toml("select = [\"SQ2\", \"SQ100\"]\nignore = [\"SQ202\"]").unwrap();
+        assert!(config.reports_code("SQ100"));
+        assert!(config.reports_code("SQ201"));
+        assert!(!config.reports_code("SQ202"));
+        assert!(!config.reports_code("SQ301"));
+
+        let config = Config::from_toml("ignore = [\"SQ301\"]").unwrap();
+        assert!(config.reports_code("X001"));
+        assert!(!config.reports_code("SQ301"));
+    }
+
+    #[test]
+    fn merge_takes_dataflow_set_to_default() {
+        let mut config = Config::default();
+        config.merge_with(Config::from_toml("dataflow = true").unwrap());
+        config.merge_with(Config::from_toml("dataflow = false").unwrap());
+        assert!(!config.dataflow);
+    }
 }
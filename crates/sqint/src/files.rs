@@ -2,7 +2,10 @@ use crate::config::{Config, DEFAULT_CONFIG_NAME, PYPROJECT_CONFIG_NAME};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use ignore::WalkBuilder;
 use logging::{always_log, error, warn};
-use std::{path::PathBuf, process::Command};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 /// Returns only files that have changed compared to the baseline branch
 pub fn filter_incremental_files(files: &[String], cfg: &Config) -> Vec<String> {
@@ -154,16 +157,29 @@ pub fn canonicalize_files(files: Vec<std::path::PathBuf>) -> Vec<String> {
         .collect()
 }
 
-/// Filters exclude files and returns files matching include patterns and files matching raw sql patterns
+/// Filters exclude files and returns files matching include patterns and files matching raw sql
+/// patterns, templated ones and data files holding SQL included
 pub fn filter_file_pats(files: Vec<String>, cfg: &Config) -> (Vec<String>, Vec<String>) {
     let include_pats: GlobSet = slice_to_glob(&cfg.file_patterns, "file_patterns");
-    let sql_pats: GlobSet = slice_to_glob(&cfg.raw_sql_file_patterns, "raw_sql_file_patterns");
+    let data_pats: Vec<String> = cfg.sql_in_data_files.keys().cloned().collect();
+    let sql_pats: GlobSet = slice_to_glob(
+        &[
+            &cfg.raw_sql_file_patterns[..],
+            &cfg.templated_sql_file_patterns,
+            &data_pats,
+        ]
+        .concat(),
+        "raw_sql_file_patterns",
+    );
     let exclude_pats: GlobSet = slice_to_glob(&cfg.exclude_patterns, "exclude_patterns");
 
     let mut include_files = Vec::new();
     let mut sql_files = Vec::new();
 
     for file in files {
+        if !cfg.check_stub_files && Path::new(&file).extension().is_some_and(|ext| ext == "pyi") {
+            continue;
+        }
         if !exclude_pats.is_match(&file) {
             if include_pats.is_match(&file) {
                 include_files.push(file);
@@ -194,3 +210,34 @@ fn slice_to_glob(patterns: &[String], log_ctx: &str) -> GlobSet {
             GlobSetBuilder::new().build().unwrap()
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stub_files_skipped_unless_enabled() {
+        let files = vec!["app/db.py".to_string(), "app/db.pyi".to_string()];
+        let mut cfg = Config::default();
+
+        let (python, _) = filter_file_pats(files.clone(), &cfg);
+        assert_eq!(python, vec!["app/db.py"]);
+
+        cfg.check_stub_files = true;
+        let (python, _) = filter_file_pats(files, &cfg);
+        assert_eq!(python, vec!["app/db.py", "app/db.pyi"]);
+    }
+
+    #[test]
+    fn templated_sql_files_collected() {
+        let files = vec!["app/db.py".to_string(), "models/users.sql.j2".to_string()];
+        let mut cfg = Config::default();
+
+        let (_, sql) = filter_file_pats(files.clone(), &cfg);
+        assert!(sql.is_empty());
+
+        cfg.templated_sql_file_patterns = vec!["*.sql.j2".to_string()];
+        let (_, sql) = filter_file_pats(files, &cfg);
+        assert_eq!(sql, vec!["models/users.sql.j2"]);
+    }
+}
@@ -1,16 +1,85 @@
 use logging::{always_log, error, info};
+use std::io::Read;
 use std::sync::Arc;
 use std::thread;
 
 use crate::analyzer::SqlDialect;
+use crate::cache::{STRING_CACHE_PATH, StringCache};
+use crate::config::OutputFormat;
+use crate::rules::{Diagnostic, Rule};
 
-#[allow(clippy::too_many_lines)]
-pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
-    let cfg = Arc::new(finder::FinderConfig::new(
+fn finder_config(config: &crate::Config) -> crate::FinderConfig {
+    let mut data_files: Vec<(String, Vec<String>)> = config
+        .sql_in_data_files
+        .iter()
+        .map(|(pattern, keys)| (pattern.clone(), keys.clone()))
+        .collect();
+    data_files.sort();
+    finder::FinderConfig::new(
         &config.variable_contexts,
         &config.function_contexts,
-    ));
-    let (found_files, explicit_files) = crate::files::collect_files(&cli.check_args.paths, config);
+        config.case_sensitive,
+    )
+    .with_suffix_matching(config.function_suffix_matching)
+    .with_excluded_function_contexts(&config.excluded_function_contexts)
+    .with_return_contexts(&config.return_contexts)
+    .with_class_contexts(&config.class_contexts)
+    .with_keyword_contexts(&config.keyword_contexts)
+    .with_passthrough_wrappers(&config.passthrough_wrappers)
+    .with_templated_sql_file_patterns(&config.templated_sql_file_patterns)
+    .with_data_file_keys(&data_files)
+    .with_sql_keywords(&[&config.sql_keywords[..], &config.extra_sql_keywords].concat())
+    .with_statement_name_pattern(config.statement_name_regex())
+    .with_fallback_extraction(config.fallback_extraction)
+    .with_dead_sql_detection(config.detect_dead_sql)
+    .with_dead_sql_private_only(config.dead_sql_private_only)
+    .with_interpolation_detection(config.detect_interpolated_sql)
+    .with_assert_comparisons(config.check_assert_comparisons)
+    .with_module_literals(config.detect_bare_string_literals)
+    .with_unnamed_literals(config.detect_unnamed_sql)
+    .with_dataflow(config.dataflow)
+    .with_min_sql_length(config.min_sql_length)
+    .with_require_sql_keyword(config.require_sql_keyword)
+    .with_unextractable_reporting(config.report_unextractable)
+    .with_respect_noqa(config.respect_noqa)
+    .with_explain_extraction(config.explain_extraction)
+}
+
+#[allow(clippy::too_many_lines)]
+pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
+    let config_errors = config.validation_errors();
+    if !config_errors.is_empty() {
+        for e in config_errors {
+            error!("Invalid configuration: {e}");
+        }
+        return;
+    }
+    let cfg = Arc::new(finder_config(config));
+    let rules = match crate::rules::registry(config) {
+        Ok(rules) => Arc::new(rules),
+        Err(errors) => {
+            for e in errors {
+                error!("Invalid custom rule: {e}");
+            }
+            return;
+        }
+    };
+    let args = cli.check_args();
+    if args.stdin {
+        check_stdin(config, &rules, args.stdin_filename.as_deref());
+        return;
+    }
+    let cache = config.string_cache.then(|| {
+        Arc::new(StringCache::load(
+            Some(
+                finder::project_root()
+                    .unwrap_or_default()
+                    .join(STRING_CACHE_PATH),
+            ),
+            crate::cache::ruleset_hash(config),
+        ))
+    });
+    let (found_files, explicit_files) = crate::files::collect_files(&args.paths, config);
     let explicit_files = crate::files::canonicalize_files(explicit_files);
     let found_files = crate::files::canonicalize_files(found_files);
     if found_files.is_empty() && explicit_files.is_empty() {
@@ -34,7 +103,8 @@ pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
             std::thread::available_parallelism()
                 .map(std::num::NonZero::get)
                 .unwrap_or(5)
-                - 1
+                .saturating_sub(1)
+                .max(1)
         } else {
             info!("Using configured thread count: {}", config.max_threads);
             config.max_threads
@@ -49,9 +119,18 @@ pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
                     let chunk_vec = chunk.to_vec();
                     let cfg = cfg.clone();
                     let app_cfg = config.clone();
+                    let rules = rules.clone();
+                    let cache = cache.clone();
                     thread::spawn(move || {
                         for file_path in chunk_vec {
-                            process_file(&file_path, cfg.clone(), &app_cfg.clone(), false);
+                            process_file(
+                                &file_path,
+                                cfg.clone(),
+                                &app_cfg.clone(),
+                                &rules,
+                                cache.as_deref(),
+                                false,
+                            );
                         }
                     })
                 })
@@ -69,9 +148,18 @@ pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
                     let chunk_vec = chunk.to_vec();
                     let cfg = cfg.clone();
                     let app_cfg = config.clone();
+                    let rules = rules.clone();
+                    let cache = cache.clone();
                     thread::spawn(move || {
                         for file_path in chunk_vec {
-                            process_file(&file_path, cfg.clone(), &app_cfg.clone(), true);
+                            process_file(
+                                &file_path,
+                                cfg.clone(),
+                                &app_cfg.clone(),
+                                &rules,
+                                cache.as_deref(),
+                                true,
+                            );
                         }
                     })
                 })
@@ -81,14 +169,32 @@ pub fn handle_check(config: &Arc<crate::Config>, cli: &crate::Cli) {
         }
     } else {
         for file_path in &target_files {
-            process_file(file_path, cfg.clone(), &config.clone(), false);
+            process_file(
+                file_path,
+                cfg.clone(),
+                &config.clone(),
+                &rules,
+                cache.as_deref(),
+                false,
+            );
         }
 
         for file_path in &sql_files {
-            process_file(file_path, cfg.clone(), &config.clone(), true);
+            process_file(
+                file_path,
+                cfg.clone(),
+                &config.clone(),
+                &rules,
+                cache.as_deref(),
+                true,
+            );
         }
     }
 
+    if let Some(cache) = cache.and_then(|c| Arc::try_unwrap(c).ok()) {
+        cache.save();
+    }
+
     always_log!(
         "Sqint: Processed {} files ({} Python, {} SQL).",
         total_files,
@@ -101,14 +207,64 @@ fn process_file(
     file_path: &str,
     cfg: Arc<crate::FinderConfig>,
     app_cfg: &Arc<crate::Config>,
+    rules: &Arc<Vec<Box<dyn Rule>>>,
+    cache: Option<&StringCache>,
     is_raw_sql: bool,
 ) {
+    // Overrides are matched against the path below the project root the config came from
+    let overridden = app_cfg.for_path(&finder::project_path(file_path));
+    let (cfg, app_cfg) = match &overridden {
+        Some(config) => (Arc::new(finder_config(config)), config),
+        None => (cfg, &**app_cfg),
+    };
     let mut sql_finder = finder::SqlFinder::new(cfg);
 
-    let Some(sql_extract) = sql_finder.analyze_file(file_path, is_raw_sql) else {
-        return;
+    let sql_extract = match sql_finder.analyze_file(file_path, is_raw_sql) {
+        Ok(extract) => extract,
+        Err(e) => {
+            // Named like the findings of files that could be read
+            let path = finder::display_path(&finder::project_path(file_path));
+            let diagnostics: Vec<Diagnostic> = [Diagnostic::for_file_error(path.clone(), &e)]
+                .into_iter()
+                .filter(|d| app_cfg.reports_code(&d.code))
+                .collect();
+            match app_cfg.output_format {
+                OutputFormat::Full => crate::output::report(&diagnostics, None, app_cfg.tab_width),
+                OutputFormat::Compact => {
+                    crate::output::report_compact(&path, &diagnostics, app_cfg.compact_width);
+                }
+            }
+            return;
+        }
     };
+    crate::output::report_explanations(&sql_extract.display_path(), &sql_extract.explanations);
+    check_extract(&sql_extract, app_cfg, rules, cache, is_raw_sql);
+}
+
+/// Checks raw SQL read from stdin as if it was a file named `file_name`, `<stdin>` by default.
+fn check_stdin(config: &crate::Config, rules: &Arc<Vec<Box<dyn Rule>>>, file_name: Option<&str>) {
+    let mut src = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut src) {
+        error!("Failed to read stdin: {e}");
+        return;
+    }
+    let file_name = file_name.unwrap_or("<stdin>");
+    let overridden = config.for_path(file_name);
+    let app_cfg = overridden.as_ref().unwrap_or(config);
 
+    let sql_finder = finder::SqlFinder::new(Arc::new(finder_config(app_cfg)));
+    let sql_extract = sql_finder.analyze_sql_src(&src, file_name);
+    check_extract(&sql_extract, app_cfg, rules, None, true);
+}
+
+/// Validates the strings of an extract and reports the findings.
+fn check_extract(
+    sql_extract: &finder::SqlExtract,
+    app_cfg: &crate::Config,
+    rules: &Arc<Vec<Box<dyn Rule>>>,
+    cache: Option<&StringCache>,
+    is_raw_sql: bool,
+) {
     let Some(dialect) = SqlDialect::from_str(&app_cfg.dialect) else {
         error!(
             "Unknown dialect. Supported: {:?}",
@@ -120,10 +276,64 @@ fn process_file(
     let analyzer = crate::analyzer::SqlAnalyzer::new(
         &dialect,
         app_cfg.dialect_mappings.clone(),
+        app_cfg.paramstyle,
         &app_cfg.param_markers,
+        rules.clone(),
+    )
+    // Statements of .sql files are complete, only strings found in Python can be fragments
+    .with_fragments(!app_cfg.require_sql_keyword && !is_raw_sql)
+    .with_statement_splitting(app_cfg.split_multi_statement_strings);
+
+    let mut diagnostics = crate::output::apply_duplicate_policy(
+        analyzer.analyze_sql_extract(sql_extract, cache),
+        app_cfg.duplicate_findings,
     );
+    diagnostics.retain(|d| app_cfg.reports_code(&d.code));
+    let encoding = app_cfg
+        .column_encoding
+        .for_output(app_cfg.output_format, app_cfg.tab_width);
+    for d in &mut diagnostics {
+        d.range = sql_extract.source.encode_range(&d.range, encoding);
+    }
+    match app_cfg.output_format {
+        OutputFormat::Full => crate::output::report(
+            &diagnostics,
+            app_cfg.show_source.then_some(&*sql_extract.source),
+            app_cfg.tab_width,
+        ),
+        OutputFormat::Compact => crate::output::report_compact(
+            &sql_extract.display_path(),
+            &diagnostics,
+            app_cfg.compact_width,
+        ),
+    }
+}
 
-    analyzer.analyze_sql_extract(&sql_extract);
+/// `presets_valid` tells whether every preset was known, unknown ones were reported already.
+pub fn handle_config_validate(config: &crate::Config, presets_valid: bool) {
+    let mut valid = presets_valid;
+    if SqlDialect::from_str(&config.dialect).is_none() {
+        valid = false;
+        error!(
+            "Unknown dialect '{}'. Supported: {:?}",
+            config.dialect,
+            SqlDialect::supported_dialects()
+        );
+    }
+    for e in config.validation_errors() {
+        valid = false;
+        error!("Invalid configuration: {e}");
+    }
+    if let Err(errors) = crate::rules::registry(config) {
+        valid = false;
+        for e in errors {
+            error!("Invalid custom rule: {e}");
+        }
+    }
+
+    if valid {
+        always_log!("Configuration is valid.");
+    }
 }
 
 pub fn handle_init() {
@@ -1,10 +1,14 @@
 mod analyzer;
+mod cache;
 mod cli;
 mod config;
 mod files;
 mod handlers;
+mod output;
+mod presets;
+mod rules;
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ConfigCommands};
 use config::{Config, DEFAULT_CONFIG, DEFAULT_CONFIG_NAME};
 use finder::FinderConfig;
 use logging::{Logger, always_log, debug};
@@ -13,8 +17,11 @@ use logging::{Logger, always_log, debug};
 //TODO: Big Refactor + Tests + Asserts
 fn main() {
     let cli = Cli::parse();
-    let config = cli.merge_with_config(files::load_config());
+    // Before loading the config, which already reports where it came from
+    Logger::set_quiet(cli.format == Some(config::OutputFormat::Compact));
+    let mut config = cli.merge_with_config(files::load_config());
     setup_logging(&cli, &config);
+    let presets_valid = config.expand_presets();
 
     debug!("CLI arguments parsed: {:?}", cli);
     debug!("Configuration loaded successfully");
@@ -33,6 +40,9 @@ fn main() {
                 Commands::Init(_) => {
                     handlers::handle_init();
                 }
+                Commands::Config(ConfigCommands::Validate) => {
+                    handlers::handle_config_validate(&config, presets_valid);
+                }
             }
         }
     }
@@ -56,4 +66,6 @@ fn setup_logging(cli: &Cli, cfg: &Config) {
     let ll = cli.loglevel.unwrap_or(cfg.loglevel);
     debug!("Logging initialized at level: {:?}", ll);
     Logger::init(ll);
+    Logger::set_colored(cli.use_colored_output());
+    Logger::set_quiet(cfg.output_format == config::OutputFormat::Compact);
 }
@@ -0,0 +1,275 @@
+use finder::Explanation;
+use finder::preanalysis::{ColumnEncoding, LineCol, PreanalyzedFile, display_width};
+use logging::{LogLevel, Logger, always_log, error, warn};
+
+use crate::config::DuplicateFindings;
+use crate::rules::{Diagnostic, Severity, plural};
+
+/// Reports every finding, followed by its source line when `source` is given.
+pub fn report(diagnostics: &[Diagnostic], source: Option<&PreanalyzedFile>, tab_width: usize) {
+    for d in diagnostics {
+        let snippet = source
+            .and_then(|source| snippet(source, &d.range.start, tab_width))
+            .map(|snippet| format!("\n{snippet}"))
+            .unwrap_or_default();
+        match d.severity {
+            Severity::Error => {
+                Logger::increment_sql_error_count();
+                error!("{d}{snippet}");
+            }
+            Severity::Warning => warn!("{d}{snippet}"),
+        }
+    }
+}
+
+/// Prints the calls the finder skipped on purpose, one line each in the format of a finding.
+pub fn report_explanations(path: &str, explanations: &[Explanation]) {
+    for e in explanations {
+        always_log!("{}", explanation_line(path, e));
+    }
+}
+
+fn explanation_line(path: &str, explanation: &Explanation) -> String {
+    format!(
+        "{path}:{}{}: not extracted, {}",
+        explanation.range.start, explanation.function_name, explanation.reason
+    )
+}
+
+/// The line of `pos` with a caret under it, `rustc` style. Tabs are expanded to `tab_width`,
+/// with a width of 1 they stay tabs so the caret lines up however wide they are shown.
+fn snippet(source: &PreanalyzedFile, pos: &LineCol, tab_width: usize) -> Option<String> {
+    let line = source.line_text(pos.line())?;
+    let (text, indent) = if tab_width > 1 {
+        let text = line.chars().fold(String::new(), |mut text, ch| {
+            if ch == '\t' {
+                let width = display_width(&text, tab_width);
+                text.extend(std::iter::repeat_n(' ', tab_width - width % tab_width));
+            } else {
+                text.push(ch);
+            }
+            text
+        });
+        let col = source.column(pos, ColumnEncoding::Display(tab_width));
+        (text, " ".repeat(col - 1))
+    } else {
+        let col = source.column(pos, ColumnEncoding::Chars);
+        let indent = line
+            .chars()
+            .take(col - 1)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        (line.to_string(), indent)
+    };
+    let gutter = " ".repeat(pos.line().to_string().len());
+    Some(format!(
+        "{gutter} |\n{} | {text}\n{gutter} | {indent}^",
+        pos.line()
+    ))
+}
+
+/// Compact output, a single line for a file with findings and nothing for a clean one.
+pub fn report_compact(path: &str, diagnostics: &[Diagnostic], width: usize) {
+    let shown: Vec<&Diagnostic> = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error || Logger::should_log(LogLevel::Warn))
+        .collect();
+    for d in &shown {
+        if d.severity == Severity::Error {
+            Logger::increment_sql_error_count();
+            Logger::mark_error_occurred();
+        }
+    }
+    if let Some(line) = compact_line(path, &shown, width) {
+        println!("{line}");
+    }
+}
+
+fn compact_line(path: &str, diagnostics: &[&Diagnostic], width: usize) -> Option<String> {
+    let first = diagnostics
+        .iter()
+        .min_by_key(|d| (d.range.start.line(), d.range.start.col()))?;
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    let counts = match (errors, warnings) {
+        (0, w) => plural(w, "SQL warning"),
+        (e, 0) => plural(e, "SQL error"),
+        (e, w) => format!("{}, {}", plural(e, "SQL error"), plural(w, "warning")),
+    };
+
+    let message = if first.message.chars().count() > width {
+        let kept: String = first
+            .message
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect();
+        format!("{kept}…")
+    } else {
+        first.message.clone()
+    };
+
+    Some(format!(
+        "{path}: {counts} (first: line {} {} {message})",
+        first.range.start.line(),
+        first.code
+    ))
+}
+
+/// Applies the `duplicate_findings` policy to the diagnostics of a single file. The same literal
+/// reached through several targets (`query = sql = "..."`) is reported once, listing the other
+/// variable names.
+pub fn apply_duplicate_policy(
+    diagnostics: Vec<Diagnostic>,
+    policy: DuplicateFindings,
+) -> Vec<Diagnostic> {
+    if policy == DuplicateFindings::All {
+        return diagnostics;
+    }
+
+    let mut collapsed: Vec<(Diagnostic, Vec<String>)> = vec![];
+    for d in diagnostics {
+        match collapsed.iter_mut().find(|(kept, _)| {
+            kept.file == d.file
+                && kept.range == d.range
+                && kept.code == d.code
+                && kept.message == d.message
+        }) {
+            Some((kept, also)) => {
+                if kept.variable_name != d.variable_name && !also.contains(&d.variable_name) {
+                    also.push(d.variable_name);
+                }
+            }
+            None => collapsed.push((d, vec![])),
+        }
+    }
+
+    collapsed
+        .into_iter()
+        .map(|(mut d, also)| {
+            if !also.is_empty() {
+                d.message = format!("{} (also assigned to: {})", d.message, also.join(", "));
+            }
+            d
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::builtin_rules;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    const CHAINED: &str = r#"query = sql = "SELEC * FROM users""#;
+
+    #[test]
+    fn chained_assignment_collapsed() {
+        let diagnostics = apply_duplicate_policy(
+            diagnostics_for(finder_config(), builtin_rules(), CHAINED),
+            DuplicateFindings::Collapse,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].variable_name, "query");
+        assert_eq!(
+            diagnostics[0].message,
+            "`SELEC * FROM users` => Expected: an SQL statement, found: SELEC (also assigned to: sql)"
+        );
+    }
+
+    #[test]
+    fn chained_assignment_all() {
+        let diagnostics = apply_duplicate_policy(
+            diagnostics_for(finder_config(), builtin_rules(), CHAINED),
+            DuplicateFindings::All,
+        );
+        let names: Vec<_> = diagnostics
+            .iter()
+            .map(|d| d.variable_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["query", "sql"]);
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| !d.message.contains("also assigned to"))
+        );
+    }
+
+    fn compact_output(files: &[(&str, &str)], width: usize) -> String {
+        files
+            .iter()
+            .filter_map(|(path, src)| {
+                let diagnostics = diagnostics_for(finder_config(), builtin_rules(), src);
+                compact_line(path, &diagnostics.iter().collect::<Vec<_>>(), width)
+            })
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn compact_clean_run_is_silent() {
+        let out = compact_output(&[("app/db.py", r#"query = "SELECT id FROM users""#)], 80);
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn compact_failing_files() {
+        let out = compact_output(
+            &[
+                (
+                    "app/db.py",
+                    "x = 1\nother_sql = \"SELECT FROM\"\nquery = \"SELEC id FROM users\"\n",
+                ),
+                ("app/clean.py", r#"query = "SELECT 1""#),
+                ("app/reports.py", r#"report_query = "DELETE users WHERE""#),
+            ],
+            30,
+        );
+        assert_eq!(
+            out,
+            "app/db.py: 2 SQL errors (first: line 2 SQ100 `SELECT FROM` => SQL parsing …)\n\
+             app/reports.py: 1 SQL error (first: line 1 SQ100 `DELETE users WHERE` => SQL p…)\n"
+        );
+    }
+
+    #[test]
+    fn explanation_lines() {
+        let explanation = Explanation {
+            function_name: "logger.info".to_string(),
+            reason: "call matches excluded_function_contexts".to_string(),
+            range: finder::preanalysis::Range {
+                start: LineCol::new(3, 5, 40),
+                end: LineCol::new(3, 40, 75),
+            },
+        };
+        assert_eq!(
+            explanation_line("app/db.py", &explanation),
+            "app/db.py:3:5:logger.info: not extracted, call matches excluded_function_contexts"
+        );
+    }
+
+    #[test]
+    fn source_snippet() {
+        let src = "def load():\n\tlabel = \"日本\"; query = \"SELEC 1\"\n";
+        let diagnostics = diagnostics_for(finder_config(), builtin_rules(), src);
+        let source = PreanalyzedFile::from_src(src);
+        assert_eq!(
+            snippet(&source, &diagnostics[0].range.start, 1).unwrap(),
+            "  |\n2 | \tlabel = \"日本\"; query = \"SELEC 1\"\n  | \t                      ^"
+        );
+    }
+
+    #[test]
+    fn source_snippet_expands_tabs() {
+        let src = "class Queries:\n \t query = \"SELEC 1\"\n";
+        let diagnostics = diagnostics_for(finder_config(), builtin_rules(), src);
+        let source = PreanalyzedFile::from_src(src);
+        assert_eq!(
+            snippet(&source, &diagnostics[0].range.start, 4).unwrap(),
+            "  |\n2 |      query = \"SELEC 1\"\n  |              ^"
+        );
+    }
+}
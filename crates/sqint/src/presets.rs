@@ -0,0 +1,277 @@
+use logging::error;
+
+use crate::config::Config;
+
+/// Function and keyword contexts of a database library, enabled through `presets`.
+struct Preset {
+    name: &'static str,
+    function_contexts: &'static [&'static str],
+    keyword_contexts: &'static [&'static str],
+}
+
+// Entries are matched against the trailing parts of dotted calls, `cursor.execute` catches
+// `self.conn.cursor.execute` too. Only the SQL argument is taken where the rest are parameters,
+// `sql=` keywords are named where the library's docs use them. Lists passed to named arguments
+// are taken apart, Django's `RunSQL` takes one statement per item. Keyword contexts are the names
+// the library gives SQL arguments, for calls of other functions passing them along.
+const PRESETS: &[Preset] = &[
+    Preset {
+        name: "dbapi",
+        function_contexts: &[
+            "cursor.execute:0",
+            "cursor.executemany:0",
+            "cursor.executescript",
+        ],
+        keyword_contexts: &["operation"],
+    },
+    Preset {
+        name: "sqlalchemy",
+        function_contexts: &[
+            "session.execute:0",
+            "engine.execute:0",
+            "connection.execute:0",
+            "conn.execute:0",
+            "connection.exec_driver_sql:0",
+            "conn.exec_driver_sql:0",
+        ],
+        keyword_contexts: &["statement"],
+    },
+    Preset {
+        name: "django",
+        function_contexts: &[
+            "objects.raw:0",
+            "RawSQL:0",
+            "cursor.execute:0",
+            "RunSQL:0",
+            "RunSQL:1",
+            "RunSQL:sql",
+            "RunSQL:reverse_sql",
+        ],
+        keyword_contexts: &["raw_query"],
+    },
+    Preset {
+        name: "asyncpg",
+        function_contexts: &[
+            "conn.execute:0",
+            "conn.executemany:0",
+            "conn.fetch:0",
+            "conn.fetchrow:0",
+            "conn.fetchval:0",
+            "connection.fetch:0",
+            "connection.fetchrow:0",
+            "connection.fetchval:0",
+            "pool.execute:0",
+            "pool.fetch:0",
+            "pool.fetchrow:0",
+            "pool.fetchval:0",
+        ],
+        keyword_contexts: &["query"],
+    },
+    Preset {
+        name: "alembic",
+        function_contexts: &["op.execute:0", "get_bind.execute:0"],
+        keyword_contexts: &[],
+    },
+    Preset {
+        name: "pandas",
+        function_contexts: &[
+            "read_sql:0",
+            "read_sql:sql",
+            "read_sql_query:0",
+            "read_sql_query:sql",
+        ],
+        keyword_contexts: &[],
+    },
+];
+
+impl Config {
+    /// Adds the contexts of every preset in `presets`. Patterns the config already lists keep
+    /// their configured entries, so `cursor.execute` there still extracts every argument.
+    /// Returns false when a preset is unknown.
+    pub fn expand_presets(&mut self) -> bool {
+        let unknown = self.add_preset_contexts();
+        for name in &unknown {
+            error!(
+                "Unknown preset '{name}'. Supported: {:?}",
+                PRESETS.iter().map(|p| p.name).collect::<Vec<_>>()
+            );
+        }
+        unknown.is_empty()
+    }
+
+    /// Adds the contexts of the known presets in `presets` and returns the unknown names.
+    pub(crate) fn add_preset_contexts(&mut self) -> Vec<String> {
+        let mut unknown = vec![];
+        for name in &self.presets {
+            match PRESETS.iter().find(|p| p.name == *name) {
+                Some(preset) => {
+                    extend_missing(&mut self.function_contexts, preset.function_contexts);
+                    extend_missing(&mut self.keyword_contexts, preset.keyword_contexts);
+                }
+                None => unknown.push(name.clone()),
+            }
+        }
+        unknown
+    }
+}
+
+/// Adds the entries whose pattern isn't configured already, argument specs aside.
+fn extend_missing(contexts: &mut Vec<String>, entries: &[&str]) {
+    let pattern = |entry: &str| entry.rsplit_once(':').map_or(entry, |(p, _)| p).to_string();
+    let configured: Vec<String> = contexts.iter().map(|c| pattern(c)).collect();
+    contexts.extend(
+        entries
+            .iter()
+            .filter(|entry| !configured.contains(&pattern(entry)))
+            .map(ToString::to_string),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use finder::{FinderConfig, SqlFinder};
+
+    use super::*;
+    use crate::rules::builtin_rules;
+    use crate::rules::test_utils::diagnostics_for;
+
+    fn found(presets: &[&str], function_contexts: &[&str], src: &str) -> Vec<String> {
+        let mut config = Config {
+            presets: presets.iter().map(ToString::to_string).collect(),
+            function_contexts: function_contexts.iter().map(ToString::to_string).collect(),
+            ..Config::default()
+        };
+        assert!(config.expand_presets());
+        let finder = SqlFinder::new(Arc::new(
+            FinderConfig::new(&[], &config.function_contexts, config.case_sensitive)
+                .with_keyword_contexts(&config.keyword_contexts),
+        ));
+        finder
+            .analyze_python_src(src, "test.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.sql_content)
+            .collect()
+    }
+
+    #[test]
+    fn each_preset_catches_its_library() {
+        let cases = [
+            (
+                "dbapi",
+                "self.conn.cursor().execute(\"SELECT 1\", (user_id,))",
+            ),
+            ("sqlalchemy", "session.execute(text(\"SELECT 1\"), params)"),
+            ("sqlalchemy", "conn.exec_driver_sql(\"SELECT 1\")"),
+            ("sqlalchemy", "session.scalars(statement=\"SELECT 1\")"),
+            ("django", "User.objects.raw(\"SELECT 1\", [user_id])"),
+            ("asyncpg", "await conn.fetchrow(\"SELECT 1\", user_id)"),
+            ("asyncpg", "conn.cursor(query=\"SELECT 1\")"),
+            ("alembic", "op.execute(\"SELECT 1\")"),
+            ("alembic", "op.get_bind().execute(sa.text(\"SELECT 1\"))"),
+            ("pandas", "pd.read_sql(sql=\"SELECT 1\", con=engine)"),
+        ];
+        for (preset, src) in cases {
+            assert_eq!(
+                found(&[preset], &[], src),
+                vec!["SELECT 1"],
+                "{preset}: {src}"
+            );
+            assert!(found(&[], &[], src).is_empty(), "{src}");
+        }
+    }
+
+    #[test]
+    fn django_migrations() {
+        let src = r#"operations = [
+    migrations.RunSQL("ALTER TABLE users ADD COLUMN age int"),
+    migrations.RunSQL(["CREATE INDEX a ON t (x)", ("INSERT INTO t VALUES (%s)", [1])]),
+    migrations.RunSQL(
+        sql="UPDATE users SET age = 0",
+        reverse_sql="UPDATE users SET age NULL",
+    ),
+]
+"#;
+        assert_eq!(
+            found(&["django"], &[], src),
+            vec![
+                "ALTER TABLE users ADD COLUMN age int",
+                "CREATE INDEX a ON t (x)",
+                "INSERT INTO t VALUES (%s)",
+                "UPDATE users SET age = 0",
+                "UPDATE users SET age NULL",
+            ]
+        );
+
+        let mut config = Config {
+            presets: vec!["django".to_string()],
+            ..Config::default()
+        };
+        assert!(config.expand_presets());
+        let finder_config = FinderConfig::new(&[], &config.function_contexts, true);
+        let diagnostics: Vec<_> = diagnostics_for(finder_config, builtin_rules(), src)
+            .into_iter()
+            .map(|d| (d.code, d.range.start.to_string()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("SQ201".to_string(), "5:14:".to_string()),
+                ("SQ100".to_string(), "6:21:".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_entries_take_precedence() {
+        let src = "cursor.execute(\"SELECT 1\", \"SELECT 2\")";
+        assert_eq!(found(&["dbapi"], &[], src), vec!["SELECT 1"]);
+        assert_eq!(
+            found(&["dbapi"], &["cursor.execute"], src),
+            vec!["SELECT 1", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn overrides_keep_preset_contexts() {
+        let mut config = Config::from_toml(
+            r#"
+presets = ["dbapi"]
+
+[[overrides]]
+path = "legacy/**"
+function_contexts = ["run_sql"]
+"#,
+        )
+        .unwrap();
+        assert!(config.expand_presets());
+
+        let legacy = config.for_path("legacy/db.py").unwrap();
+        assert_eq!(
+            legacy.function_contexts,
+            vec![
+                "run_sql",
+                "cursor.execute:0",
+                "cursor.executemany:0",
+                "cursor.executescript"
+            ]
+        );
+        assert_eq!(legacy.keyword_contexts, vec!["operation"]);
+    }
+
+    #[test]
+    fn unknown_preset() {
+        let mut config = Config {
+            presets: vec!["dbapi".to_string(), "peewee".to_string()],
+            ..Config::default()
+        };
+        assert!(!config.expand_presets());
+        assert!(
+            config
+                .function_contexts
+                .contains(&"cursor.execute:0".to_string())
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::fs;
+use std::ops::ControlFlow;
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde::Deserialize;
+use sqlparser::ast::{ObjectNamePart, Statement, visit_relations};
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// Layout of a custom rule file, one `[[rule]]` table per rule.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<RuleDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    code: String,
+    severity: Severity,
+    message: String,
+    /// Matched against the SQL with whitespace collapsed.
+    regex: Option<String>,
+    /// Statement kind, e.g. "select" or "insert".
+    statement: Option<String>,
+    /// Glob over the names of the tables the statement touches.
+    table: Option<String>,
+    require_token: Option<String>,
+    forbid_token: Option<String>,
+}
+
+const STATEMENT_KINDS: &[&str] = &[
+    "select", "insert", "update", "delete", "merge", "create", "alter", "drop", "truncate",
+];
+
+enum Matcher {
+    Regex(Regex),
+    Structural {
+        statement: Option<String>,
+        table: Option<GlobMatcher>,
+        require_token: Option<String>,
+        forbid_token: Option<String>,
+    },
+}
+
+pub struct CustomRule {
+    code: String,
+    severity: Severity,
+    message: String,
+    matcher: Matcher,
+}
+
+impl Rule for CustomRule {
+    fn code(&self) -> &str {
+        &self.code
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let normalized = normalize(&ctx.sql.sql_content);
+        let matched = match &self.matcher {
+            Matcher::Regex(re) => re.is_match(&normalized),
+            Matcher::Structural {
+                statement,
+                table,
+                require_token,
+                forbid_token,
+            } => {
+                // Structural rules need the AST, strings that don't parse are reported elsewhere
+                let Some(statements) = ctx.statements else {
+                    return vec![];
+                };
+                let lowered = normalized.to_lowercase();
+                statements.iter().any(|stmt| {
+                    statement
+                        .as_ref()
+                        .is_none_or(|kind| statement_kind(stmt) == Some(kind.as_str()))
+                        && table
+                            .as_ref()
+                            .is_none_or(|glob| table_names(stmt).iter().any(|t| glob.is_match(t)))
+                        && require_token
+                            .as_ref()
+                            .is_none_or(|token| !lowered.contains(token.as_str()))
+                        && forbid_token
+                            .as_ref()
+                            .is_none_or(|token| lowered.contains(token.as_str()))
+                })
+            }
+        };
+
+        if matched {
+            vec![self.message.clone().into()]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Loads every rule file, failing with one message per invalid definition.
+pub fn load_custom_rules(
+    paths: &[String],
+    taken_codes: &[&str],
+) -> Result<Vec<CustomRule>, Vec<String>> {
+    let mut rules: Vec<CustomRule> = vec![];
+    let mut errors = vec![];
+
+    for path in paths {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                errors.push(format!("{path}: failed to read custom rule file: {e}"));
+                continue;
+            }
+        };
+        match parse_custom_rules(&content, path) {
+            Ok(parsed) => rules.extend(parsed),
+            Err(e) => errors.extend(e),
+        }
+    }
+
+    let mut seen: Vec<&str> = taken_codes.to_vec();
+    for rule in &rules {
+        if seen.contains(&rule.code.as_str()) {
+            errors.push(format!(
+                "rule code `{}` is defined more than once",
+                rule.code
+            ));
+        }
+        seen.push(&rule.code);
+    }
+
+    if errors.is_empty() {
+        Ok(rules)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn parse_custom_rules(content: &str, source: &str) -> Result<Vec<CustomRule>, Vec<String>> {
+    let file: RuleFile = toml::from_str(content).map_err(|e| vec![format!("{source}: {e}")])?;
+
+    let mut rules = vec![];
+    let mut errors = vec![];
+    for (idx, def) in file.rule.into_iter().enumerate() {
+        let ctx = format!("{source}: rule #{} (`{}`)", idx + 1, def.code);
+        match build_rule(def) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => errors.push(format!("{ctx}: {e}")),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rules)
+    } else {
+        Err(errors)
+    }
+}
+
+fn build_rule(def: RuleDefinition) -> Result<CustomRule, String> {
+    if def.code.trim().is_empty() {
+        return Err("`code` must not be empty".to_string());
+    }
+    let structural = def.statement.is_some()
+        || def.table.is_some()
+        || def.require_token.is_some()
+        || def.forbid_token.is_some();
+
+    let matcher = match def.regex {
+        Some(_) if structural => {
+            return Err(
+                "`regex` can't be combined with `statement`, `table`, `require_token` or `forbid_token`"
+                    .to_string(),
+            );
+        }
+        Some(pattern) => {
+            Matcher::Regex(Regex::new(&pattern).map_err(|e| format!("invalid `regex`: {e}"))?)
+        }
+        None if !structural => {
+            return Err(
+                "needs either `regex` or at least one of `statement`, `table`, `require_token`, `forbid_token`"
+                    .to_string(),
+            );
+        }
+        None => {
+            let statement = def.statement.map(|s| s.to_lowercase());
+            if let Some(kind) = &statement
+                && !STATEMENT_KINDS.contains(&kind.as_str())
+            {
+                return Err(format!(
+                    "unknown `statement` kind `{kind}`, expected one of {STATEMENT_KINDS:?}"
+                ));
+            }
+            let table = def
+                .table
+                .map(|t| Glob::new(&t.to_lowercase()).map(|g| g.compile_matcher()))
+                .transpose()
+                .map_err(|e| format!("invalid `table` glob: {e}"))?;
+
+            Matcher::Structural {
+                statement,
+                table,
+                require_token: def.require_token.map(|t| normalize(&t).to_lowercase()),
+                forbid_token: def.forbid_token.map(|t| normalize(&t).to_lowercase()),
+            }
+        }
+    };
+
+    Ok(CustomRule {
+        code: def.code,
+        severity: def.severity,
+        message: def.message,
+        matcher,
+    })
+}
+
+fn normalize(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+const fn statement_kind(stmt: &Statement) -> Option<&'static str> {
+    match stmt {
+        Statement::Query(_) => Some("select"),
+        Statement::Insert(_) => Some("insert"),
+        Statement::Update { .. } => Some("update"),
+        Statement::Delete(_) => Some("delete"),
+        Statement::Merge { .. } => Some("merge"),
+        Statement::CreateTable(_) | Statement::CreateView { .. } | Statement::CreateIndex(_) => {
+            Some("create")
+        }
+        Statement::AlterTable { .. } => Some("alter"),
+        Statement::Drop { .. } => Some("drop"),
+        Statement::Truncate { .. } => Some("truncate"),
+        _ => None,
+    }
+}
+
+fn table_names(stmt: &Statement) -> Vec<String> {
+    let mut names = vec![];
+    let _ = visit_relations(stmt, |name| {
+        if let Some(ObjectNamePart::Identifier(ident)) = name.0.last() {
+            names.push(ident.value.to_lowercase());
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_utils::{codes_for_rules, finder_config};
+
+    fn codes_for(rules: &str, src: &str) -> Vec<String> {
+        let rules = parse_custom_rules(rules, "rules.toml")
+            .unwrap()
+            .into_iter()
+            .map(|r| Box::new(r) as Box<dyn Rule>)
+            .collect();
+        codes_for_rules(finder_config(), rules, src)
+    }
+
+    #[test]
+    fn regex_rule() {
+        let rules = r#"
+[[rule]]
+code = "X001"
+severity = "warning"
+message = "Don't select from the legacy schema"
+regex = "(?i)FROM legacy\\."
+"#;
+        assert_eq!(
+            codes_for(rules, r#"query = "SELECT *  FROM   legacy.users""#),
+            vec!["X001"]
+        );
+        assert!(codes_for(rules, r#"query = "SELECT * FROM users""#).is_empty());
+    }
+
+    #[test]
+    fn structural_rule() {
+        let rules = r#"
+[[rule]]
+code = "X002"
+severity = "error"
+message = "Selects against PII tables must be audited"
+statement = "select"
+table = "pii_*"
+require_token = "/* audited */"
+"#;
+        assert_eq!(
+            codes_for(rules, r#"query = "SELECT email FROM pii_contacts""#),
+            vec!["X002"]
+        );
+        assert!(
+            codes_for(
+                rules,
+                r#"query = "SELECT email FROM pii_contacts /* audited */""#
+            )
+            .is_empty()
+        );
+        assert!(codes_for(rules, r#"query = "DELETE FROM pii_contacts WHERE id = 1""#).is_empty());
+        assert!(codes_for(rules, r#"query = "SELECT email FROM contacts""#).is_empty());
+    }
+
+    #[test]
+    fn invalid_definition_rejected() {
+        let rules = r#"
+[[rule]]
+code = "X003"
+severity = "warning"
+message = "no matcher"
+
+[[rule]]
+code = "X004"
+severity = "warning"
+message = "bad regex"
+regex = "(unclosed"
+
+[[rule]]
+code = "X005"
+severity = "warning"
+message = "bad kind"
+statement = "upsert"
+"#;
+        let errors = parse_custom_rules(rules, "rules.toml").err().unwrap();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("rules.toml: rule #1 (`X003`): needs either `regex`"));
+        assert!(errors[1].starts_with("rules.toml: rule #2 (`X004`): invalid `regex`"));
+        assert!(
+            errors[2]
+                .starts_with("rules.toml: rule #3 (`X005`): unknown `statement` kind `upsert`")
+        );
+    }
+}
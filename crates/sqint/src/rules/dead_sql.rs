@@ -0,0 +1,75 @@
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// Module level SQL that nothing in the module reads anymore. Only reported when dead SQL
+/// detection is enabled, since the finder doesn't mark anything otherwise.
+pub struct DeadSql;
+
+impl Rule for DeadSql {
+    fn code(&self) -> &str {
+        "SQ401"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        if ctx.sql.unreferenced {
+            vec![
+                format!(
+                    "`{}` is never used in this module, this may be dead SQL",
+                    ctx.sql.variable_name
+                )
+                .into(),
+            ]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::test_utils::{codes_for_config, finder_config};
+
+    fn codes_for(src: &str) -> Vec<String> {
+        codes_for_config(finder_config().with_dead_sql_detection(true), src)
+    }
+
+    #[test]
+    fn unused_query_variable() {
+        let src = r#"old_report_sql = "SELECT * FROM reports""#;
+        assert_eq!(codes_for(src), vec!["SQ401"]);
+    }
+
+    #[test]
+    fn query_passed_to_execute() {
+        let src = r#"
+report_sql = "SELECT * FROM reports"
+
+def run(cursor):
+    cursor.execute(report_sql)
+"#;
+        assert!(codes_for(src).is_empty());
+    }
+
+    #[test]
+    fn query_passed_as_keyword() {
+        let src = r#"
+report_sql = "SELECT * FROM reports"
+
+def run(session):
+    session.run(statement=report_sql)
+"#;
+        assert!(codes_for(src).is_empty());
+    }
+
+    #[test]
+    fn query_listed_in_all() {
+        let src = r#"
+__all__ = ["report_sql"]
+report_sql = "SELECT * FROM reports"
+"#;
+        assert!(codes_for(src).is_empty());
+    }
+}
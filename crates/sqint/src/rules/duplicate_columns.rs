@@ -0,0 +1,196 @@
+use sqlparser::ast::{Expr, Ident, SelectItem, SetExpr, Statement};
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::Token;
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// Result columns of a `SELECT` sharing a name, `SELECT u.id, o.id ...`. Rows read as dicts or
+/// by column name silently keep only one of them. Expressions without an alias and wildcards
+/// have no name known up front and aren't compared.
+pub struct DuplicateColumns {
+    severity: Severity,
+}
+
+impl DuplicateColumns {
+    pub const fn new(severity: Severity) -> Self {
+        Self { severity }
+    }
+}
+
+impl Rule for DuplicateColumns {
+    fn code(&self) -> &str {
+        "SQ204"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let Some(statements) = ctx.statements else {
+            return vec![];
+        };
+        let statement_tokens = ctx.statement_tokens();
+
+        let mut findings = vec![];
+        for (i, statement) in statements.iter().enumerate() {
+            let Statement::Query(query) = statement else {
+                continue;
+            };
+            let Some(projection) = first_select(&query.body) else {
+                continue;
+            };
+            let tokens = statement_tokens.as_ref().and_then(|s| s.get(i));
+
+            let names: Vec<Option<String>> = projection.iter().map(output_name).collect();
+            for (column, name) in names.iter().enumerate() {
+                let Some(name) = name else {
+                    continue;
+                };
+                let Some(first) = names[..column]
+                    .iter()
+                    .position(|n| n.as_ref() == Some(name))
+                else {
+                    continue;
+                };
+                let message = format!(
+                    "Duplicate output column `{name}`, also selected as column {}",
+                    first + 1
+                );
+                let offset = tokens.and_then(|tokens| select_item_start(tokens, column));
+                findings.push(match offset {
+                    Some(offset) => Finding::at(message, offset),
+                    None => message.into(),
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Select list of the query, or of the first `SELECT` of a union, which names its columns.
+fn first_select(body: &SetExpr) -> Option<&[SelectItem]> {
+    match body {
+        SetExpr::Select(select) => Some(&select.projection),
+        SetExpr::SetOperation { left, .. } => first_select(left),
+        SetExpr::Query(query) => first_select(&query.body),
+        _ => None,
+    }
+}
+
+/// Name of the result column of `item`, lowercased unless it's quoted as databases fold it.
+fn output_name(item: &SelectItem) -> Option<String> {
+    let ident = match item {
+        SelectItem::ExprWithAlias { alias, .. } => alias,
+        SelectItem::UnnamedExpr(Expr::Identifier(ident)) => ident,
+        SelectItem::UnnamedExpr(Expr::CompoundIdentifier(idents)) => idents.last()?,
+        _ => return None,
+    };
+    Some(normalized(ident))
+}
+
+fn normalized(ident: &Ident) -> String {
+    if ident.quote_style.is_some() {
+        ident.value.clone()
+    } else {
+        ident.value.to_lowercase()
+    }
+}
+
+/// Byte offset of the `index`th item of the first `SELECT` list at the top level of a
+/// statement, by the commas between them. `None` when that `SELECT` is parenthesized.
+fn select_item_start(tokens: &[(Token, usize)], index: usize) -> Option<usize> {
+    let is_keyword =
+        |token: &Token, keyword: Keyword| matches!(token, Token::Word(w) if w.keyword == keyword);
+    let mut depth = 0_usize;
+    let mut select = None;
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            _ if depth == 0 && is_keyword(token, Keyword::SELECT) => {
+                select = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let items = tokens[select? + 1..].iter().skip_while(|(token, _)| {
+        is_keyword(token, Keyword::DISTINCT) || is_keyword(token, Keyword::ALL)
+    });
+    let mut item = 0;
+    for (token, offset) in items {
+        match token {
+            Token::Comma if depth == 0 => {
+                item += 1;
+                continue;
+            }
+            _ if depth == 0 && is_keyword(token, Keyword::FROM) => return None,
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if item == index {
+            return Some(*offset);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Diagnostic;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    fn diagnostics(src: &str) -> Vec<Diagnostic> {
+        diagnostics_for(
+            finder_config(),
+            vec![Box::new(DuplicateColumns::new(Severity::Warning))],
+            src,
+        )
+    }
+
+    #[test]
+    fn duplicate_aliases() {
+        let src = "query = \"SELECT u.id AS user_id, u.name AS name, o.label AS name FROM users u JOIN orgs o ON o.id = u.org_id\"\n";
+        let found = diagnostics(src);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].message,
+            "Duplicate output column `name`, also selected as column 2"
+        );
+        // At the second occurrence, `o.label AS name`
+        assert_eq!(found[0].range.start.to_string(), "1:50:");
+    }
+
+    #[test]
+    fn same_column_of_different_tables() {
+        let src = "query = \"\"\"\n    SELECT u.id, o.id, count(*)\n    FROM users u JOIN orgs o ON o.id = u.org_id\n\"\"\"\n";
+        let found = diagnostics(src);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].message,
+            "Duplicate output column `id`, also selected as column 1"
+        );
+        assert_eq!(found[0].range.start.to_string(), "2:18:");
+
+        // Names of a union come from its first SELECT
+        let src = r#"query = "SELECT id, ID FROM a UNION SELECT x, y FROM b""#;
+        assert_eq!(diagnostics(src).len(), 1);
+    }
+
+    #[test]
+    fn distinct_names_and_wildcards() {
+        for src in [
+            r#"query = "SELECT u.id, o.id AS org_id, u.name FROM users u JOIN orgs o ON o.id = u.org_id""#,
+            r#"query = "SELECT u.*, o.* FROM users u JOIN orgs o ON o.id = u.org_id""#,
+            r#"query = "SELECT count(*), count(*) FROM users""#,
+            r#"query = "SELECT \"Id\", \"id\" FROM users""#,
+            r#"query = "SELECT id FROM (SELECT id, id FROM users) AS u""#,
+        ] {
+            assert!(diagnostics(src).is_empty(), "{src}");
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::Token;
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// `IN (...)` lists filled from runtime values, typically `",".join(ids)`. Besides the injection
+/// risk, every distinct list length produces a new statement text for the database to plan.
+pub struct InClauseJoin;
+
+impl Rule for InClauseJoin {
+    fn code(&self) -> &str {
+        "SQ302"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        if ctx.sql.dynamic_spans.is_empty() {
+            return vec![];
+        }
+        let Some(tokens) = ctx.tokens() else {
+            return vec![];
+        };
+
+        in_list_bodies(&tokens)
+            .into_iter()
+            .filter_map(|body| {
+                ctx.sql
+                    .dynamic_spans
+                    .iter()
+                    .find(|span| span.start >= body.start && span.end <= body.end)
+            })
+            .map(|span| {
+                let source = if span.joined {
+                    "built with str.join()"
+                } else {
+                    "filled from a runtime value"
+                };
+                format!(
+                    "IN list is {source}; pass the values as parameters instead (expanding bind params or executemany)"
+                )
+                .into()
+            })
+            .collect()
+    }
+}
+
+/// Byte ranges between the parentheses of `IN (...)` predicates, subqueries excluded.
+fn in_list_bodies(tokens: &[(Token, usize)]) -> Vec<Range<usize>> {
+    let is_keyword = |i: usize, kw: Keyword| matches!(tokens.get(i), Some((Token::Word(w), _)) if w.keyword == kw);
+
+    let mut bodies = vec![];
+    for i in 0..tokens.len() {
+        if !is_keyword(i, Keyword::IN) || !matches!(tokens.get(i + 1), Some((Token::LParen, _))) {
+            continue;
+        }
+        if is_keyword(i + 2, Keyword::SELECT) || is_keyword(i + 2, Keyword::WITH) {
+            continue;
+        }
+
+        let open = tokens[i + 1].1;
+        let mut depth = 0usize;
+        for (token, offset) in &tokens[i + 1..] {
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        bodies.push(open + 1..*offset);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    bodies
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::test_utils::codes_for;
+
+    #[test]
+    fn percent_join_in_list() {
+        let src = r#"query = "SELECT * FROM t WHERE id IN (%s)" % ",".join(ids)"#;
+        assert_eq!(codes_for(src), vec!["SQ302"]);
+    }
+
+    #[test]
+    fn fstring_join_in_list() {
+        let src = r#"query = f"SELECT * FROM t WHERE id IN ({', '.join(map(str, ids))})""#;
+        assert_eq!(codes_for(src), vec!["SQ302"]);
+    }
+
+    #[test]
+    fn literal_in_list() {
+        let src = r#"query = "SELECT * FROM t WHERE id IN (1, 2, 3)""#;
+        assert!(codes_for(src).is_empty());
+    }
+
+    #[test]
+    fn parameterized_in_list() {
+        let src = r#"execute("SELECT * FROM t WHERE id IN (?)", (ids,))"#;
+        assert!(codes_for(src).is_empty());
+    }
+}
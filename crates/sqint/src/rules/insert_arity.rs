@@ -0,0 +1,131 @@
+use sqlparser::ast::{Insert, SetExpr, Statement};
+
+use super::{Finding, Rule, RuleContext, Severity, plural};
+
+/// `VALUES` rows of an `INSERT` with more or fewer values than the statement has columns,
+/// which parses but fails once the database sees it. Without a column list the rows are
+/// compared to the first one.
+pub struct InsertArity;
+
+impl Rule for InsertArity {
+    fn code(&self) -> &str {
+        "SQ203"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let Some(statements) = ctx.statements else {
+            return vec![];
+        };
+        let starts = ctx.statement_starts();
+
+        let mut findings = vec![];
+        for (i, statement) in statements.iter().enumerate() {
+            let Statement::Insert(insert) = statement else {
+                continue;
+            };
+            for message in mismatches(insert) {
+                findings.push(match starts.as_ref().and_then(|starts| starts.get(i)) {
+                    Some(&offset) => Finding::at(message, offset),
+                    None => message.into(),
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// One message per `VALUES` row that doesn't fit, `INSERT ... SELECT` is left alone.
+fn mismatches(insert: &Insert) -> Vec<String> {
+    let Some(SetExpr::Values(values)) = insert.source.as_ref().map(|source| source.body.as_ref())
+    else {
+        return vec![];
+    };
+    let Some(first) = values.rows.first() else {
+        return vec![];
+    };
+
+    let columns = insert.columns.len();
+    values
+        .rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| {
+            if columns > 0 {
+                (row.len() != columns).then(|| {
+                    format!(
+                        "Row {} has {} for {}",
+                        i + 1,
+                        plural(row.len(), "value"),
+                        plural(columns, "column")
+                    )
+                })
+            } else {
+                (row.len() != first.len()).then(|| {
+                    format!(
+                        "Row {} has {} but row 1 has {}",
+                        i + 1,
+                        plural(row.len(), "value"),
+                        first.len()
+                    )
+                })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::builtin_rules;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    fn messages(src: &str) -> Vec<String> {
+        diagnostics_for(finder_config(), builtin_rules(), src)
+            .into_iter()
+            .filter(|d| d.code == "SQ203")
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn matching_counts() {
+        for src in [
+            r#"query = "INSERT INTO users (id, name, email) VALUES (%s, %s, %s)""#,
+            r#"query = "INSERT INTO users (id, name) VALUES (1, 'a'), (2, 'b')""#,
+            r#"query = "INSERT INTO users VALUES (1, 'a'), (2, 'b')""#,
+        ] {
+            assert!(messages(src).is_empty(), "{src}");
+        }
+    }
+
+    #[test]
+    fn short_row() {
+        assert_eq!(
+            messages(r#"query = "INSERT INTO users (id, name, email) VALUES (%s, %s)""#),
+            vec!["Row 1 has 2 values for 3 columns"]
+        );
+        assert_eq!(
+            messages(
+                r#"query = "INSERT INTO users (id, name) VALUES (1, 'a'), (2), (3, 'c', 'x')""#
+            ),
+            vec![
+                "Row 2 has 1 value for 2 columns",
+                "Row 3 has 3 values for 2 columns"
+            ]
+        );
+        // Without a column list the rows still have to agree with each other
+        assert_eq!(
+            messages(r#"query = "INSERT INTO users VALUES (1, 'a'), (2, 'b', 'c')""#),
+            vec!["Row 2 has 3 values but row 1 has 2"]
+        );
+    }
+
+    #[test]
+    fn insert_select_skipped() {
+        let src = r#"query = "INSERT INTO archive (id, name, email) SELECT id, name FROM users""#;
+        assert!(messages(src).is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use finder::Interpolation;
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// Keywords after which an interpolated value names a table or column rather than being one,
+/// `FROM {table}` or `ORDER BY {column}`
+const IDENTIFIER_KEYWORDS: &[&str] = &["FROM", "JOIN", "INTO", "UPDATE", "TABLE", "BY"];
+
+/// Runtime values formatted or concatenated into SQL that goes straight to an execution
+/// function, `cursor.execute(f"... WHERE name = '{name}'")`. Interpolated table and column
+/// names are left alone, they can't be passed as parameters.
+pub struct InterpolatedSql;
+
+impl Rule for InterpolatedSql {
+    fn code(&self) -> &str {
+        "SQ301"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let Some(interpolation) = ctx.sql.interpolation else {
+            return vec![];
+        };
+        let sql = &ctx.sql.sql_content;
+        let is_identifier = |start: usize, end: usize| {
+            let before = sql[..start].trim_end();
+            before.ends_with('.')
+                || sql[end..].starts_with('.')
+                || before
+                    .rsplit(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next()
+                    .is_some_and(|word| {
+                        IDENTIFIER_KEYWORDS
+                            .iter()
+                            .any(|k| word.eq_ignore_ascii_case(k))
+                    })
+        };
+        if ctx
+            .sql
+            .dynamic_spans
+            .iter()
+            .all(|span| is_identifier(span.start, span.end))
+        {
+            return vec![];
+        }
+
+        let how = match interpolation {
+            Interpolation::Fstring => "An f-string",
+            Interpolation::Format => "str.format()",
+            Interpolation::Percent => "% formatting",
+            Interpolation::Concat => "Concatenation",
+        };
+        vec![
+            format!("{how} puts runtime values into executed SQL, pass them as parameters instead")
+                .into(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::test_utils::{codes_for_config, finder_config};
+
+    fn codes(src: &str) -> Vec<String> {
+        codes_for_config(finder_config().with_interpolation_detection(true), src)
+    }
+
+    #[test]
+    fn interpolated_values() {
+        for src in [
+            r#"cursor.execute(f"SELECT * FROM users WHERE name = '{name}'")"#,
+            r#"cursor.execute("SELECT * FROM users WHERE name = '{}'".format(name))"#,
+            r#"cursor.execute("SELECT * FROM users WHERE id = %s" % user_id)"#,
+            r#"cursor.execute("SELECT * FROM users WHERE name = " + user_input)"#,
+            r#"cursor.execute(text(f"SELECT * FROM users WHERE id = {user_id}"))"#,
+        ] {
+            assert_eq!(codes(src), vec!["SQ301"], "{src}");
+        }
+    }
+
+    #[test]
+    fn constants_and_identifiers_allowed() {
+        for src in [
+            // Parameters, the driver fills them in
+            r#"cursor.execute("SELECT * FROM users WHERE id = %s", (user_id,))"#,
+            // Module constants are resolved, nothing runtime goes in
+            "TABLE = 'users'\ncursor.execute(f\"SELECT * FROM {TABLE}\")",
+            r#"cursor.execute(f"SELECT * FROM {schema}.users ORDER BY {column}")"#,
+            r#"cursor.execute(f"DROP TABLE {table}")"#,
+        ] {
+            assert!(codes(src).is_empty(), "{src}");
+        }
+        // Off unless enabled
+        let src = r#"cursor.execute(f"SELECT * FROM users WHERE id = {user_id}")"#;
+        assert!(codes_for_config(finder_config(), src).is_empty());
+    }
+
+    #[test]
+    fn suppressed_by_pragma() {
+        let src = "cursor.execute(f\"ALTER TABLE users ADD COLUMN {name} {kind}\")  # sqint: ignore[SQ301]\n";
+        assert!(codes(src).is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::Token;
+
+use super::{Finding, Fix, Rule, RuleContext, Severity};
+use crate::config::KeywordCase;
+
+/// Keywords checked for their case. Ones that commonly name columns, like `name`, `key` or
+/// `type`, are left out since a lowercase identifier is no style issue.
+const KEYWORDS: &[Keyword] = &[
+    Keyword::ALL,
+    Keyword::ALTER,
+    Keyword::AND,
+    Keyword::ANY,
+    Keyword::AS,
+    Keyword::ASC,
+    Keyword::BETWEEN,
+    Keyword::BY,
+    Keyword::CASE,
+    Keyword::CAST,
+    Keyword::CONFLICT,
+    Keyword::CONSTRAINT,
+    Keyword::CREATE,
+    Keyword::CROSS,
+    Keyword::DEFAULT,
+    Keyword::DELETE,
+    Keyword::DESC,
+    Keyword::DISTINCT,
+    Keyword::DO,
+    Keyword::DROP,
+    Keyword::ELSE,
+    Keyword::END,
+    Keyword::EXCEPT,
+    Keyword::EXISTS,
+    Keyword::FALSE,
+    Keyword::FETCH,
+    Keyword::FOREIGN,
+    Keyword::FROM,
+    Keyword::FULL,
+    Keyword::GROUP,
+    Keyword::HAVING,
+    Keyword::IF,
+    Keyword::ILIKE,
+    Keyword::IN,
+    Keyword::INDEX,
+    Keyword::INNER,
+    Keyword::INSERT,
+    Keyword::INTERSECT,
+    Keyword::INTO,
+    Keyword::IS,
+    Keyword::JOIN,
+    Keyword::LEFT,
+    Keyword::LIKE,
+    Keyword::LIMIT,
+    Keyword::MERGE,
+    Keyword::NATURAL,
+    Keyword::NOT,
+    Keyword::NOTHING,
+    Keyword::NULL,
+    Keyword::OFFSET,
+    Keyword::ON,
+    Keyword::OR,
+    Keyword::ORDER,
+    Keyword::OUTER,
+    Keyword::OVER,
+    Keyword::PARTITION,
+    Keyword::PRIMARY,
+    Keyword::RECURSIVE,
+    Keyword::REFERENCES,
+    Keyword::RETURNING,
+    Keyword::RIGHT,
+    Keyword::SELECT,
+    Keyword::SET,
+    Keyword::TABLE,
+    Keyword::THEN,
+    Keyword::TRUE,
+    Keyword::TRUNCATE,
+    Keyword::UNION,
+    Keyword::UNIQUE,
+    Keyword::UPDATE,
+    Keyword::USING,
+    Keyword::VALUES,
+    Keyword::VIEW,
+    Keyword::WHEN,
+    Keyword::WHERE,
+    Keyword::WITH,
+];
+
+/// Keywords not written in the configured case, each with a fix rewriting it. Strings that
+/// don't parse are checked as well, the tokenizer doesn't need them to.
+pub struct KeywordCasing {
+    case: KeywordCase,
+}
+
+impl KeywordCasing {
+    pub const fn new(case: KeywordCase) -> Self {
+        Self { case }
+    }
+}
+
+impl Rule for KeywordCasing {
+    fn code(&self) -> &str {
+        "SQ501"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let convert: fn(&str) -> String = match self.case {
+            KeywordCase::Upper => str::to_ascii_uppercase,
+            KeywordCase::Lower => str::to_ascii_lowercase,
+            KeywordCase::Off => return vec![],
+        };
+        let Some(tokens) = ctx.tokens() else {
+            return vec![];
+        };
+        let is_period =
+            |i: Option<usize>| matches!(i.and_then(|i| tokens.get(i)), Some((Token::Period, _)));
+
+        let mut findings = vec![];
+        for (i, (token, offset)) in tokens.iter().enumerate() {
+            let Token::Word(word) = token else {
+                continue;
+            };
+            // `t.order` is a column, whatever its name
+            if word.quote_style.is_some()
+                || !KEYWORDS.contains(&word.keyword)
+                || is_period(i.checked_sub(1))
+                || is_period(Some(i + 1))
+            {
+                continue;
+            }
+            let expected = convert(&word.value);
+            if expected == word.value {
+                continue;
+            }
+            findings.push(
+                Finding::at(
+                    format!("Keyword `{}` should be `{expected}`", word.value),
+                    *offset,
+                )
+                .with_fix(Fix {
+                    start: *offset,
+                    end: offset + word.value.len(),
+                    replacement: expected,
+                }),
+            );
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Diagnostic;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    fn diagnostics(case: KeywordCase, src: &str) -> Vec<Diagnostic> {
+        diagnostics_for(
+            finder_config(),
+            vec![Box::new(KeywordCasing::new(case))],
+            src,
+        )
+    }
+
+    /// `src` with every fix applied.
+    fn fixed(src: &str, diagnostics: &[Diagnostic]) -> String {
+        let mut fixed = src.to_string();
+        let mut fixes: Vec<&Fix> = diagnostics.iter().filter_map(|d| d.fix.as_ref()).collect();
+        fixes.sort_by_key(|fix| std::cmp::Reverse(fix.start));
+        for fix in fixes {
+            fixed.replace_range(fix.start..fix.end, &fix.replacement);
+        }
+        fixed
+    }
+
+    #[test]
+    fn mixed_case_across_lines() {
+        let src = "query = \"\"\"\n    Select id, name\n    from users u\n    WHERE u.active = true\n      and u.\"order\" > 1\n    order by name\n\"\"\"\n";
+        let found = diagnostics(KeywordCase::Upper, src);
+
+        let positions: Vec<String> = found
+            .iter()
+            .map(|d| format!("{} {}", d.range.start, d.message))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                "2:5: Keyword `Select` should be `SELECT`",
+                "3:5: Keyword `from` should be `FROM`",
+                "4:22: Keyword `true` should be `TRUE`",
+                "5:7: Keyword `and` should be `AND`",
+                "6:5: Keyword `order` should be `ORDER`",
+                "6:11: Keyword `by` should be `BY`",
+            ]
+        );
+        assert_eq!(
+            fixed(src, &found),
+            "query = \"\"\"\n    SELECT id, name\n    FROM users u\n    WHERE u.active = TRUE\n      AND u.\"order\" > 1\n    ORDER BY name\n\"\"\"\n"
+        );
+        assert!(diagnostics(KeywordCase::Upper, &fixed(src, &found)).is_empty());
+    }
+
+    #[test]
+    fn lower_and_off() {
+        let src = r#"query = "SELECT id FROM users""#;
+        assert_eq!(
+            fixed(src, &diagnostics(KeywordCase::Lower, src)),
+            r#"query = "select id from users""#
+        );
+        assert!(diagnostics(KeywordCase::Off, src).is_empty());
+    }
+
+    #[test]
+    fn raw_strings_and_fstrings_not_fixed() {
+        for src in [
+            r#"query = r"select id FROM users WHERE name ~ '\d+'""#,
+            r#"cursor.execute(f"select id FROM {table} WHERE id = 1")"#,
+        ] {
+            let found = diagnostics(KeywordCase::Upper, src);
+            assert_eq!(found.len(), 1, "{src}");
+            assert!(found[0].fix.is_none(), "{src}");
+        }
+    }
+
+    #[test]
+    fn fallback_strings_not_fixed() {
+        let src = "query = \"select id FROM users\"\ndef broken(:\n";
+        let found = diagnostics(KeywordCase::Upper, src);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].approximate);
+        assert!(found[0].fix.is_none());
+        // Reported at the string rather than a guessed keyword position
+        assert_eq!(found[0].range.start.to_string(), "1:9:");
+    }
+}
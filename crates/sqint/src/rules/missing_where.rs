@@ -0,0 +1,174 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use sqlparser::ast::{
+    FromTable, ObjectName, ObjectNamePart, Statement, TableFactor, TableWithJoins,
+};
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// `DELETE` and `UPDATE` statements without a `WHERE` clause, which touch every row of their
+/// table. Often a condition that was meant to be appended later and never was.
+pub struct MissingWhere {
+    allowed_tables: GlobSet,
+    severity: Severity,
+}
+
+impl MissingWhere {
+    /// Tables matching one of the `allowed_tables` globs, like scratch tables emptied on
+    /// purpose, aren't reported.
+    pub fn new(allowed_tables: &[String], severity: Severity) -> Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        for table in allowed_tables {
+            let glob = Glob::new(&table.to_lowercase()).map_err(|e| {
+                format!("invalid glob `{table}` in `missing_where_allowed_tables`: {e}")
+            })?;
+            builder.add(glob);
+        }
+        Ok(Self {
+            allowed_tables: builder.build().map_err(|e| e.to_string())?,
+            severity,
+        })
+    }
+}
+
+impl Default for MissingWhere {
+    fn default() -> Self {
+        Self {
+            allowed_tables: GlobSet::empty(),
+            severity: Severity::Error,
+        }
+    }
+}
+
+impl Rule for MissingWhere {
+    fn code(&self) -> &str {
+        "SQ201"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let Some(statements) = ctx.statements else {
+            return vec![];
+        };
+        let starts = ctx.statement_starts();
+
+        statements
+            .iter()
+            .enumerate()
+            .filter_map(|(i, statement)| {
+                let (verb, tables) = match statement {
+                    Statement::Update {
+                        table,
+                        selection: None,
+                        ..
+                    } => ("UPDATE", relation_names(std::slice::from_ref(table))),
+                    Statement::Delete(delete) if delete.selection.is_none() => {
+                        let tables = if delete.tables.is_empty() {
+                            let (FromTable::WithFromKeyword(from)
+                            | FromTable::WithoutKeyword(from)) = &delete.from;
+                            relation_names(from)
+                        } else {
+                            delete.tables.iter().filter_map(table_name).collect()
+                        };
+                        ("DELETE", tables)
+                    }
+                    _ => return None,
+                };
+                if !tables.is_empty() && tables.iter().all(|t| self.allowed_tables.is_match(t)) {
+                    return None;
+                }
+
+                let message = format!(
+                    "{verb} of `{}` without a WHERE clause affects every row",
+                    tables.join("`, `")
+                );
+                Some(match starts.as_ref().and_then(|starts| starts.get(i)) {
+                    Some(&offset) => Finding::at(message, offset),
+                    None => message.into(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn relation_names(tables: &[TableWithJoins]) -> Vec<String> {
+    tables
+        .iter()
+        .filter_map(|t| match &t.relation {
+            TableFactor::Table { name, .. } => table_name(name),
+            _ => None,
+        })
+        .collect()
+}
+
+fn table_name(name: &ObjectName) -> Option<String> {
+    let Some(ObjectNamePart::Identifier(ident)) = name.0.last() else {
+        return None;
+    };
+    Some(ident.value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    fn diagnostics(rule: MissingWhere, src: &str) -> Vec<crate::rules::Diagnostic> {
+        diagnostics_for(finder_config(), vec![Box::new(rule)], src)
+    }
+
+    #[test]
+    fn update_without_where() {
+        let found = diagnostics(
+            MissingWhere::default(),
+            r#"query = "UPDATE users SET active = false""#,
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].code, "SQ201");
+        assert_eq!(found[0].severity, Severity::Error);
+        assert_eq!(
+            found[0].message,
+            "UPDATE of `users` without a WHERE clause affects every row"
+        );
+    }
+
+    #[test]
+    fn delete_with_where_and_truncate() {
+        for src in [
+            r#"query = "DELETE FROM sessions WHERE expires_at < now()""#,
+            r#"query = "UPDATE users SET active = false WHERE id = %s""#,
+            // Emptying a table on purpose is spelled out, nothing to report
+            r#"query = "TRUNCATE TABLE sessions""#,
+        ] {
+            assert!(
+                diagnostics(MissingWhere::default(), src).is_empty(),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn allowed_tables_and_severity() {
+        let rule = MissingWhere::new(&["tmp_*".to_string()], Severity::Warning).unwrap();
+        let src = "query = \"DELETE FROM tmp_import\"\nother_query = \"DELETE FROM Sessions\"\n";
+        let found = diagnostics(rule, src);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Warning);
+        assert_eq!(
+            found[0].message,
+            "DELETE of `sessions` without a WHERE clause affects every row"
+        );
+        assert!(MissingWhere::new(&["tmp_[".to_string()], Severity::Error).is_err());
+    }
+
+    #[test]
+    fn points_at_statement() {
+        let src = "query = \"\"\"\n    SELECT 1;\n    DELETE FROM sessions;\n\"\"\"\n";
+        let found = diagnostics(MissingWhere::default(), src);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range.start.to_string(), "3:5:");
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+
+use finder::preanalysis::{LineCol, PreanalyzedFile, Range};
+use finder::{AnalyzeError, SqlString};
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::Statement;
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+
+mod custom;
+mod dead_sql;
+mod duplicate_columns;
+mod in_clause_join;
+mod insert_arity;
+mod interpolation;
+mod keyword_case;
+mod missing_where;
+mod param_count;
+mod unordered_limit;
+
+/// Code reported for strings that fail to parse. Produced by the analyzer itself rather than
+/// by a rule, since every other rule depends on the outcome of the parse.
+pub const SYNTAX_ERROR_CODE: &str = "SQ100";
+
+/// Code reported for matched variables and calls whose SQL couldn't be extracted, only when
+/// `report_unextractable` is set. There's nothing to parse or check for them.
+pub const UNEXTRACTABLE_CODE: &str = "SQ402";
+
+/// Code reported for `# sqint: dialect=...` pragmas naming a dialect sqint doesn't support,
+/// pointing at the pragma instead of a string.
+pub const INVALID_PRAGMA_CODE: &str = "SQ001";
+
+/// Code reported for files that couldn't be read or parsed at all, pointing at the file
+/// instead of a string so the failure shows up with the other findings.
+pub const FILE_ERROR_CODE: &str = "SQ002";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    pub file: String,
+    pub variable_name: String,
+    pub range: Range,
+    pub approximate: bool,
+    /// Edit of the source making the finding go away, for findings that have one.
+    pub fix: Option<Fix>,
+}
+
+/// Text replacing a byte range. Of the decoded source file in a `Diagnostic`, of the extracted
+/// content in a `Finding`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Fix {
+    /// This fix of the content of `sql` as one of the source, if the literal can be rewritten.
+    pub fn in_source(self, sql: &SqlString, source: &PreanalyzedFile) -> Option<Self> {
+        let range = sql.rewritable_range(source, self.start..self.end)?;
+        Some(Self {
+            start: range.start(),
+            end: range.end(),
+            replacement: self.replacement,
+        })
+    }
+
+    /// This fix of the source as one of the content of `sql`.
+    pub fn in_content(&self, sql: &SqlString) -> Option<Self> {
+        let start = sql.content_start?;
+        Some(Self {
+            start: self.start.checked_sub(start)?,
+            end: self.end.checked_sub(start)?,
+            replacement: self.replacement.clone(),
+        })
+    }
+}
+
+impl Diagnostic {
+    /// The finding for a file the finder failed on, at the failure or its first line.
+    pub fn for_file_error(file: String, error: &AnalyzeError) -> Self {
+        let (message, location) = match error {
+            AnalyzeError::Io { source, .. } => (format!("Failed to read file: {source}"), None),
+            AnalyzeError::PythonParse {
+                message, location, ..
+            } => (format!("Failed to parse Python: {message}"), Some(location)),
+            AnalyzeError::DataParse {
+                message, location, ..
+            } => (
+                format!("Failed to parse data file: {message}"),
+                Some(location),
+            ),
+            AnalyzeError::NotebookParse {
+                message, location, ..
+            } => (
+                format!("Failed to parse notebook: {message}"),
+                Some(location),
+            ),
+        };
+        let start = location.cloned().unwrap_or_else(|| LineCol::new(1, 1, 0));
+        Self {
+            code: FILE_ERROR_CODE.to_string(),
+            severity: Severity::Error,
+            message,
+            file,
+            variable_name: "file".to_string(),
+            range: Range {
+                end: start.clone(),
+                start,
+            },
+            approximate: false,
+            fix: None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let approximate = if self.approximate {
+            " (approximate)"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{}:{}:{}{}: {} {}",
+            self.file, self.range.start, self.variable_name, approximate, self.code, self.message
+        )
+    }
+}
+
+/// Everything a rule gets to look at for a single extracted string.
+pub struct RuleContext<'a> {
+    pub sql: &'a SqlString,
+    pub dialect: &'a dyn Dialect,
+    /// `None` when the string didn't parse, token based rules still run in that case.
+    pub statements: Option<&'a [Statement]>,
+    /// Markers of the configured paramstyle in the SQL, only collected for strings passed
+    /// along with literal parameters.
+    pub placeholders: &'a [&'a str],
+}
+
+impl RuleContext<'_> {
+    /// Byte offsets the statements of the extracted content start at, in the order of
+    /// `statements`. `None` when the content doesn't tokenize.
+    pub fn statement_starts(&self) -> Option<Vec<usize>> {
+        Some(
+            self.statement_tokens()?
+                .iter()
+                .map(|tokens| tokens[0].1)
+                .collect(),
+        )
+    }
+
+    /// Tokens of each statement of the extracted content, without the semicolons between them.
+    pub fn statement_tokens(&self) -> Option<Vec<Vec<(Token, usize)>>> {
+        let mut statements: Vec<Vec<(Token, usize)>> = vec![];
+        let mut at_start = true;
+        for (token, offset) in self.tokens()? {
+            match token {
+                Token::SemiColon => at_start = true,
+                Token::EOF => {}
+                _ if at_start => {
+                    statements.push(vec![(token, offset)]);
+                    at_start = false;
+                }
+                _ => {
+                    if let Some(statement) = statements.last_mut() {
+                        statement.push((token, offset));
+                    }
+                }
+            }
+        }
+        Some(statements)
+    }
+
+    /// Non whitespace tokens of the extracted content, paired with their byte offset.
+    pub fn tokens(&self) -> Option<Vec<(Token, usize)>> {
+        let sql = &self.sql.sql_content;
+        let tokens = Tokenizer::new(self.dialect, sql)
+            .tokenize_with_location()
+            .ok()?;
+
+        Some(
+            tokens
+                .into_iter()
+                .filter(|t| !matches!(t.token, Token::Whitespace(_)))
+                .map(|t| {
+                    let offset = byte_offset(sql, t.span.start);
+                    (t.token, offset)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// What a rule found in a string.
+pub struct Finding {
+    pub message: String,
+    /// Byte offset into the extracted content the finding starts at, the whole string is
+    /// reported when `None` or when the content can't be mapped back to the source.
+    pub offset: Option<usize>,
+    /// Dropped when the literal can't be rewritten in place.
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    pub const fn at(message: String, offset: usize) -> Self {
+        Self {
+            message,
+            offset: Some(offset),
+            fix: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+impl From<String> for Finding {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            offset: None,
+            fix: None,
+        }
+    }
+}
+
+pub trait Rule: Send + Sync {
+    fn code(&self) -> &str;
+    fn severity(&self) -> Severity;
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding>;
+}
+
+/// Built-in rules with their default settings.
+#[cfg(test)]
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    configured_builtin_rules(&crate::Config::default()).expect("default settings are valid")
+}
+
+/// Built-in rules with the settings of `config`.
+fn configured_builtin_rules(config: &crate::Config) -> Result<Vec<Box<dyn Rule>>, Vec<String>> {
+    let missing_where = missing_where::MissingWhere::new(
+        &config.missing_where_allowed_tables,
+        config.missing_where_severity,
+    )
+    .map_err(|e| vec![e])?;
+
+    Ok(vec![
+        Box::new(missing_where),
+        Box::new(unordered_limit::UnorderedLimit::new(
+            config.unordered_limit_severity,
+        )),
+        Box::new(insert_arity::InsertArity),
+        Box::new(duplicate_columns::DuplicateColumns::new(
+            config.duplicate_column_severity,
+        )),
+        Box::new(interpolation::InterpolatedSql),
+        Box::new(in_clause_join::InClauseJoin),
+        Box::new(param_count::ParamCount),
+        Box::new(dead_sql::DeadSql),
+        Box::new(keyword_case::KeywordCasing::new(config.keyword_case)),
+    ])
+}
+
+/// Built-in rules followed by the ones defined in `custom_rules` files.
+pub fn registry(config: &crate::Config) -> Result<Vec<Box<dyn Rule>>, Vec<String>> {
+    let mut rules = configured_builtin_rules(config)?;
+    let taken: Vec<&str> = [
+        INVALID_PRAGMA_CODE,
+        FILE_ERROR_CODE,
+        SYNTAX_ERROR_CODE,
+        UNEXTRACTABLE_CODE,
+    ]
+    .into_iter()
+    .chain(rules.iter().map(|r| r.code()))
+    .collect();
+    let custom = custom::load_custom_rules(&config.custom_rules, &taken)?;
+
+    rules.extend(custom.into_iter().map(|r| Box::new(r) as Box<dyn Rule>));
+    Ok(rules)
+}
+
+/// `n` followed by `word`, with an `s` unless there's one.
+pub(crate) fn plural(n: usize, word: &str) -> String {
+    format!("{n} {word}{}", if n == 1 { "" } else { "s" })
+}
+
+// Tokenizer locations are 1 based and count chars, not bytes.
+#[allow(clippy::cast_possible_truncation)]
+fn byte_offset(sql: &str, loc: Location) -> usize {
+    let line_start: usize = sql
+        .split_inclusive('\n')
+        .take((loc.line as usize).saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let line = &sql[line_start..];
+    line_start
+        + line
+            .char_indices()
+            .nth((loc.column as usize).saturating_sub(1))
+            .map_or(line.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use finder::preanalysis::PreanalyzedFile;
+    use finder::{FinderConfig, SqlFinder};
+
+    use super::{Diagnostic, Rule, builtin_rules};
+    use crate::analyzer::{SqlAnalyzer, SqlDialect};
+    use crate::config::ParamStyle;
+
+    pub fn finder_config() -> FinderConfig {
+        FinderConfig::new(
+            &["*query*".to_string(), "*sql*".to_string()],
+            &["execute".to_string()],
+            true,
+        )
+    }
+
+    pub fn codes_for(src: &str) -> Vec<String> {
+        codes_for_config(finder_config(), src)
+    }
+
+    pub fn codes_for_config(config: FinderConfig, src: &str) -> Vec<String> {
+        codes_for_rules(config, builtin_rules(), src)
+    }
+
+    pub fn codes_for_rules(
+        config: FinderConfig,
+        rules: Vec<Box<dyn Rule>>,
+        src: &str,
+    ) -> Vec<String> {
+        diagnostics_for(config, rules, src)
+            .into_iter()
+            .map(|d| d.code)
+            .collect()
+    }
+
+    /// Runs the finder and analyzer over `src` and returns every diagnostic.
+    pub fn diagnostics_for(
+        config: FinderConfig,
+        rules: Vec<Box<dyn Rule>>,
+        src: &str,
+    ) -> Vec<Diagnostic> {
+        diagnostics_for_paramstyle(config, rules, ParamStyle::default(), src)
+    }
+
+    pub fn diagnostics_for_paramstyle(
+        config: FinderConfig,
+        rules: Vec<Box<dyn Rule>>,
+        paramstyle: ParamStyle,
+        src: &str,
+    ) -> Vec<Diagnostic> {
+        let finder = SqlFinder::new(Arc::new(config));
+        let analyzer = SqlAnalyzer::new(
+            &SqlDialect::Generic,
+            HashMap::new(),
+            paramstyle,
+            &["?".to_string()],
+            Arc::new(rules),
+        );
+
+        let source = PreanalyzedFile::from_src(src);
+        finder
+            .analyze_python_src(src, "test.py")
+            .expect("test source should be analyzable")
+            .iter()
+            .flat_map(|s| analyzer.analyze_sql_string(s, &source, "test.py"))
+            .collect()
+    }
+}
@@ -0,0 +1,216 @@
+use finder::CallParams;
+
+use super::{Finding, Rule, RuleContext, Severity, plural};
+
+/// Placeholders that don't line up with the literal parameters passed along, like two `%s`
+/// with a single value or a `%(name)s` missing from the dict. The SQL parses fine, the
+/// driver fails when running it.
+pub struct ParamCount;
+
+impl Rule for ParamCount {
+    fn code(&self) -> &str {
+        "SQ303"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        // Runtime parts may hold placeholders of their own
+        let Some(params) = &ctx.sql.params else {
+            return vec![];
+        };
+        if !ctx.sql.dynamic_spans.is_empty() || ctx.sql.approximate {
+            return vec![];
+        }
+        let markers: Vec<Marker> = ctx.placeholders.iter().map(|p| Marker::parse(p)).collect();
+
+        match params {
+            CallParams::Rows(rows) => rows
+                .iter()
+                .enumerate()
+                .filter_map(|(i, row)| Some(format!("Row {}: {}", i + 1, mismatch(&markers, row)?)))
+                .map(Finding::from)
+                .collect(),
+            params => mismatch(&markers, params)
+                .into_iter()
+                .map(Finding::from)
+                .collect(),
+        }
+    }
+}
+
+enum Marker<'a> {
+    /// `?` or `%s`
+    Positional,
+    /// `$1` or `:1`
+    Numbered(usize),
+    /// `%(name)s` or `:name`
+    Named(&'a str),
+}
+
+impl<'a> Marker<'a> {
+    fn parse(marker: &'a str) -> Self {
+        let name = marker
+            .strip_prefix("%(")
+            .and_then(|rest| rest.split_once(')'))
+            .map(|(name, _)| name)
+            .or_else(|| marker.strip_prefix(':'))
+            .or_else(|| marker.strip_prefix('$'));
+        match name {
+            Some(name) => name.parse().map_or(Self::Named(name), Self::Numbered),
+            None => Self::Positional,
+        }
+    }
+}
+
+/// What's wrong with passing `params` to `markers`, if anything.
+fn mismatch(markers: &[Marker], params: &CallParams) -> Option<String> {
+    let names: Vec<&str> = markers
+        .iter()
+        .filter_map(|m| match m {
+            Marker::Named(name) => Some(*name),
+            _ => None,
+        })
+        .collect();
+    match params {
+        CallParams::Positional(count) => {
+            if !names.is_empty() {
+                return (names.len() == markers.len()).then(|| {
+                    format!(
+                        "Named placeholders need a dict of parameters, got {}",
+                        plural(*count, "value")
+                    )
+                });
+            }
+            // Numbered placeholders may repeat, `$1` twice is a single parameter
+            let expected = markers
+                .iter()
+                .map(|m| match m {
+                    Marker::Numbered(n) => *n,
+                    _ => 0,
+                })
+                .max()
+                .filter(|&n| n > 0)
+                .unwrap_or(markers.len());
+            (expected != *count).then(|| {
+                format!(
+                    "{} but {} passed",
+                    plural(expected, "placeholder"),
+                    plural(*count, "parameter")
+                )
+            })
+        }
+        CallParams::Named(keys) => {
+            if names.len() != markers.len() {
+                return None;
+            }
+            let mut missing: Vec<&str> = names
+                .into_iter()
+                .filter(|name| !keys.iter().any(|key| key == name))
+                .collect();
+            missing.dedup();
+            (!missing.is_empty()).then(|| {
+                format!(
+                    "No parameter passed for {}",
+                    missing
+                        .iter()
+                        .map(|name| format!("`{name}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+        }
+        CallParams::Rows(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ParamStyle;
+    use crate::rules::builtin_rules;
+    use crate::rules::test_utils::diagnostics_for_paramstyle;
+    use finder::FinderConfig;
+
+    fn messages(paramstyle: ParamStyle, src: &str) -> Vec<String> {
+        let config = FinderConfig::new(
+            &[],
+            &["execute".to_string(), "executemany".to_string()],
+            true,
+        );
+        diagnostics_for_paramstyle(config, builtin_rules(), paramstyle, src)
+            .into_iter()
+            .filter(|d| d.code == "SQ303")
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn qmark() {
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = ? AND b = ? AND c = '?'", (a,))"#;
+        assert_eq!(
+            messages(ParamStyle::Qmark, src),
+            vec!["2 placeholders but 1 parameter passed"]
+        );
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = ? AND b = ?", [a, b])"#;
+        assert!(messages(ParamStyle::Qmark, src).is_empty());
+    }
+
+    #[test]
+    fn format() {
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = %s AND b = %s", (x,))"#;
+        assert_eq!(
+            messages(ParamStyle::Format, src),
+            vec!["2 placeholders but 1 parameter passed"]
+        );
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a LIKE '100%%' AND b = %s", (x,))"#;
+        assert!(messages(ParamStyle::Format, src).is_empty());
+        // Unpacked values leave the count open
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = %s AND b = %s", (*pair,))"#;
+        assert!(messages(ParamStyle::Format, src).is_empty());
+    }
+
+    #[test]
+    fn named() {
+        let src =
+            r#"cursor.execute("SELECT * FROM t WHERE a = :a AND b = :b OR c = :a", {"a": 1})"#;
+        assert_eq!(
+            messages(ParamStyle::Named, src),
+            vec!["No parameter passed for `b`"]
+        );
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = :a AND b::text = :b", {"a": 1, "b": 2, "c": 3})"#;
+        assert!(messages(ParamStyle::Named, src).is_empty());
+
+        let src = r#"cursor.execute("SELECT * FROM t WHERE a = %(a)s", (1,))"#;
+        assert_eq!(
+            messages(ParamStyle::Pyformat, src),
+            vec!["Named placeholders need a dict of parameters, got 1 value"]
+        );
+    }
+
+    #[test]
+    fn numbered() {
+        let src =
+            r#"cursor.execute("SELECT * FROM t WHERE a = $1 OR b = $1 AND c = $2", (1, 2, 3))"#;
+        assert_eq!(
+            messages(ParamStyle::Dollar, src),
+            vec!["2 placeholders but 3 parameters passed"]
+        );
+    }
+
+    #[test]
+    fn executemany_rows() {
+        let src =
+            r#"cursor.executemany("INSERT INTO t (a, b) VALUES (?, ?)", [(1, 2), (3,), (4, 5)])"#;
+        assert_eq!(
+            messages(ParamStyle::Qmark, src),
+            vec!["Row 2: 2 placeholders but 1 parameter passed"]
+        );
+        let src = r#"cursor.executemany("INSERT INTO t (a) VALUES (%(a)s)", [{"a": 1}, {"b": 2}])"#;
+        assert_eq!(
+            messages(ParamStyle::Pyformat, src),
+            vec!["Row 2: No parameter passed for `a`"]
+        );
+    }
+}
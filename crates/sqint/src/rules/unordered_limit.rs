@@ -0,0 +1,164 @@
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{LimitClause, Query, SetExpr, Visit, Visitor};
+
+use super::{Finding, Rule, RuleContext, Severity};
+
+/// `LIMIT`, `OFFSET` or `FETCH FIRST` without an `ORDER BY` on the same query, which picks
+/// whatever rows the database comes across first. Pages of such a query can overlap or skip
+/// rows between calls. An `ORDER BY` of a subquery doesn't order the query around it.
+pub struct UnorderedLimit {
+    severity: Severity,
+}
+
+impl UnorderedLimit {
+    pub const fn new(severity: Severity) -> Self {
+        Self { severity }
+    }
+}
+
+impl Rule for UnorderedLimit {
+    fn code(&self) -> &str {
+        "SQ202"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, ctx: &RuleContext) -> Vec<Finding> {
+        let Some(statements) = ctx.statements else {
+            return vec![];
+        };
+        let starts = ctx.statement_starts();
+
+        let mut findings = vec![];
+        for (i, statement) in statements.iter().enumerate() {
+            let mut clauses = UnorderedClauses(vec![]);
+            let _ = statement.visit(&mut clauses);
+            for clause in clauses.0 {
+                let message = format!("{clause} without ORDER BY returns arbitrary rows");
+                findings.push(match starts.as_ref().and_then(|starts| starts.get(i)) {
+                    Some(&offset) => Finding::at(message, offset),
+                    None => message.into(),
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Row limiting clauses of every query and subquery that has no `ORDER BY`.
+struct UnorderedClauses(Vec<&'static str>);
+
+impl Visitor for UnorderedClauses {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if let Some(clause) = limiting_clause(query)
+            && !is_ordered(query)
+        {
+            self.0.push(clause);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn limiting_clause(query: &Query) -> Option<&'static str> {
+    match &query.limit_clause {
+        Some(LimitClause::LimitOffset { limit: Some(_), .. })
+        | Some(LimitClause::OffsetCommaLimit { .. }) => Some("LIMIT"),
+        Some(LimitClause::LimitOffset {
+            offset: Some(_), ..
+        }) => Some("OFFSET"),
+        _ => query.fetch.as_ref().map(|_| "FETCH FIRST"),
+    }
+}
+
+/// Whether the rows of `query` come out ordered, by its own `ORDER BY` or that of the
+/// parenthesized query it consists of, `(SELECT ... ORDER BY a) LIMIT 10`.
+fn is_ordered(query: &Query) -> bool {
+    query.order_by.is_some()
+        || matches!(query.body.as_ref(), SetExpr::Query(inner) if is_ordered(inner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_utils::{diagnostics_for, finder_config};
+
+    fn messages(src: &str) -> Vec<String> {
+        diagnostics_for(
+            finder_config(),
+            vec![Box::new(UnorderedLimit::new(Severity::Warning))],
+            src,
+        )
+        .into_iter()
+        .map(|d| d.message)
+        .collect()
+    }
+
+    #[test]
+    fn top_level_queries() {
+        assert_eq!(
+            messages(r#"query = "SELECT id FROM users LIMIT 10 OFFSET 20""#),
+            vec!["LIMIT without ORDER BY returns arbitrary rows"]
+        );
+        assert_eq!(
+            messages(r#"query = "SELECT id FROM users OFFSET 20 ROWS""#),
+            vec!["OFFSET without ORDER BY returns arbitrary rows"]
+        );
+        assert_eq!(
+            messages(r#"query = "SELECT id FROM users FETCH FIRST 10 ROWS ONLY""#),
+            vec!["FETCH FIRST without ORDER BY returns arbitrary rows"]
+        );
+        for src in [
+            r#"query = "SELECT id FROM users ORDER BY id LIMIT 10 OFFSET 20""#,
+            r#"query = "SELECT id FROM users ORDER BY id FETCH FIRST 10 ROWS ONLY""#,
+            r#"query = "SELECT id FROM users""#,
+        ] {
+            assert!(messages(src).is_empty(), "{src}");
+        }
+    }
+
+    #[test]
+    fn subqueries() {
+        // The ORDER BY of the subquery doesn't carry over to the outer LIMIT
+        assert_eq!(
+            messages(r#"query = "SELECT * FROM (SELECT id FROM users ORDER BY id) AS u LIMIT 10""#),
+            vec!["LIMIT without ORDER BY returns arbitrary rows"]
+        );
+        assert_eq!(
+            messages(
+                r#"query = "SELECT id, (SELECT at FROM logins WHERE user_id = id LIMIT 1) FROM users ORDER BY id""#
+            ),
+            vec!["LIMIT without ORDER BY returns arbitrary rows"]
+        );
+        assert!(messages(r#"query = "(SELECT id FROM users ORDER BY id) LIMIT 10""#).is_empty());
+    }
+
+    #[test]
+    fn union_queries() {
+        // The ORDER BY applies to the whole union
+        assert!(
+            messages(r#"query = "SELECT id FROM a UNION SELECT id FROM b ORDER BY id LIMIT 10""#)
+                .is_empty()
+        );
+        assert_eq!(
+            messages(r#"query = "SELECT id FROM a UNION ALL SELECT id FROM b LIMIT 10""#),
+            vec!["LIMIT without ORDER BY returns arbitrary rows"]
+        );
+        assert_eq!(
+            messages(
+                r#"query = "(SELECT id FROM a LIMIT 5) UNION (SELECT id FROM b ORDER BY id LIMIT 5)""#
+            ),
+            vec!["LIMIT without ORDER BY returns arbitrary rows"]
+        );
+    }
+
+    #[test]
+    fn suppressed_by_pragma() {
+        let src = "query = \"SELECT id FROM users LIMIT 1\"  # sqint: ignore[SQ202]\n";
+        assert!(messages(src).is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A directory of its own for each test, the project root `sqint` is run in.
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("sqint_compact_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    for (path, content) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    root
+}
+
+fn run_compact(root: &Path) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_sqint"))
+        .args(["check", "--format", "compact", "."])
+        .current_dir(root)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(root).unwrap();
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn clean_run_is_silent_and_succeeds() {
+    let output = run_compact(&project(
+        "clean",
+        &[("app/db.py", "query = \"SELECT id FROM users\"\n")],
+    ));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn failing_file_fails_the_run() {
+    let output = run_compact(&project(
+        "failing",
+        &[
+            ("app/db.py", "query = \"SELEC id FROM users\"\n"),
+            ("app/clean.py", "query = \"SELECT 1\"\n"),
+        ],
+    ));
+    let expected = format!(
+        "{}: 1 SQL error (first: line 1 SQ100 `SELEC id FROM users` => Expected: an SQL statement, found: SELEC)\n",
+        Path::new("app").join("db.py").display()
+    );
+    assert_eq!(stdout(&output), expected);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn unparseable_file_named_like_the_others() {
+    let output = run_compact(&project(
+        "file_error",
+        &[
+            (
+                "sqint.toml",
+                "raw_sql_file_patterns = [\"*.sql\", \"*.yaml\"]\n\n[sql_in_data_files]\n\"*.yaml\" = [\"query\"]\n",
+            ),
+            ("data/reports.yaml", "query: [unclosed\n"),
+        ],
+    ));
+    let prefix = format!(
+        "{}: 1 SQL error",
+        Path::new("data").join("reports.yaml").display()
+    );
+    assert!(stdout(&output).starts_with(&prefix), "{}", stdout(&output));
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn overrides_match_project_paths() {
+    let output = run_compact(&project(
+        "overrides",
+        &[
+            (
+                "sqint.toml",
+                "[[overrides]]\npath = \"legacy/**\"\nvariable_contexts = [\"*raw*\"]\n",
+            ),
+            ("legacy/db.py", "query = \"SELEC id FROM users\"\n"),
+        ],
+    ));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn ignored_codes_not_reported() {
+    let output = run_compact(&project(
+        "ignore",
+        &[
+            ("sqint.toml", "ignore = [\"SQ1\"]\n"),
+            ("app/db.py", "query = \"SELEC id FROM users\"\n"),
+        ],
+    ));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(output.status.code(), Some(0));
+}